@@ -1 +1,721 @@
+use crate::game::GameVersion;
+use crate::http_cache::{Cached, CachedResponse, HttpCache};
+use crate::launcher_settings::{CacheSettings, RequestSettings};
+use crate::mods::ModInfo;
+use crate::throttle::{Limiter, backoff};
+use log::{debug, error, warn};
+use reqwest::header::{
+    ETAG, HeaderMap, HeaderValue, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, RETRY_AFTER,
+};
+use reqwest::{Client, Request, RequestBuilder, StatusCode};
+use serde::Deserialize;
+use std::fmt;
+use std::time::Duration;
 
+pub const MODDB_URL: &str = "https://mods.vintagestory.at";
+/// Lines are wrapped by the GUI, not while converting HTML.
+const TEXT_WIDTH: usize = 1000;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ModDbMod {
+    #[serde(rename = "modid")]
+    pub id: u64,
+    pub name: String,
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub author: String,
+    #[serde(default)]
+    pub urlalias: Option<String>,
+    #[serde(default)]
+    pub downloads: u64,
+    #[serde(default)]
+    pub side: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub releases: Vec<Release>,
+    #[serde(default)]
+    pub screenshots: Vec<Screenshot>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Screenshot {
+    #[serde(rename = "mainfile")]
+    pub url: String,
+    #[serde(default)]
+    pub filename: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Release {
+    #[serde(rename = "releaseid")]
+    pub id: u64,
+    #[serde(rename = "mainfile")]
+    pub url: String,
+    pub filename: String,
+    #[serde(rename = "modidstr", default)]
+    pub mod_id: String,
+    #[serde(rename = "modversion")]
+    pub version: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub created: String,
+    /// HTML, see [`html_to_markdown`].
+    #[serde(default)]
+    pub changelog: String,
+}
+
+/// Search result entry, without description and releases.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ModSummary {
+    #[serde(rename = "modid")]
+    pub id: u64,
+    pub name: String,
+    #[serde(default)]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub author: String,
+    #[serde(rename = "modidstrs", default)]
+    pub mod_ids: Vec<String>,
+    #[serde(default)]
+    pub urlalias: Option<String>,
+    #[serde(default)]
+    pub downloads: u64,
+    #[serde(default)]
+    pub side: String,
+    #[serde(default)]
+    pub logo: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(rename = "lastreleased", default)]
+    pub last_released: String,
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+pub struct Tag {
+    #[serde(rename = "tagid")]
+    pub id: u64,
+    pub name: String,
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    #[default]
+    Downloads,
+    Trending,
+    Updated,
+}
+
+impl SortOrder {
+    pub const ALL: [SortOrder; 3] = [
+        SortOrder::Downloads,
+        SortOrder::Trending,
+        SortOrder::Updated,
+    ];
+
+    fn order_by(self) -> &'static str {
+        match self {
+            SortOrder::Downloads => "downloads",
+            SortOrder::Trending => "trendingpoints",
+            SortOrder::Updated => "lastreleased",
+        }
+    }
+}
+
+impl fmt::Display for SortOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SortOrder::Downloads => write!(f, "Most downloads"),
+            SortOrder::Trending => write!(f, "Trending"),
+            SortOrder::Updated => write!(f, "Recently updated"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    pub text: String,
+    /// Mods need all of the tags.
+    pub tags: Vec<Tag>,
+    pub sort: SortOrder,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModResponse {
+    #[serde(rename = "mod")]
+    mod_db_mod: Option<ModDbMod>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModsResponse {
+    #[serde(default)]
+    mods: Vec<ModSummary>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TagsResponse {
+    #[serde(default)]
+    tags: Vec<Tag>,
+}
+
+#[derive(Debug)]
+pub enum ApiError {
+    Request(reqwest::Error),
+    Status(StatusCode),
+    ParseError(serde_json::Error),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Request(e) => write!(f, "request failed: {}", e),
+            ApiError::Status(status) => write!(f, "ModDB answered with {}", status),
+            ApiError::ParseError(e) => write!(f, "invalid ModDB response: {}", e),
+            ApiError::Io(e) => write!(f, "failed writing download: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ApiError::Request(e) => Some(e),
+            ApiError::Status(..) => None,
+            ApiError::ParseError(e) => Some(e),
+            ApiError::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(e: reqwest::Error) -> Self {
+        ApiError::Request(e)
+    }
+}
+
+impl Release {
+    /// Game versions from the release tags, e.g. `v1.20.4`.
+    pub fn game_versions(&self) -> Vec<GameVersion> {
+        self.tags.iter().flat_map(|tag| tag.parse()).collect()
+    }
+
+    /// Releases are tagged with exact versions, but patches of the same major release are
+    /// compatible with each other.
+    pub fn supports(&self, game_version: &GameVersion) -> bool {
+        self.game_versions()
+            .iter()
+            .any(|v| (v.major, v.minor) == (game_version.major, game_version.minor))
+    }
+}
+
+impl ModSummary {
+    /// Whether one of the mod ids of this ModDB entry is installed.
+    pub fn is_installed(&self, mods: &[ModInfo]) -> bool {
+        mods.iter().any(|mod_info| {
+            self.mod_ids
+                .iter()
+                .any(|mod_id| mod_id.eq_ignore_ascii_case(&mod_info.mod_id))
+        })
+    }
+}
+
+impl ModDbMod {
+    /// `major.minor` game versions any release supports, oldest first.
+    pub fn supported_versions(&self) -> Vec<String> {
+        let mut versions: Vec<(u32, u32)> = self
+            .releases
+            .iter()
+            .flat_map(|release| release.game_versions())
+            .map(|version| (version.major, version.minor))
+            .collect();
+        versions.sort();
+        versions.dedup();
+
+        versions
+            .into_iter()
+            .map(|(major, minor)| format!("{}.{}", major, minor))
+            .collect()
+    }
+
+    /// Newest release for the game version, or the newest release at all if it is unknown.
+    pub fn latest_release(&self, game_version: Option<&GameVersion>) -> Option<&Release> {
+        // the API lists releases newest first
+        self.releases
+            .iter()
+            .find(|release| game_version.is_none_or(|version| release.supports(version)))
+    }
+}
+
+/// ModDB descriptions and changelogs are HTML, the GUI renders them as Markdown. Links become
+/// reference links, so the text stays readable where it is shown as is.
+pub fn html_to_markdown(html: &str) -> String {
+    match html2text::from_read(html.as_bytes(), TEXT_WIDTH) {
+        Ok(text) => text.trim_end().to_string(),
+        Err(e) => {
+            debug!("failed converting HTML: {}", e);
+            html.to_string()
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ModDb {
+    client: Client,
+    base_url: String,
+    cache: Option<HttpCache>,
+    limits: RequestSettings,
+    limiter: Limiter,
+}
+
+/// Complete answer of ModDB, read while the request slot is held.
+struct RawResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+impl Default for ModDb {
+    fn default() -> Self {
+        Self::new(MODDB_URL)
+    }
+}
+
+/// Answer of the cache when ModDB cannot be reached.
+fn stale_fallback(
+    cached: Option<&CachedResponse>,
+    url: &str,
+    e: ApiError,
+) -> Result<Option<Cached<Vec<u8>>>, ApiError> {
+    match cached {
+        Some(response) => {
+            warn!("using cached {}: {}", url, e);
+            Ok(Some(Cached {
+                value: response.body.clone(),
+                stale: true,
+            }))
+        }
+        None => Err(e),
+    }
+}
+
+fn client(limits: &RequestSettings) -> Client {
+    Client::builder()
+        .timeout(limits.timeout)
+        .build()
+        .expect("TLS backend initializes")
+}
+
+impl ModDb {
+    pub fn new(base_url: &str) -> ModDb {
+        let limits = RequestSettings::default();
+        Self {
+            client: client(&limits),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            cache: None,
+            limiter: Limiter::new(&limits),
+            limits,
+        }
+    }
+
+    /// Replaces the default limits, clones made before keep the old ones.
+    pub fn with_limits(self, limits: &RequestSettings) -> ModDb {
+        Self {
+            client: client(limits),
+            limits: limits.clone(),
+            limiter: Limiter::new(limits),
+            ..self
+        }
+    }
+
+    pub fn with_cache(self, cache: HttpCache) -> ModDb {
+        Self {
+            cache: Some(cache),
+            ..self
+        }
+    }
+
+    /// Relative URLs are resolved against ModDB.
+    fn url(&self, url: &str) -> String {
+        if url.starts_with("http") {
+            url.to_string()
+        } else {
+            format!("{}/{}", self.base_url, url.trim_start_matches('/'))
+        }
+    }
+
+    /// Sends the request within the limits, throttled (429) and failed (5xx) requests and
+    /// timeouts are retried. `Retry-After` is honored up to the maximum backoff.
+    async fn fetch(&self, request: Request) -> Result<RawResponse, ApiError> {
+        let mut attempt = 0;
+        loop {
+            let request = request
+                .try_clone()
+                .expect("GET requests have no streamed body");
+            let url = request.url().clone();
+            let permit = self.limiter.acquire().await;
+
+            let result = match self.client.execute(request).await {
+                Ok(response) => {
+                    let (status, headers) = (response.status(), response.headers().clone());
+                    response.bytes().await.map(|body| RawResponse {
+                        status,
+                        headers,
+                        body: body.to_vec(),
+                    })
+                }
+                Err(e) => Err(e),
+            };
+            drop(permit);
+
+            let retry = match &result {
+                Ok(response) => {
+                    response.status == StatusCode::TOO_MANY_REQUESTS
+                        || response.status.is_server_error()
+                }
+                Err(e) => e.is_timeout(),
+            };
+            if !retry || attempt >= self.limits.max_retries {
+                return Ok(result?);
+            }
+
+            let retry_after = result.as_ref().ok().and_then(|response| {
+                let seconds = response.headers.get(RETRY_AFTER)?.to_str().ok()?;
+                Some(Duration::from_secs(seconds.parse().ok()?).min(self.limits.max_backoff))
+            });
+            let delay = retry_after.unwrap_or_else(|| backoff(&self.limits, attempt));
+            match &result {
+                Ok(response) => warn!(
+                    "{} answered {}, retrying in {:?}",
+                    url, response.status, delay
+                ),
+                Err(e) => warn!("{} failed: {}, retrying in {:?}", url, e, delay),
+            }
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Sends the request unless the cache has a fresh answer, `None` if ModDB answers 404.
+    async fn get(
+        &self,
+        request: RequestBuilder,
+        ttl: fn(&CacheSettings) -> Duration,
+    ) -> Result<Option<Cached<Vec<u8>>>, ApiError> {
+        let mut request = request.build()?;
+        let url = request.url().to_string();
+
+        let cached = self.cache.as_ref().and_then(|cache| cache.load(&url));
+        if let (Some(cache), Some(response)) = (&self.cache, &cached) {
+            if response.is_fresh(ttl(&cache.settings)) {
+                debug!("cached {}", url);
+                return Ok(Some(Cached {
+                    value: response.body.clone(),
+                    stale: false,
+                }));
+            }
+
+            let headers = request.headers_mut();
+            if let Some(etag) = response.etag().and_then(|v| HeaderValue::from_str(v).ok()) {
+                headers.insert(IF_NONE_MATCH, etag);
+            }
+            if let Some(date) = response
+                .last_modified()
+                .and_then(|v| HeaderValue::from_str(v).ok())
+            {
+                headers.insert(IF_MODIFIED_SINCE, date);
+            }
+        }
+        debug!("GET {}", url);
+
+        let response = match self.fetch(request).await {
+            Ok(response) => response,
+            Err(e) => return stale_fallback(cached.as_ref(), &url, e),
+        };
+        let status = response.status;
+        if status == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if let (StatusCode::NOT_MODIFIED, Some(cache), Some(cached)) =
+            (status, &self.cache, &cached)
+        {
+            debug!("not modified {}", url);
+            if let Err(e) = cache.touch(&url, cached) {
+                error!("failed updating cache of {}: {}", url, e);
+            }
+            return Ok(Some(Cached {
+                value: cached.body.clone(),
+                stale: false,
+            }));
+        }
+        if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+            return stale_fallback(cached.as_ref(), &url, ApiError::Status(status));
+        }
+        if !status.is_success() {
+            return Err(ApiError::Status(status));
+        }
+
+        let header = |name| {
+            response
+                .headers
+                .get(name)
+                .and_then(|value: &HeaderValue| value.to_str().ok())
+                .map(String::from)
+        };
+        let (etag, last_modified) = (header(ETAG), header(LAST_MODIFIED));
+        if let Some(cache) = &self.cache
+            && let Err(e) = cache.store(&url, etag, last_modified, &response.body)
+        {
+            error!("failed caching {}: {}", url, e);
+        }
+
+        Ok(Some(Cached {
+            value: response.body,
+            stale: false,
+        }))
+    }
+
+    /// Looks up a mod by its mod id (`modid` in modinfo.json), `None` if ModDB does not know it.
+    pub async fn get_mod(&self, mod_id: &str) -> Result<Option<ModDbMod>, ApiError> {
+        Ok(self.get_mod_cached(mod_id).await?.value)
+    }
+
+    /// Like [`ModDb::get_mod`], tells whether the answer came from the cache while offline.
+    pub async fn get_mod_cached(&self, mod_id: &str) -> Result<Cached<Option<ModDbMod>>, ApiError> {
+        let request = self
+            .client
+            .get(format!("{}/api/mod/{}", self.base_url, mod_id));
+
+        match self.get(request, |settings| settings.mods_ttl).await? {
+            Some(cached) => {
+                let response: ModResponse =
+                    serde_json::from_slice(&cached.value).map_err(ApiError::ParseError)?;
+                Ok(cached.map(|_| response.mod_db_mod))
+            }
+            None => Ok(Cached {
+                value: None,
+                stale: false,
+            }),
+        }
+    }
+
+    /// Mods matching the query, ModDB returns all of them at once.
+    pub async fn search(&self, query: &SearchQuery) -> Result<Cached<Vec<ModSummary>>, ApiError> {
+        let mut params = vec![
+            ("orderby", query.sort.order_by().to_string()),
+            ("orderdirection", "desc".to_string()),
+        ];
+        if !query.text.trim().is_empty() {
+            params.push(("text", query.text.trim().to_string()));
+        }
+        params.extend(
+            query
+                .tags
+                .iter()
+                .map(|tag| ("tagids[]", tag.id.to_string())),
+        );
+        let request = self
+            .client
+            .get(format!("{}/api/mods", self.base_url))
+            .query(&params);
+
+        let cached = self
+            .get(request, |settings| settings.search_ttl)
+            .await?
+            .ok_or(ApiError::Status(StatusCode::NOT_FOUND))?;
+        let response: ModsResponse =
+            serde_json::from_slice(&cached.value).map_err(ApiError::ParseError)?;
+        Ok(cached.map(|_| response.mods))
+    }
+
+    pub async fn tags(&self) -> Result<Vec<Tag>, ApiError> {
+        let request = self.client.get(format!("{}/api/tags", self.base_url));
+
+        let cached = self
+            .get(request, |settings| settings.search_ttl)
+            .await?
+            .ok_or(ApiError::Status(StatusCode::NOT_FOUND))?;
+        let response: TagsResponse =
+            serde_json::from_slice(&cached.value).map_err(ApiError::ParseError)?;
+        Ok(response.tags)
+    }
+
+    /// Mod files come from the download cache if they were downloaded before.
+    pub async fn download(&self, release: &Release) -> Result<Vec<u8>, ApiError> {
+        let url = self.url(&release.url);
+        if let Some(data) = self.cache.as_ref().and_then(|c| c.load_download(&url)) {
+            debug!("cached download {}", url);
+            return Ok(data);
+        }
+        debug!("GET {}", url);
+
+        let response = self.fetch(self.client.get(&url).build()?).await?;
+        if !response.status.is_success() {
+            return Err(ApiError::Status(response.status));
+        }
+        let data = response.body;
+
+        if let Some(cache) = &self.cache
+            && let Err(e) = cache.store_download(&url, &data)
+        {
+            error!("failed caching {}: {}", url, e);
+        }
+        Ok(data)
+    }
+
+    /// Logos and screenshots.
+    pub async fn get_file(&self, url: &str) -> Result<Vec<u8>, ApiError> {
+        let request = self.client.get(self.url(url));
+
+        Ok(self
+            .get(request, |settings| settings.files_ttl)
+            .await?
+            .ok_or(ApiError::Status(StatusCode::NOT_FOUND))?
+            .value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use tempfile::tempdir;
+
+    const MOD_JSON: &str = r#"{"mod": {"modid": 1, "name": "Test", "releases": []}}"#;
+
+    fn response(status: &str, headers: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n{}\r\n{}",
+            status,
+            body.len(),
+            headers,
+            body
+        )
+    }
+
+    /// Answers one connection per response and records the request heads, then stops
+    /// listening.
+    fn mock_server(responses: Vec<String>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let recorded = requests.clone();
+        thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut head = Vec::new();
+                let mut buffer = [0; 1024];
+                while !head.ends_with(b"\r\n\r\n") {
+                    let read = stream.read(&mut buffer).unwrap();
+                    if read == 0 {
+                        break;
+                    }
+                    head.extend_from_slice(&buffer[..read]);
+                }
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&head).to_lowercase());
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        (base_url, requests)
+    }
+
+    fn fast_retries(max_retries: u32) -> RequestSettings {
+        RequestSettings {
+            max_retries,
+            backoff: Duration::from_millis(1),
+            ..RequestSettings::default()
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn retries_throttled_requests() {
+        let (base_url, requests) = mock_server(vec![
+            response("429 Too Many Requests", "Retry-After: 0\r\n", ""),
+            response("503 Service Unavailable", "", ""),
+            response("200 OK", "", MOD_JSON),
+        ]);
+        let moddb = ModDb::new(&base_url).with_limits(&fast_retries(4));
+
+        let mod_db_mod = block_on(moddb.get_mod("test")).unwrap().unwrap();
+        assert_eq!(mod_db_mod.name, "Test");
+        assert_eq!(requests.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn gives_up_after_retries() {
+        let throttled = response("429 Too Many Requests", "", "");
+        let (base_url, requests) = mock_server(vec![throttled; 3]);
+        let moddb = ModDb::new(&base_url).with_limits(&fast_retries(2));
+
+        match block_on(moddb.get_mod("test")) {
+            Err(ApiError::Status(StatusCode::TOO_MANY_REQUESTS)) => {}
+            result => panic!("unexpected {:?}", result),
+        }
+        assert_eq!(requests.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn revalidates_and_falls_back_to_cache() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        let (base_url, requests) = mock_server(vec![
+            response("200 OK", "ETag: \"v1\"\r\n", MOD_JSON),
+            response("304 Not Modified", "ETag: \"v1\"\r\n", ""),
+        ]);
+        let settings = CacheSettings {
+            mods_ttl: Duration::ZERO,
+            ..CacheSettings::default()
+        };
+        let moddb = ModDb::new(&base_url)
+            .with_limits(&fast_retries(0))
+            .with_cache(HttpCache::new(root, &settings));
+
+        let first = block_on(moddb.get_mod_cached("test")).unwrap();
+        assert!(!first.stale);
+        let second = block_on(moddb.get_mod_cached("test")).unwrap();
+        assert!(!second.stale);
+        assert_eq!(second.value.unwrap().name, "Test");
+        assert!(requests.lock().unwrap()[1].contains("if-none-match: \"v1\""));
+
+        // the server is gone
+        let offline = block_on(moddb.get_mod_cached("test")).unwrap();
+        assert!(offline.stale);
+        assert_eq!(offline.value.unwrap().name, "Test");
+    }
+
+    #[test]
+    fn html_description() {
+        let markdown = html_to_markdown(
+            "<p>Adds <strong>more</strong> <a href=\"https://example.com/ores\">ores</a>.</p>\
+             <ul><li>Copper</li><li>Tin</li></ul>",
+        );
+        assert_eq!(
+            markdown,
+            "Adds **more** [ores][1].\n* Copper\n* Tin\n\n[1]: https://example.com/ores"
+        );
+    }
+}
@@ -0,0 +1,54 @@
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// Zips the contents of `dir` into `zip_path`, paths inside the zip are relative to `dir`.
+pub fn zip_dir(dir: &Path, zip_path: &Path) -> io::Result<()> {
+    zip_dir_into(dir, File::create(zip_path)?)
+}
+
+/// Like [`zip_dir`], into a file that is already open.
+pub fn zip_dir_into(dir: &Path, file: File) -> io::Result<()> {
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    add_dir(&mut zip, dir, dir, options)?;
+    zip.finish()?;
+
+    Ok(())
+}
+
+fn add_dir(
+    zip: &mut ZipWriter<File>,
+    root: &Path,
+    dir: &Path,
+    options: SimpleFileOptions,
+) -> io::Result<()> {
+    for entry in fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+        let name = path
+            .strip_prefix(root)
+            .map_err(io::Error::other)?
+            .to_string_lossy()
+            .replace('\\', "/");
+
+        if path.is_dir() {
+            zip.add_directory(name, options)?;
+            add_dir(zip, root, &path, options)?;
+        } else {
+            zip.start_file(name, options)?;
+            io::copy(&mut File::open(&path)?, zip)?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn unzip(zip_path: &Path, dir: &Path) -> io::Result<()> {
+    let mut zip = ZipArchive::new(File::open(zip_path)?)?;
+    zip.extract(dir)?;
+
+    Ok(())
+}
@@ -0,0 +1,400 @@
+use crate::archive::{unzip, zip_dir_into};
+use crate::instance::Instance;
+use crate::worlds::SAVES_FOLDER;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Shared with the game, which puts its own `.vcdbs` backups there.
+pub const BACKUPS_FOLDER: &str = "Backups";
+const BACKUP_PREFIX: &str = "saves-";
+
+const DAY: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupSchedule {
+    Daily,
+    Weekly,
+}
+
+impl BackupSchedule {
+    fn interval(&self) -> Duration {
+        match self {
+            BackupSchedule::Daily => Duration::from_secs(DAY),
+            BackupSchedule::Weekly => Duration::from_secs(7 * DAY),
+        }
+    }
+}
+
+/// Backup settings of an instance, all counts at 0 keep every backup.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BackupSettings {
+    pub before_launch: bool,
+    /// Any change of the `Mods` folder: updates, installs, syncs, imports and roll backs.
+    pub before_mod_update: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<BackupSchedule>,
+    /// Newest backups to keep.
+    pub keep_last: usize,
+    /// Days to keep the newest backup of.
+    pub keep_daily: usize,
+    /// Weeks to keep the newest backup of.
+    pub keep_weekly: usize,
+}
+
+impl BackupSettings {
+    pub fn is_default(&self) -> bool {
+        *self == BackupSettings::default()
+    }
+
+    fn keeps_all(&self) -> bool {
+        self.keep_last == 0 && self.keep_daily == 0 && self.keep_weekly == 0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackupTrigger {
+    Launch,
+    ModUpdate,
+    Schedule,
+}
+
+/// Zip of the `Saves` folder, named after its UTC creation time.
+#[derive(Debug, Clone)]
+pub struct Backup {
+    pub path: PathBuf,
+    pub created: SystemTime,
+    /// Tells backups made in the same second apart, the first has 1.
+    pub number: u32,
+    pub size: u64,
+}
+
+impl Backup {
+    /// Later backups of the same second get `-<number>` appended.
+    fn file_name(created: SystemTime, number: u32) -> String {
+        // colons are not allowed on Windows
+        let timestamp = humantime::format_rfc3339_seconds(created)
+            .to_string()
+            .replace(':', "-");
+        match number {
+            0 | 1 => format!("{}{}.zip", BACKUP_PREFIX, timestamp),
+            _ => format!("{}{}-{}.zip", BACKUP_PREFIX, timestamp, number),
+        }
+    }
+
+    fn parse_file_name(file_name: &str) -> Option<(SystemTime, u32)> {
+        let name = file_name
+            .strip_prefix(BACKUP_PREFIX)?
+            .strip_suffix(".zip")?;
+        // 2024-01-31T12-30-00Z with an optional -2
+        if name.len() < 20 || !name.is_ascii() {
+            return None;
+        }
+        let (timestamp, number) = name.split_at(20);
+        let number = match number.strip_prefix('-') {
+            Some(number) => number.parse().ok().filter(|number| *number > 1)?,
+            None if number.is_empty() => 1,
+            None => return None,
+        };
+        let timestamp = format!(
+            "{}:{}:{}",
+            &timestamp[..13],
+            &timestamp[14..16],
+            &timestamp[17..]
+        );
+        Some((humantime::parse_rfc3339(&timestamp).ok()?, number))
+    }
+
+    pub fn name(&self) -> String {
+        let created = humantime::format_rfc3339_seconds(self.created);
+        match self.number {
+            0 | 1 => created.to_string(),
+            number => format!("{} ({})", created, number),
+        }
+    }
+}
+
+fn since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn day(time: SystemTime) -> u64 {
+    since_epoch(time) / DAY
+}
+
+fn week(time: SystemTime) -> u64 {
+    // 1970-01-01 was a Thursday, weeks start on Monday
+    (day(time) + 3) / 7
+}
+
+/// All backups made by Rustic, newest first.
+pub fn list_backups(data_path: &Path) -> io::Result<Vec<Backup>> {
+    let backups_path = data_path.join(BACKUPS_FOLDER);
+    if !backups_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(backups_path)?.flatten() {
+        let Some((created, number)) = Backup::parse_file_name(&entry.file_name().to_string_lossy())
+        else {
+            continue;
+        };
+        backups.push(Backup {
+            path: entry.path(),
+            created,
+            number,
+            size: entry.metadata()?.len(),
+        });
+    }
+    backups.sort_by_key(|backup| Reverse((backup.created, backup.number)));
+
+    Ok(backups)
+}
+
+/// Marks the backups to keep, `backups` must be sorted newest first.
+pub fn retained(backups: &[Backup], settings: &BackupSettings) -> Vec<bool> {
+    if settings.keeps_all() {
+        return vec![true; backups.len()];
+    }
+
+    let mut keep = vec![false; backups.len()];
+    keep.iter_mut()
+        .take(settings.keep_last)
+        .for_each(|keep| *keep = true);
+    keep_newest_per_period(backups, settings.keep_daily, day, &mut keep);
+    keep_newest_per_period(backups, settings.keep_weekly, week, &mut keep);
+
+    keep
+}
+
+fn keep_newest_per_period(
+    backups: &[Backup],
+    count: usize,
+    period: fn(SystemTime) -> u64,
+    keep: &mut [bool],
+) {
+    let mut periods = Vec::new();
+    for (index, backup) in backups.iter().enumerate() {
+        if periods.len() == count {
+            break;
+        }
+        let backup_period = period(backup.created);
+        if periods.last() != Some(&backup_period) {
+            periods.push(backup_period);
+            keep[index] = true;
+        }
+    }
+}
+
+/// Deletes backups outside the retention policy, returns how many were deleted.
+pub fn prune_backups(data_path: &Path, settings: &BackupSettings) -> io::Result<usize> {
+    let backups = list_backups(data_path)?;
+    let keep = retained(&backups, settings);
+
+    let mut deleted = 0;
+    for (backup, keep) in backups.iter().zip(keep) {
+        if !keep {
+            debug!("deleting backup {}", backup.path.display());
+            fs::remove_file(&backup.path)?;
+            deleted += 1;
+        }
+    }
+
+    Ok(deleted)
+}
+
+/// Zips the `Saves` folder and applies the retention policy, `None` without saves.
+pub fn back_up(instance: &Instance) -> io::Result<Option<Backup>> {
+    let backup = create_backup(instance)?;
+    prune_backups(&instance.path(), &instance.backups)?;
+    Ok(backup)
+}
+
+fn create_backup(instance: &Instance) -> io::Result<Option<Backup>> {
+    let data_path = instance.path();
+    let saves_path = data_path.join(SAVES_FOLDER);
+    if !saves_path.is_dir() {
+        debug!("no saves to back up in {}", instance.folder_name_string());
+        return Ok(None);
+    }
+
+    let backups_path = data_path.join(BACKUPS_FOLDER);
+    fs::create_dir_all(&backups_path)?;
+
+    // backups before a launch and a mod update can happen in the same second
+    let created = SystemTime::now();
+    let mut number = 1;
+    let (path, file) = loop {
+        let path = backups_path.join(Backup::file_name(created, number));
+        match File::create_new(&path) {
+            Ok(file) => break (path, file),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => number += 1,
+            Err(e) => return Err(e),
+        }
+    };
+    info!(
+        "backing up saves of {} to {}",
+        instance.name,
+        path.display()
+    );
+    zip_dir_into(&saves_path, file)?;
+
+    Ok(Some(Backup {
+        size: fs::metadata(&path)?.len(),
+        path,
+        created,
+        number,
+    }))
+}
+
+/// Backs up if the instance settings ask for it on `trigger`.
+pub fn back_up_on(instance: &Instance, trigger: BackupTrigger) -> io::Result<Option<Backup>> {
+    let settings = &instance.backups;
+    let enabled = match trigger {
+        BackupTrigger::Launch => settings.before_launch,
+        BackupTrigger::ModUpdate => settings.before_mod_update,
+        BackupTrigger::Schedule => match settings.schedule {
+            Some(schedule) => is_due(
+                schedule,
+                list_backups(&instance.path())?.first(),
+                SystemTime::now(),
+            ),
+            None => false,
+        },
+    };
+
+    if enabled { back_up(instance) } else { Ok(None) }
+}
+
+fn is_due(schedule: BackupSchedule, newest: Option<&Backup>, now: SystemTime) -> bool {
+    newest.is_none_or(|backup| {
+        now.duration_since(backup.created)
+            .is_ok_and(|age| age >= schedule.interval())
+    })
+}
+
+/// Replaces the `Saves` folder with the backup, the current saves are backed up first.
+/// Retention is not applied here, it could delete the backup being restored.
+pub fn restore_backup(instance: &Instance, backup: &Backup) -> io::Result<()> {
+    create_backup(instance)?;
+
+    let saves_path = instance.path().join(SAVES_FOLDER);
+    if saves_path.exists() {
+        fs::remove_dir_all(&saves_path)?;
+    }
+    info!(
+        "restoring {} into {}",
+        backup.path.display(),
+        saves_path.display()
+    );
+    unzip(&backup.path, &saves_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn backup(days: u64, hours: u64) -> Backup {
+        Backup {
+            path: PathBuf::new(),
+            created: UNIX_EPOCH + Duration::from_secs(days * DAY + hours * 60 * 60),
+            number: 1,
+            size: 0,
+        }
+    }
+
+    #[test]
+    fn file_name_roundtrip() {
+        let created = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let file_name = Backup::file_name(created, 1);
+
+        assert_eq!(file_name, "saves-2023-11-14T22-13-20Z.zip");
+        assert_eq!(Backup::parse_file_name(&file_name), Some((created, 1)));
+        assert_eq!(
+            Backup::parse_file_name(&Backup::file_name(created, 12)),
+            Some((created, 12))
+        );
+        assert_eq!(Backup::parse_file_name("saves-latest.zip"), None);
+        assert_eq!(
+            Backup::parse_file_name("saves-2023-11-14T22-13-20Z-1.zip"),
+            None
+        );
+        assert_eq!(
+            Backup::parse_file_name("saves-2023-11-14T22-13-20Zx.zip"),
+            None
+        );
+    }
+
+    #[test]
+    fn same_second_newest_first() {
+        let temp_dir = tempdir().unwrap();
+        let backups_path = temp_dir.path().join(BACKUPS_FOLDER);
+        fs::create_dir_all(&backups_path).unwrap();
+        let created = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        for number in [1, 2, 10] {
+            fs::write(backups_path.join(Backup::file_name(created, number)), "zip").unwrap();
+        }
+
+        let numbers: Vec<_> = list_backups(temp_dir.path())
+            .unwrap()
+            .iter()
+            .map(|backup| backup.number)
+            .collect();
+        assert_eq!(numbers, [10, 2, 1]);
+    }
+
+    #[test]
+    fn retention() {
+        // newest first: two on day 15, one on 14, one on 8, one on 1
+        let backups = [
+            backup(15, 20),
+            backup(15, 10),
+            backup(14, 10),
+            backup(8, 10),
+            backup(1, 10),
+        ];
+
+        let settings = BackupSettings::default();
+        assert_eq!(retained(&backups, &settings), [true; 5]);
+
+        let settings = BackupSettings {
+            keep_last: 1,
+            keep_daily: 2,
+            ..BackupSettings::default()
+        };
+        assert_eq!(
+            retained(&backups, &settings),
+            [true, false, true, false, false]
+        );
+
+        // day 15 and 14 share a week, day 8 and 1 fall into the two weeks before
+        let settings = BackupSettings {
+            keep_weekly: 3,
+            ..BackupSettings::default()
+        };
+        assert_eq!(
+            retained(&backups, &settings),
+            [true, false, false, true, true]
+        );
+    }
+
+    #[test]
+    fn schedule_due() {
+        let now = UNIX_EPOCH + Duration::from_secs(10 * DAY);
+
+        assert!(is_due(BackupSchedule::Daily, None, now));
+        assert!(is_due(BackupSchedule::Daily, Some(&backup(9, 0)), now));
+        assert!(!is_due(BackupSchedule::Daily, Some(&backup(9, 1)), now));
+        assert!(!is_due(BackupSchedule::Weekly, Some(&backup(5, 0)), now));
+    }
+}
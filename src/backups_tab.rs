@@ -0,0 +1,296 @@
+use crate::toast::Toast;
+use crate::ui::format_size;
+use crate::{Message, style};
+use iced::alignment::{Horizontal, Vertical};
+use iced::widget::{
+    Column, Row, button, checkbox, column, container, horizontal_rule, horizontal_space, radio,
+    row, scrollable, text, text_input,
+};
+use iced::{Element, Length};
+use log::error;
+use rustic_vs::backups::{
+    BACKUPS_FOLDER, Backup, BackupSchedule, BackupSettings, BackupTrigger, back_up, back_up_on,
+    list_backups, restore_backup,
+};
+use rustic_vs::instance::Instance;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Keep {
+    Last,
+    Daily,
+    Weekly,
+}
+
+impl Keep {
+    const ALL: [Keep; 3] = [Keep::Last, Keep::Daily, Keep::Weekly];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Keep::Last => "Keep last:",
+            Keep::Daily => "Keep days:",
+            Keep::Weekly => "Keep weeks:",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum BackupsMessage {
+    BeforeLaunch(bool),
+    BeforeModUpdate(bool),
+    Schedule(Option<BackupSchedule>),
+    Keep(Keep, String),
+    SaveSettings,
+    BackUpNow,
+    Restore(usize),
+    ConfirmRestore,
+    CancelRestore,
+}
+
+/// Backup settings form and the list of backups of an instance.
+pub struct BackupsTab {
+    settings: BackupSettings,
+    keep: Vec<String>,
+    backups: Result<Vec<Backup>, String>,
+    confirm_restore: Option<usize>,
+}
+
+fn setting_row<'a>(label: &'a str, widget: Element<'a, Message>) -> Row<'a, Message> {
+    row![text(label).width(110), widget]
+        .spacing(10)
+        .align_y(Vertical::Center)
+}
+
+impl BackupsTab {
+    pub fn load(instance: &Instance) -> BackupsTab {
+        let settings = instance.backups.clone();
+
+        Self {
+            keep: [
+                settings.keep_last,
+                settings.keep_daily,
+                settings.keep_weekly,
+            ]
+            .map(|count| count.to_string())
+            .to_vec(),
+            settings,
+            backups: list_backups(&instance.path()).map_err(|e| e.to_string()),
+            confirm_restore: None,
+        }
+    }
+
+    pub fn reload(&mut self, instance: &Instance) {
+        self.backups = list_backups(&instance.path()).map_err(|e| e.to_string());
+    }
+
+    fn to_settings(&self) -> Result<BackupSettings, String> {
+        let count = |keep: Keep| {
+            let value = self.keep[keep as usize].trim();
+            value
+                .parse()
+                .map_err(|_| format!("{} '{}' is not a number", keep.label(), value))
+        };
+
+        Ok(BackupSettings {
+            keep_last: count(Keep::Last)?,
+            keep_daily: count(Keep::Daily)?,
+            keep_weekly: count(Keep::Weekly)?,
+            ..self.settings.clone()
+        })
+    }
+
+    /// Backup to restore on [`BackupsMessage::ConfirmRestore`], see [`restore_job`].
+    pub fn confirmed_restore(&mut self) -> Option<Backup> {
+        self.confirm_restore
+            .take()
+            .and_then(|index| self.backups.as_ref().ok()?.get(index).cloned())
+    }
+
+    /// Returns a toast for finished actions.
+    pub fn update(&mut self, message: BackupsMessage, instance: &mut Instance) -> Option<Toast> {
+        match message {
+            BackupsMessage::BeforeLaunch(enabled) => self.settings.before_launch = enabled,
+            BackupsMessage::BeforeModUpdate(enabled) => self.settings.before_mod_update = enabled,
+            BackupsMessage::Schedule(schedule) => self.settings.schedule = schedule,
+            BackupsMessage::Keep(keep, value) => self.keep[keep as usize] = value,
+            BackupsMessage::SaveSettings => {
+                let settings = match self.to_settings() {
+                    Ok(settings) => settings,
+                    Err(e) => return Some(Toast::error("Invalid backup settings", e)),
+                };
+                let mut edited = instance.clone();
+                edited.backups = settings;
+                return Some(match edited.save() {
+                    Ok(()) => {
+                        *instance = edited;
+                        Toast::info("Backup settings saved", &instance.name)
+                    }
+                    Err(e) => {
+                        error!("failed saving {}: {}", instance.folder_name_string(), e);
+                        Toast::error("Failed saving backup settings", e)
+                    }
+                });
+            }
+            BackupsMessage::Restore(index) => self.confirm_restore = Some(index),
+            BackupsMessage::CancelRestore => self.confirm_restore = None,
+            // run in the background, see `back_up_job` and `confirmed_restore`
+            BackupsMessage::BackUpNow | BackupsMessage::ConfirmRestore => {}
+        }
+        None
+    }
+
+    pub fn view<'a>(&'a self, instance: &'a Instance) -> Element<'a, Message> {
+        let schedule_radio = |label, schedule| {
+            radio(label, schedule, Some(self.settings.schedule), |schedule| {
+                Message::Backups(BackupsMessage::Schedule(schedule))
+            })
+        };
+
+        let mut form = Column::new()
+            .push(setting_row(
+                "Back up:",
+                column![
+                    checkbox("before launch", self.settings.before_launch).on_toggle(|enabled| {
+                        Message::Backups(BackupsMessage::BeforeLaunch(enabled))
+                    }),
+                    checkbox("before mods change", self.settings.before_mod_update).on_toggle(
+                        |enabled| Message::Backups(BackupsMessage::BeforeModUpdate(enabled))
+                    ),
+                ]
+                .spacing(5)
+                .into(),
+            ))
+            .push(setting_row(
+                "Schedule:",
+                row![
+                    schedule_radio("Off", None),
+                    schedule_radio("Daily", Some(BackupSchedule::Daily)),
+                    schedule_radio("Weekly", Some(BackupSchedule::Weekly)),
+                ]
+                .spacing(20)
+                .into(),
+            ));
+        for keep in Keep::ALL {
+            form = form.push(setting_row(
+                keep.label(),
+                text_input("0 keeps all", &self.keep[keep as usize])
+                    .on_input(move |value| Message::Backups(BackupsMessage::Keep(keep, value)))
+                    .width(100)
+                    .into(),
+            ));
+        }
+
+        let backups: Element<Message> = match &self.backups {
+            Err(e) => text(e).style(text::danger).into(),
+            Ok(backups) if backups.is_empty() => text("no backups yet")
+                .height(50)
+                .width(Length::Fill)
+                .align_y(Vertical::Center)
+                .align_x(Horizontal::Center)
+                .into(),
+            Ok(backups) => column(backups.iter().enumerate().map(|(index, backup)| {
+                let actions: Element<Message> = if self.confirm_restore == Some(index) {
+                    row![
+                        text("Replace current saves?").size(12),
+                        button(text("Restore").size(12))
+                            .style(button::danger)
+                            .on_press(Message::Backups(BackupsMessage::ConfirmRestore)),
+                        button(text("Cancel").size(12))
+                            .style(button::secondary)
+                            .on_press(Message::Backups(BackupsMessage::CancelRestore)),
+                    ]
+                    .spacing(5)
+                    .align_y(Vertical::Center)
+                    .into()
+                } else {
+                    button(text("Restore").size(12))
+                        .style(button::secondary)
+                        .on_press(Message::Backups(BackupsMessage::Restore(index)))
+                        .into()
+                };
+
+                container(
+                    row![
+                        text(backup.name()).width(Length::Fill),
+                        text(format_size(backup.size)).size(12).width(70),
+                        actions,
+                    ]
+                    .spacing(10)
+                    .align_y(Vertical::Center),
+                )
+                .padding([5, 10])
+                .style(style::striped(index))
+                .into()
+            }))
+            .into(),
+        };
+
+        column![
+            scrollable(
+                column![
+                    form.push(row![
+                        horizontal_space(),
+                        button("Save settings")
+                            .on_press(Message::Backups(BackupsMessage::SaveSettings)),
+                    ])
+                    .spacing(5),
+                    horizontal_rule(1),
+                    backups,
+                ]
+                .spacing(10)
+                .padding(10)
+            )
+            .height(Length::Fill),
+            row![
+                horizontal_space(),
+                button("Open folder")
+                    .style(button::secondary)
+                    .on_press(Message::OpenPath(instance.path().join(BACKUPS_FOLDER))),
+                button("Back up now").on_press(Message::Backups(BackupsMessage::BackUpNow)),
+            ]
+            .spacing(10)
+            .padding(10),
+        ]
+        .into()
+    }
+}
+
+/// Background job of [`BackupsMessage::BackUpNow`].
+pub fn back_up_job(instance: &Instance) -> Option<Toast> {
+    Some(match back_up(instance) {
+        Ok(Some(backup)) => Toast::info("Saves backed up", backup.name()),
+        Ok(None) => Toast::info("Nothing to back up", "the instance has no saves"),
+        Err(e) => {
+            error!("failed backing up {}: {}", instance.folder_name_string(), e);
+            Toast::error("Failed backing up saves", e)
+        }
+    })
+}
+
+/// Background job of [`BackupsMessage::ConfirmRestore`].
+pub fn restore_job(instance: &Instance, backup: &Backup) -> Option<Toast> {
+    Some(match restore_backup(instance, backup) {
+        Ok(()) => Toast::info("Backup restored", backup.name()),
+        Err(e) => {
+            error!("failed restoring {}: {}", backup.path.display(), e);
+            Toast::error("Failed restoring backup", e)
+        }
+    })
+}
+
+/// Scheduled backup in the background, only finished backups and errors are shown.
+pub fn scheduled_backup_job(instance: &Instance) -> Option<Toast> {
+    match back_up_on(instance, BackupTrigger::Schedule) {
+        Ok(Some(backup)) => Some(Toast::info(
+            format!("Backed up {}", instance.name),
+            backup.name(),
+        )),
+        Ok(None) => None,
+        Err(e) => {
+            error!("failed backing up {}: {}", instance.folder_name_string(), e);
+            Some(Toast::error(
+                format!("Failed backing up {}", instance.name),
+                e,
+            ))
+        }
+    }
+}
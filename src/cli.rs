@@ -0,0 +1,712 @@
+use clap::{Parser, Subcommand};
+use log::warn;
+use rustic_vs::backups::back_up;
+use rustic_vs::game::default_data_path;
+use rustic_vs::instance::{
+    AdoptMode, Instance, InstanceKind, adopt_data_folder, find_instance, import_instance,
+    is_env_name, load_instances,
+};
+use rustic_vs::launcher;
+use rustic_vs::launcher_settings::LauncherSettings;
+use rustic_vs::lockfile::{
+    Drift, INSTANCE_LOCK, lock_mods, read_lockfile, resolve_release_ids, sync, verify,
+    write_lockfile,
+};
+use rustic_vs::mod_list::{apply_mod_list, parse_mod_list};
+use rustic_vs::server_sync::{compare_mods, sync_client_mods};
+use rustic_vs::shortcuts::update_shortcuts;
+use rustic_vs::store::{StoreReport, collect_garbage, link_if_enabled, link_instance};
+use rustic_vs::updates::{apply_updates, check_updates};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::PathBuf;
+use std::{fs, io};
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Basic instance and mod manager for Vintage Story")]
+pub struct Cli {
+    /// Print JSON instead of text
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    /// Opens the GUI if omitted
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// List all instances
+    List,
+    /// Create a new instance
+    Create {
+        name: String,
+        /// File name of an icon in the icons folder
+        #[arg(long)]
+        icon: Option<String>,
+        /// Path to the game executable, or VintagestoryServer with --server
+        #[arg(long)]
+        game: Option<PathBuf>,
+        /// Create a dedicated server instance
+        #[arg(long)]
+        server: bool,
+    },
+    /// Change the launch options of an instance, which Rustic and launch.sh pass to the game
+    Edit {
+        instance: String,
+        /// Folder passed as --addModPath, can be repeated
+        #[arg(long = "add-mod-path")]
+        add_mod_paths: Vec<PathBuf>,
+        /// Remove the folders added before
+        #[arg(long)]
+        clear_mod_paths: bool,
+        /// Environment variable as NAME=VALUE, an empty value removes it, can be repeated
+        #[arg(long = "env", value_parser = parse_env)]
+        env: Vec<(String, String)>,
+    },
+    /// List the mods of an instance
+    Mods { instance: String },
+    /// Start the game or server with an instance
+    Launch {
+        instance: String,
+        /// Launch even if mods are incompatible with the game version
+        #[arg(long)]
+        force: bool,
+    },
+    /// Export an instance as zip
+    Export {
+        instance: String,
+        /// Defaults to <folder name>.zip
+        output: Option<PathBuf>,
+    },
+    /// Import an instance zip created by export
+    Import {
+        file: PathBuf,
+        /// Defaults to the zip file name
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Create an instance from an existing Vintage Story data folder
+    Adopt {
+        name: String,
+        /// Defaults to the data folder of a normal game install
+        #[arg(long)]
+        path: Option<PathBuf>,
+        /// Link to the folder instead of copying it, instance.toml and installed mods are
+        /// then written into the original folder
+        #[arg(long)]
+        link: bool,
+    },
+    /// Back up the saves of an instance, e.g. from a cron job
+    Backup { instance: String },
+    /// Pin the mods of an instance in instance.lock
+    Lock {
+        instance: String,
+        /// Skip looking up ModDB release ids
+        #[arg(long)]
+        offline: bool,
+    },
+    /// Compare the mods of an instance with instance.lock
+    Verify { instance: String },
+    /// Download or remove mods until they match instance.lock
+    Sync { instance: String },
+    /// Manage the mod store shared by all instances
+    Store {
+        #[command(subcommand)]
+        command: StoreCommand,
+    },
+    /// Make the mods of an instance match a list of modid@version entries, e.g. from a
+    /// server log
+    ImportMods {
+        instance: String,
+        /// Text file with the list, - reads stdin
+        list: PathBuf,
+        /// Create the instance if it does not exist
+        #[arg(long)]
+        create: bool,
+    },
+    /// Copy the mods a server requires into a client instance
+    SyncServer {
+        server: String,
+        client: String,
+        /// Only compare the mods
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Update the mods of an instance from ModDB
+    UpdateMods {
+        instance: String,
+        /// Only list available updates
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum StoreCommand {
+    /// Replace mod files by links into the store
+    Link {
+        /// Defaults to all instances
+        instance: Option<String>,
+    },
+    /// Delete store files no instance uses anymore
+    Gc,
+}
+
+#[derive(Serialize)]
+struct InstanceOutput {
+    folder: String,
+    name: String,
+    kind: InstanceKind,
+    icon: Option<String>,
+    game_exe_path: Option<PathBuf>,
+    game_version: Option<String>,
+    mods: usize,
+    incompatible_mods: usize,
+    add_mod_paths: Vec<PathBuf>,
+    env: BTreeMap<String, String>,
+}
+
+impl From<&Instance> for InstanceOutput {
+    fn from(instance: &Instance) -> Self {
+        Self {
+            folder: instance.folder_name_string(),
+            name: instance.name.clone(),
+            kind: instance.kind,
+            icon: instance.icon.clone(),
+            game_exe_path: instance.game_exe_path.clone(),
+            game_version: instance.game_version.as_ref().map(ToString::to_string),
+            mods: instance.mods_count(),
+            incompatible_mods: instance.incompatible_mods().len(),
+            add_mod_paths: instance.add_mod_paths.clone(),
+            env: instance.env.clone(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ModOutput {
+    file: String,
+    mod_id: String,
+    name: String,
+    version: String,
+    compatible: bool,
+}
+
+#[derive(Serialize)]
+struct ComparisonOutput {
+    mod_id: String,
+    server_version: Option<String>,
+    client_version: Option<String>,
+    status: String,
+}
+
+#[derive(Serialize)]
+struct UpdateOutput {
+    mod_id: String,
+    file: String,
+    version: String,
+    new_file: String,
+    new_version: String,
+    release_id: u64,
+}
+
+pub fn run(command: Command, json: bool) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::List => {
+            // broken instances are logged to stderr
+            let (instances, _) = load_instances();
+            if json {
+                let output: Vec<InstanceOutput> = instances.iter().map(Into::into).collect();
+                print_json(&output)?;
+            } else {
+                for instance in &instances {
+                    print_instance(instance);
+                }
+            }
+        }
+        Command::Create {
+            name,
+            icon,
+            game,
+            server,
+        } => {
+            let name = name.trim();
+            if name.is_empty() {
+                return Err("instance name must not be empty".into());
+            }
+
+            let mut instance = Instance::new(name);
+            if instance.path().exists() {
+                return Err(
+                    format!("folder {} already exists", instance.folder_name_string()).into(),
+                );
+            }
+            instance.icon = icon;
+            instance.game_exe_path = game;
+            if server {
+                instance.kind = InstanceKind::Server;
+            }
+            instance.detect_game_version();
+            instance.save()?;
+            update_launchers(&instance);
+
+            if json {
+                print_json(&InstanceOutput::from(&instance))?;
+            } else {
+                print_instance(&instance);
+            }
+        }
+        Command::Edit {
+            instance,
+            add_mod_paths,
+            clear_mod_paths,
+            env,
+        } => {
+            let mut instance = find(&instance)?;
+            if clear_mod_paths {
+                instance.add_mod_paths.clear();
+            }
+            for mod_path in add_mod_paths {
+                if !instance.add_mod_paths.contains(&mod_path) {
+                    instance.add_mod_paths.push(mod_path);
+                }
+            }
+            for (name, value) in env {
+                if value.is_empty() {
+                    instance.env.remove(&name);
+                } else {
+                    instance.env.insert(name, value);
+                }
+            }
+            instance.save()?;
+            update_launchers(&instance);
+
+            if json {
+                print_json(&InstanceOutput::from(&instance))?;
+            } else {
+                print_instance(&instance);
+                for mod_path in &instance.add_mod_paths {
+                    println!("  --addModPath {}", mod_path.display());
+                }
+                for (name, value) in &instance.env {
+                    println!("  {}={}", name, value);
+                }
+            }
+        }
+        Command::Mods { instance } => {
+            let instance = find(&instance)?;
+            let output: Vec<ModOutput> = instance
+                .mods
+                .iter()
+                .map(|mod_info| ModOutput {
+                    file: mod_info.zip_name.to_string_lossy().to_string(),
+                    mod_id: mod_info.mod_id.clone(),
+                    name: mod_info.name.clone(),
+                    version: mod_info.version.clone(),
+                    compatible: instance
+                        .game_version
+                        .as_ref()
+                        .is_none_or(|v| mod_info.compatibility(v).is_compatible()),
+                })
+                .collect();
+
+            if json {
+                print_json(&output)?;
+            } else {
+                for mod_output in output {
+                    println!(
+                        "{:<30} {:<12} {}{}",
+                        mod_output.mod_id,
+                        mod_output.version,
+                        mod_output.file,
+                        if mod_output.compatible {
+                            ""
+                        } else {
+                            " (incompatible)"
+                        }
+                    );
+                }
+            }
+        }
+        Command::Launch { instance, force } => {
+            let instance = find(&instance)?;
+            let incompatible_mods = instance.incompatible_mods();
+            if !force && !incompatible_mods.is_empty() {
+                let names: Vec<_> = incompatible_mods.iter().map(|m| m.name.as_str()).collect();
+                return Err(format!(
+                    "incompatible mods: {}, use --force to launch anyway",
+                    names.join(", ")
+                )
+                .into());
+            }
+
+            launcher::back_up_before_launch(&instance)?;
+            let child = launcher::launch(&instance)?;
+            if json {
+                print_json(&serde_json::json!({ "pid": child.id() }))?;
+            } else {
+                println!("launched {} (pid {})", instance.name, child.id());
+            }
+        }
+        Command::Export { instance, output } => {
+            let instance = find(&instance)?;
+            let output =
+                output.unwrap_or_else(|| format!("{}.zip", instance.folder_name_string()).into());
+            instance.export(&output)?;
+
+            if json {
+                print_json(&serde_json::json!({ "file": output }))?;
+            } else {
+                println!("exported {} to {}", instance.name, output.display());
+            }
+        }
+        Command::Import { file, name } => {
+            let name = match name {
+                Some(name) => name,
+                None => file
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .ok_or("missing instance name")?,
+            };
+            let instance = import_instance(&file, &name)
+                .map_err(|e| format!("failed importing {}: {}", file.display(), e))?;
+            update_launchers(&instance);
+
+            if json {
+                print_json(&InstanceOutput::from(&instance))?;
+            } else {
+                print_instance(&instance);
+            }
+        }
+        Command::Adopt { name, path, link } => {
+            let path = path
+                .or_else(default_data_path)
+                .ok_or("no data folder found, use --path")?;
+            let mode = if link {
+                AdoptMode::Link
+            } else {
+                AdoptMode::Copy
+            };
+            let instance = adopt_data_folder(&path, name.trim(), mode)
+                .map_err(|e| format!("failed adopting {}: {}", path.display(), e))?;
+            update_launchers(&instance);
+
+            if json {
+                print_json(&InstanceOutput::from(&instance))?;
+            } else {
+                print_instance(&instance);
+            }
+        }
+        Command::Backup { instance } => {
+            let instance = find(&instance)?;
+            let backup = back_up(&instance)?;
+
+            if json {
+                print_json(&serde_json::json!({
+                    "file": backup.as_ref().map(|backup| &backup.path)
+                }))?;
+            } else {
+                match backup {
+                    Some(backup) => println!("backed up to {}", backup.path.display()),
+                    None => println!("{} has no saves", instance.name),
+                }
+            }
+        }
+        Command::Lock { instance, offline } => {
+            let instance = find(&instance)?;
+            let mut lockfile = lock_mods(&instance)?;
+            if !offline {
+                let runtime = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()?;
+                runtime.block_on(resolve_release_ids(
+                    &LauncherSettings::load()?.moddb(),
+                    &mut lockfile,
+                ))?;
+            }
+            write_lockfile(&instance, &lockfile)?;
+
+            if json {
+                print_json(&lockfile.mods)?;
+            } else {
+                println!("locked {} mods of {}", lockfile.mods.len(), instance.name);
+            }
+        }
+        Command::Verify { instance } => {
+            let instance = find(&instance)?;
+            let drift = verify(&instance, &read_lockfile(&instance)?)?;
+            print_drift(&drift, json)?;
+            if !drift.is_empty() {
+                return Err(format!("{} differs from {}", instance.name, INSTANCE_LOCK).into());
+            }
+        }
+        Command::Sync { instance } => {
+            let instance = find(&instance)?;
+            let lockfile = read_lockfile(&instance)?;
+            let settings = LauncherSettings::load()?;
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()?;
+            let drift = runtime.block_on(sync(&instance, &settings.moddb(), &lockfile))?;
+            link_if_enabled(&settings.mod_store, &instance)?;
+            print_drift(&drift, json)?;
+        }
+        Command::Store { command } => {
+            let report = match command {
+                StoreCommand::Link { instance } => {
+                    let instances = match instance {
+                        Some(instance) => vec![find(&instance)?],
+                        None => load_instances().0,
+                    };
+                    let link_mode = LauncherSettings::load()?.mod_store.link_mode;
+
+                    let mut report = StoreReport::default();
+                    for instance in &instances {
+                        let instance_report = link_instance(instance, link_mode)?;
+                        report.files += instance_report.files;
+                        report.bytes += instance_report.bytes;
+                    }
+                    report
+                }
+                StoreCommand::Gc => collect_garbage()?,
+            };
+
+            if json {
+                print_json(&serde_json::json!({ "files": report.files, "bytes": report.bytes }))?;
+            } else {
+                println!("{} files, {} bytes freed", report.files, report.bytes);
+            }
+        }
+        Command::ImportMods {
+            instance,
+            list,
+            create,
+        } => {
+            let text = if list.as_os_str() == "-" {
+                io::read_to_string(io::stdin())?
+            } else {
+                fs::read_to_string(&list)?
+            };
+            let mod_list = parse_mod_list(&text);
+            if mod_list.is_empty() {
+                return Err("no modid@version entries found".into());
+            }
+
+            let instance = match find_instance(&instance) {
+                Some(instance) => instance,
+                None if create => {
+                    let mut new_instance = Instance::new(instance.trim());
+                    new_instance.detect_game_version();
+                    new_instance.create().map_err(|e| {
+                        format!(
+                            "failed creating {}: {}",
+                            new_instance.folder_name_string(),
+                            e
+                        )
+                    })?;
+                    update_launchers(&new_instance);
+                    new_instance
+                }
+                None => return Err(format!("instance {} not found", instance).into()),
+            };
+            let settings = LauncherSettings::load()?;
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()?;
+            let report =
+                runtime.block_on(apply_mod_list(&settings.moddb(), &instance, &mod_list))?;
+            link_if_enabled(&settings.mod_store, &instance)?;
+
+            if json {
+                print_json(&serde_json::json!({
+                    "installed": report.installed.iter().map(ToString::to_string).collect::<Vec<_>>(),
+                    "kept": report.kept,
+                    "removed": report.removed,
+                    "missing": report.missing.iter().map(ToString::to_string).collect::<Vec<_>>(),
+                }))?;
+            } else {
+                println!(
+                    "{}: {} installed, {} kept, {} removed",
+                    instance.name,
+                    report.installed.len(),
+                    report.kept,
+                    report.removed.len()
+                );
+                for missing in &report.missing {
+                    println!("not found {}", missing);
+                }
+            }
+            if !report.missing.is_empty() {
+                return Err(format!("{} mods not found", report.missing.len()).into());
+            }
+        }
+        Command::SyncServer {
+            server,
+            client,
+            dry_run,
+        } => {
+            let server = find(&server)?;
+            let client = find(&client)?;
+            if !server.is_server() {
+                return Err(format!("{} is not a server instance", server.name).into());
+            }
+
+            let comparisons = if dry_run {
+                compare_mods(&server, &client)
+            } else {
+                let changes = sync_client_mods(&server, &client)?;
+                link_if_enabled(&LauncherSettings::load()?.mod_store, &client)?;
+                changes
+            };
+
+            let output: Vec<ComparisonOutput> = comparisons
+                .into_iter()
+                .map(|comparison| ComparisonOutput {
+                    status: comparison.status.to_string(),
+                    server_version: comparison.server.map(|mod_info| mod_info.version),
+                    client_version: comparison.client.map(|mod_info| mod_info.version),
+                    mod_id: comparison.mod_id,
+                })
+                .collect();
+
+            if json {
+                print_json(&output)?;
+            } else if !dry_run && output.is_empty() {
+                println!("mods of {} already match {}", client.name, server.name);
+            } else {
+                for comparison in output {
+                    println!(
+                        "{:<30} {:<12} {:<12} {}",
+                        comparison.mod_id,
+                        comparison.server_version.as_deref().unwrap_or("-"),
+                        comparison.client_version.as_deref().unwrap_or("-"),
+                        if dry_run {
+                            comparison.status.as_str()
+                        } else {
+                            "synced"
+                        }
+                    );
+                }
+            }
+        }
+        Command::UpdateMods { instance, dry_run } => {
+            let instance = find(&instance)?;
+            let settings = LauncherSettings::load()?;
+            let moddb = settings.moddb();
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()?;
+
+            let updates = runtime.block_on(check_updates(
+                &moddb,
+                &instance.mods,
+                instance.game_version.as_ref(),
+            ));
+            if !dry_run {
+                runtime.block_on(apply_updates(&moddb, &instance, &updates))?;
+                link_if_enabled(&settings.mod_store, &instance)?;
+            }
+
+            let output: Vec<UpdateOutput> = updates
+                .into_iter()
+                .map(|update| UpdateOutput {
+                    mod_id: update.mod_info.mod_id,
+                    file: update.mod_info.zip_name.to_string_lossy().to_string(),
+                    version: update.mod_info.version,
+                    new_file: update.release.filename,
+                    new_version: update.release.version,
+                    release_id: update.release.id,
+                })
+                .collect();
+
+            if json {
+                print_json(&output)?;
+            } else if output.is_empty() {
+                println!("all mods are up to date");
+            } else {
+                for update in output {
+                    println!(
+                        "{} {} -> {}{}",
+                        update.mod_id,
+                        update.version,
+                        update.new_version,
+                        if dry_run { " (available)" } else { "" }
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_env(value: &str) -> Result<(String, String), String> {
+    let (name, value) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=VALUE, got '{}'", value))?;
+    if !is_env_name(name) {
+        return Err(format!(
+            "'{}' is not a variable name, use letters, digits and _",
+            name
+        ));
+    }
+    Ok((name.to_string(), value.to_string()))
+}
+
+/// Regenerates the launchers of a saved instance, failing only warns as the instance itself
+/// is saved already.
+fn update_launchers(instance: &Instance) {
+    let result = LauncherSettings::load()
+        .map_err(|e| e.to_string())
+        .and_then(|settings| {
+            update_shortcuts(&settings.shortcuts, instance).map_err(|e| e.to_string())
+        });
+    if let Err(e) = result {
+        warn!(
+            "failed updating shortcuts of {}: {}",
+            instance.folder_name_string(),
+            e
+        );
+    }
+}
+
+fn find(query: &str) -> Result<Instance, Box<dyn Error>> {
+    find_instance(query).ok_or_else(|| format!("instance {} not found", query).into())
+}
+
+fn print_drift(drift: &[Drift], json: bool) -> Result<(), Box<dyn Error>> {
+    if json {
+        let output: Vec<_> = drift.iter().map(ToString::to_string).collect();
+        print_json(&output)?;
+    } else if drift.is_empty() {
+        println!("mods match {}", INSTANCE_LOCK);
+    } else {
+        for item in drift {
+            println!("{}", item);
+        }
+    }
+
+    Ok(())
+}
+
+fn print_instance(instance: &Instance) {
+    println!(
+        "{:<20} {:<30} {:<6} {:>4} mods  {}",
+        instance.folder_name_string(),
+        instance.name,
+        instance.kind,
+        instance.mods_count(),
+        instance
+            .game_version
+            .as_ref()
+            .map(ToString::to_string)
+            .unwrap_or_default()
+    );
+}
+
+fn print_json(value: &impl Serialize) -> Result<(), Box<dyn Error>> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
@@ -0,0 +1,150 @@
+//! Console of a running server, its output is read on background threads and sent as
+//! [`ConsoleEvent`]s, commands are written to its stdin.
+
+use log::{debug, error};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin};
+use std::sync::{Arc, Mutex};
+use std::{io, thread};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConsoleEvent {
+    Line(ConsoleLine),
+    /// The process ended, with its exit code if it was not killed by a signal.
+    Exited(Option<i32>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsoleLine {
+    pub text: String,
+    pub stderr: bool,
+}
+
+/// Stdin of a server started by [`crate::launcher::launch_server`]. Clones write to the same
+/// process.
+#[derive(Debug, Clone)]
+pub struct ServerConsole {
+    stdin: Option<Arc<Mutex<ChildStdin>>>,
+    pub pid: u32,
+}
+
+impl ServerConsole {
+    /// Takes over the piped stdin, stdout and stderr of the child. The receiver ends with
+    /// [`ConsoleEvent::Exited`] once both outputs are closed and the process was reaped.
+    pub fn attach(mut child: Child) -> (ServerConsole, UnboundedReceiver<ConsoleEvent>) {
+        let (sender, receiver) = unbounded_channel();
+        let console = ServerConsole {
+            stdin: child.stdin.take().map(|stdin| Arc::new(Mutex::new(stdin))),
+            pid: child.id(),
+        };
+
+        let readers: Vec<_> = [
+            child
+                .stdout
+                .take()
+                .map(|out| read_lines(out, false, sender.clone())),
+            child
+                .stderr
+                .take()
+                .map(|err| read_lines(err, true, sender.clone())),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        thread::spawn(move || {
+            for reader in readers {
+                let _ = reader.join();
+            }
+            let code = match child.wait() {
+                Ok(status) => status.code(),
+                Err(e) => {
+                    error!("failed waiting for server {}: {}", child.id(), e);
+                    None
+                }
+            };
+            debug!("server {} exited with {:?}", child.id(), code);
+            let _ = sender.send(ConsoleEvent::Exited(code));
+        });
+
+        (console, receiver)
+    }
+
+    /// Writes one command line, e.g. `/time set day`.
+    pub fn send(&self, command: &str) -> io::Result<()> {
+        let Some(stdin) = &self.stdin else {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        };
+        let mut stdin = stdin.lock().unwrap_or_else(|e| e.into_inner());
+        writeln!(stdin, "{}", command.trim_end())?;
+        stdin.flush()
+    }
+}
+
+/// Reads until the pipe closes, invalid UTF-8 is replaced instead of ending the stream.
+fn read_lines(
+    output: impl Read + Send + 'static,
+    stderr: bool,
+    sender: UnboundedSender<ConsoleEvent>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(output);
+        let mut buffer = Vec::new();
+        loop {
+            buffer.clear();
+            match reader.read_until(b'\n', &mut buffer) {
+                Ok(0) => break,
+                Ok(_) => {
+                    let text = String::from_utf8_lossy(&buffer)
+                        .trim_end_matches(['\r', '\n'])
+                        .to_string();
+                    if sender
+                        .send(ConsoleEvent::Line(ConsoleLine { text, stderr }))
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    debug!("stopped reading server output: {}", e);
+                    break;
+                }
+            }
+        }
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::process::{Command, Stdio};
+
+    #[test]
+    fn echoes_commands() {
+        let child = Command::new("sh")
+            .args(["-c", "read line; echo \"got $line\"; echo oops >&2; exit 3"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let (console, mut receiver) = ServerConsole::attach(child);
+        console.send("/time set day").unwrap();
+
+        let mut events = Vec::new();
+        while let Some(event) = receiver.blocking_recv() {
+            events.push(event);
+        }
+
+        let line = |text: &str, stderr| {
+            ConsoleEvent::Line(ConsoleLine {
+                text: text.to_string(),
+                stderr,
+            })
+        };
+        assert!(events.contains(&line("got /time set day", false)));
+        assert!(events.contains(&line("oops", true)));
+        assert_eq!(events.last(), Some(&ConsoleEvent::Exited(Some(3))));
+    }
+}
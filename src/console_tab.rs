@@ -0,0 +1,172 @@
+use crate::Message;
+use iced::alignment::Vertical;
+use iced::widget::{button, column, container, row, scrollable, text, text_input};
+use iced::{Element, Font, Length, Task, clipboard};
+use rustic_vs::console::{ConsoleEvent, ConsoleLine, ServerConsole};
+use std::collections::VecDeque;
+
+/// Older output is dropped, long running servers log a lot.
+const MAX_LINES: usize = 1000;
+const MAX_HISTORY: usize = 100;
+
+#[derive(Debug, Clone)]
+pub enum ConsoleMessage {
+    Event(ConsoleEvent),
+    Input(String),
+    Submit,
+    HistoryPrevious,
+    HistoryNext,
+    Copy(usize),
+}
+
+/// Output and command line of a server started from the GUI, kept while the instance view
+/// is closed.
+pub struct ConsoleTab {
+    console: ServerConsole,
+    lines: VecDeque<ConsoleLine>,
+    input: String,
+    history: Vec<String>,
+    /// Position while stepping through the history, `None` for a new command.
+    history_index: Option<usize>,
+    copied: Option<usize>,
+    /// Exit code once the server stopped.
+    exited: Option<Option<i32>>,
+}
+
+impl ConsoleTab {
+    pub fn new(console: ServerConsole) -> ConsoleTab {
+        Self {
+            console,
+            lines: VecDeque::new(),
+            input: String::new(),
+            history: Vec::new(),
+            history_index: None,
+            copied: None,
+            exited: None,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.exited.is_none()
+    }
+
+    fn push_line(&mut self, line: ConsoleLine) {
+        if self.lines.len() == MAX_LINES {
+            self.lines.pop_front();
+            self.copied = self.copied.and_then(|index| index.checked_sub(1));
+        }
+        self.lines.push_back(line);
+    }
+
+    pub fn update(&mut self, message: ConsoleMessage) -> Task<Message> {
+        match message {
+            ConsoleMessage::Event(ConsoleEvent::Line(line)) => self.push_line(line),
+            ConsoleMessage::Event(ConsoleEvent::Exited(code)) => self.exited = Some(code),
+            ConsoleMessage::Input(input) => {
+                self.input = input;
+                self.history_index = None;
+            }
+            ConsoleMessage::Submit => {
+                let command = self.input.trim().to_string();
+                if command.is_empty() || !self.is_running() {
+                    return Task::none();
+                }
+                if let Err(e) = self.console.send(&command) {
+                    self.push_line(ConsoleLine {
+                        text: format!("failed sending {}: {}", command, e),
+                        stderr: true,
+                    });
+                }
+                if self.history.last() != Some(&command) {
+                    self.history.push(command);
+                    if self.history.len() > MAX_HISTORY {
+                        self.history.remove(0);
+                    }
+                }
+                self.input.clear();
+                self.history_index = None;
+            }
+            ConsoleMessage::HistoryPrevious => {
+                let index = match self.history_index {
+                    Some(index) => index.saturating_sub(1),
+                    None if self.history.is_empty() => return Task::none(),
+                    None => self.history.len() - 1,
+                };
+                self.history_index = Some(index);
+                self.input = self.history[index].clone();
+            }
+            ConsoleMessage::HistoryNext => {
+                let Some(index) = self.history_index else {
+                    return Task::none();
+                };
+                if index + 1 < self.history.len() {
+                    self.history_index = Some(index + 1);
+                    self.input = self.history[index + 1].clone();
+                } else {
+                    self.history_index = None;
+                    self.input.clear();
+                }
+            }
+            ConsoleMessage::Copy(index) => {
+                if let Some(line) = self.lines.get(index) {
+                    self.copied = Some(index);
+                    return clipboard::write(line.text.clone());
+                }
+            }
+        }
+        Task::none()
+    }
+
+    pub fn view(&self, folder: String) -> Element<'_, Message> {
+        let message = |message| Message::Console(folder.clone(), message);
+
+        let status = match self.exited {
+            None => format!(
+                "Running (pid {}), click a line to copy it",
+                self.console.pid
+            ),
+            Some(Some(code)) => format!("Stopped with exit code {}", code),
+            Some(None) => "Stopped".to_string(),
+        };
+
+        let lines = column(self.lines.iter().enumerate().map(|(index, line)| {
+            button(text(&line.text).size(12).font(Font::MONOSPACE).style(
+                if self.copied == Some(index) {
+                    text::success
+                } else if line.stderr {
+                    text::danger
+                } else {
+                    text::default
+                },
+            ))
+            .style(button::text)
+            .padding(0)
+            .on_press(message(ConsoleMessage::Copy(index)))
+            .into()
+        }))
+        .padding(10);
+
+        column![
+            container(text(status).size(12)).padding([5, 10]),
+            scrollable(lines)
+                .anchor_bottom()
+                .width(Length::Fill)
+                .height(Length::Fill),
+            row![
+                text_input("/help", &self.input)
+                    .font(Font::MONOSPACE)
+                    .on_input_maybe(self.is_running().then(|| {
+                        let folder = folder.clone();
+                        move |input| Message::Console(folder.clone(), ConsoleMessage::Input(input))
+                    }))
+                    .on_submit(message(ConsoleMessage::Submit)),
+                button("Send")
+                    .on_press_maybe(self.is_running().then(|| message(ConsoleMessage::Submit))),
+            ]
+            .spacing(10)
+            .padding(10)
+            .align_y(Vertical::Center),
+        ]
+        .into()
+    }
+}
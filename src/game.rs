@@ -0,0 +1,238 @@
+use log::debug;
+use std::cmp::Ordering;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+const API_DLL: &str = "VintagestoryAPI.dll";
+const DATA_FOLDER: &str = "VintagestoryData";
+const VERSION_KEYS: [&str; 2] = ["ProductVersion", "FileVersion"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GameVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+    pub pre: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct VersionParseError;
+
+impl FromStr for GameVersion {
+    type Err = VersionParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().trim_start_matches(['v', 'V']);
+        let (numbers, pre) = match s.split_once('-') {
+            Some((numbers, pre)) if !pre.is_empty() => (numbers, Some(pre.to_string())),
+            Some(..) => return Err(VersionParseError),
+            None => (s, None),
+        };
+
+        let mut parts = numbers.split('.').map(|part| part.parse::<u32>());
+        let major = parts.next().ok_or(VersionParseError)?;
+        let minor = parts.next().ok_or(VersionParseError)?;
+        let patch = parts.next().unwrap_or(Ok(0));
+        // FileVersion has a fourth (revision) part which is always 0, any other would be
+        // lost in the ordering
+        match parts.next() {
+            None | Some(Ok(0)) => {}
+            Some(..) => return Err(VersionParseError),
+        }
+        if parts.next().is_some() {
+            return Err(VersionParseError);
+        }
+
+        Ok(Self {
+            major: major.map_err(|_| VersionParseError)?,
+            minor: minor.map_err(|_| VersionParseError)?,
+            patch: patch.map_err(|_| VersionParseError)?,
+            pre,
+        })
+    }
+}
+
+impl fmt::Display for GameVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if let Some(pre) = &self.pre {
+            write!(f, "-{}", pre)?;
+        }
+        Ok(())
+    }
+}
+
+impl Ord for GameVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (&self.pre, &other.pre) {
+                (None, None) => Ordering::Equal,
+                (None, Some(..)) => Ordering::Greater,
+                (Some(..), None) => Ordering::Less,
+                (Some(a), Some(b)) => cmp_pre(a, b),
+            })
+    }
+}
+
+impl PartialOrd for GameVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Compares pre-release tags like `rc.2` and `rc.10` part by part, numerically where possible.
+fn cmp_pre(a: &str, b: &str) -> Ordering {
+    let mut a_parts = a.split('.');
+    let mut b_parts = b.split('.');
+    loop {
+        match (a_parts.next(), b_parts.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(..)) => return Ordering::Less,
+            (Some(..), None) => return Ordering::Greater,
+            (Some(a), Some(b)) => {
+                let ordering = match (a.parse::<u32>(), b.parse::<u32>()) {
+                    (Ok(a), Ok(b)) => a.cmp(&b),
+                    _ => a.cmp(b),
+                };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+        }
+    }
+}
+
+/// Default data folder used when the game is started without `--dataPath`.
+pub fn default_data_path() -> Option<PathBuf> {
+    let config_dir = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+
+    config_dir.map(|dir| dir.join(DATA_FOLDER))
+}
+
+/// Reads the game version from the `VintagestoryAPI.dll` next to the game executable.
+pub fn detect_version(install_dir: &Path) -> Option<GameVersion> {
+    let dll_path = install_dir.join(API_DLL);
+    match fs::read(&dll_path) {
+        Ok(data) => parse_version_resource(&data),
+        Err(e) => {
+            debug!("failed reading {}: {}", dll_path.display(), e);
+            None
+        }
+    }
+}
+
+/// Looks for the version strings of the Win32 version resource, which .NET embeds in every
+/// assembly. Keys and values are null-terminated UTF-16LE, separated by alignment padding.
+pub fn parse_version_resource(data: &[u8]) -> Option<GameVersion> {
+    VERSION_KEYS
+        .iter()
+        .filter_map(|key| find_version_string(data, key))
+        .find_map(|value| value.parse().ok())
+}
+
+fn find_version_string(data: &[u8], key: &str) -> Option<String> {
+    let needle: Vec<u8> = key
+        .encode_utf16()
+        .chain([0])
+        .flat_map(u16::to_le_bytes)
+        .collect();
+
+    let start = data
+        .windows(needle.len())
+        .position(|window| window == needle.as_slice())?
+        + needle.len();
+
+    let value: Vec<u16> = data[start..]
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .skip_while(|unit| *unit == 0)
+        .take_while(|unit| *unit != 0)
+        .collect();
+
+    String::from_utf16(&value).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn version(major: u32, minor: u32, patch: u32) -> GameVersion {
+        GameVersion {
+            major,
+            minor,
+            patch,
+            pre: None,
+        }
+    }
+
+    fn fixture(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/installs")
+            .join(name)
+    }
+
+    #[test]
+    fn parse_versions() {
+        assert_eq!("1.20.4".parse(), Ok(version(1, 20, 4)));
+        assert_eq!("v1.19".parse(), Ok(version(1, 19, 0)));
+        assert_eq!("1.20.4.0".parse(), Ok(version(1, 20, 4)));
+        assert_eq!(
+            "1.21.0-rc.3".parse::<GameVersion>().map(|v| v.to_string()),
+            Ok("1.21.0-rc.3".to_string())
+        );
+        assert_eq!("1".parse::<GameVersion>(), Err(VersionParseError));
+        assert_eq!("1.20.x".parse::<GameVersion>(), Err(VersionParseError));
+        assert_eq!("1.20.4.0.1".parse::<GameVersion>(), Err(VersionParseError));
+        assert_eq!("1.20.4.1".parse::<GameVersion>(), Err(VersionParseError));
+        assert_eq!("1.20.4.x".parse::<GameVersion>(), Err(VersionParseError));
+        assert_eq!("1.20.0-".parse::<GameVersion>(), Err(VersionParseError));
+    }
+
+    #[test]
+    fn order_versions() {
+        let parse = |s: &str| s.parse::<GameVersion>().unwrap();
+        assert!(parse("1.20.0") > parse("1.19.8"));
+        assert!(parse("1.20.0") > parse("1.20.0-rc.10"));
+        assert!(parse("1.20.0-rc.10") > parse("1.20.0-rc.9"));
+        assert!(parse("1.20.0-rc.1") > parse("1.20.0-pre.3"));
+    }
+
+    #[test]
+    fn detect_product_version() {
+        assert_eq!(detect_version(&fixture("1.20.4")), Some(version(1, 20, 4)));
+    }
+
+    #[test]
+    fn detect_pre_release() {
+        assert_eq!(
+            detect_version(&fixture("1.21.0-rc.2")).map(|v| v.to_string()),
+            Some("1.21.0-rc.2".to_string())
+        );
+    }
+
+    #[test]
+    fn detect_file_version_fallback() {
+        assert_eq!(
+            detect_version(&fixture("file-version-only")),
+            Some(version(1, 19, 8))
+        );
+    }
+
+    #[test]
+    fn detect_missing_dll() {
+        assert_eq!(detect_version(&fixture("missing")), None);
+    }
+}
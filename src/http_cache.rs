@@ -0,0 +1,311 @@
+//! Disk cache for ModDB. Responses are kept with their `ETag`/`Last-Modified` headers in
+//! `cache/http`, downloaded mod files in `cache/downloads` with the SHA-256 of their content.
+//! Both are named after the SHA-256 of their URL.
+
+use crate::launcher_settings::CacheSettings;
+use crate::mods::sha256_data;
+use log::{debug, error};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{env, fs, io};
+
+pub const CACHE_FOLDER: &str = "cache";
+const RESPONSES_FOLDER: &str = "http";
+const DOWNLOADS_FOLDER: &str = "downloads";
+
+/// Value from ModDB, `stale` if it could not be reached and the cache answered instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cached<T> {
+    pub value: T,
+    pub stale: bool,
+}
+
+impl<T> Cached<T> {
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Cached<U> {
+        Cached {
+            value: f(self.value),
+            stale: self.stale,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+struct ResponseMeta {
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    etag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_modified: Option<String>,
+    /// Seconds since the Unix epoch of the last time ModDB confirmed the body.
+    fetched: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CachedResponse {
+    meta: ResponseMeta,
+    pub body: Vec<u8>,
+}
+
+impl CachedResponse {
+    pub fn etag(&self) -> Option<&str> {
+        self.meta.etag.as_deref()
+    }
+
+    pub fn last_modified(&self) -> Option<&str> {
+        self.meta.last_modified.as_deref()
+    }
+
+    /// Whether it can be used without asking ModDB.
+    pub fn is_fresh(&self, ttl: Duration) -> bool {
+        now().saturating_sub(self.meta.fetched) < ttl.as_secs()
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+pub fn cache_root() -> io::Result<PathBuf> {
+    Ok(env::current_dir()?.join(CACHE_FOLDER))
+}
+
+/// Writes next to the target and renames, readers never see half written files.
+fn write_file(path: &Path, data: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut part_path = path.as_os_str().to_os_string();
+    part_path.push(".part");
+    fs::write(&part_path, data)?;
+    fs::rename(part_path, path)
+}
+
+fn remove_download(path: &Path) {
+    for path in [path.to_path_buf(), path.with_extension("sha256")] {
+        if let Err(e) = fs::remove_file(&path)
+            && e.kind() != io::ErrorKind::NotFound
+        {
+            error!("failed removing {}: {}", path.display(), e);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpCache {
+    root: PathBuf,
+    pub settings: CacheSettings,
+}
+
+impl HttpCache {
+    pub fn new(root: &Path, settings: &CacheSettings) -> HttpCache {
+        Self {
+            root: root.to_path_buf(),
+            settings: settings.clone(),
+        }
+    }
+
+    fn response_path(&self, url: &str) -> PathBuf {
+        self.root
+            .join(RESPONSES_FOLDER)
+            .join(sha256_data(url.as_bytes()))
+    }
+
+    fn download_path(&self, url: &str) -> PathBuf {
+        self.root
+            .join(DOWNLOADS_FOLDER)
+            .join(sha256_data(url.as_bytes()))
+    }
+
+    /// Cached response for the URL, unreadable entries count as missing.
+    pub(crate) fn load(&self, url: &str) -> Option<CachedResponse> {
+        let path = self.response_path(url);
+        let meta = fs::read_to_string(path.with_extension("toml")).ok()?;
+        let meta: ResponseMeta = match toml::from_str(&meta) {
+            Ok(meta) => meta,
+            Err(e) => {
+                debug!("ignoring cache entry of {}: {}", url, e);
+                return None;
+            }
+        };
+        let body = fs::read(path.with_extension("body")).ok()?;
+
+        Some(CachedResponse { meta, body })
+    }
+
+    pub(crate) fn store(
+        &self,
+        url: &str,
+        etag: Option<String>,
+        last_modified: Option<String>,
+        body: &[u8],
+    ) -> io::Result<()> {
+        let path = self.response_path(url);
+        let meta = ResponseMeta {
+            url: url.to_string(),
+            etag,
+            last_modified,
+            fetched: now(),
+        };
+
+        write_file(&path.with_extension("body"), body)?;
+        self.write_meta(&path, &meta)
+    }
+
+    /// Marks the response as confirmed by ModDB, after it answered `304 Not Modified`.
+    pub(crate) fn touch(&self, url: &str, response: &CachedResponse) -> io::Result<()> {
+        let meta = ResponseMeta {
+            fetched: now(),
+            ..response.meta.clone()
+        };
+        self.write_meta(&self.response_path(url), &meta)
+    }
+
+    fn write_meta(&self, path: &Path, meta: &ResponseMeta) -> io::Result<()> {
+        let toml_data = toml::to_string_pretty(meta).map_err(io::Error::other)?;
+        write_file(&path.with_extension("toml"), toml_data.as_bytes())
+    }
+
+    /// Downloads that do not match their hash anymore are removed and count as missing.
+    pub(crate) fn load_download(&self, url: &str) -> Option<Vec<u8>> {
+        if !self.settings.keep_downloads {
+            return None;
+        }
+        let path = self.download_path(url);
+        let data = fs::read(&path).ok()?;
+        let hash = fs::read_to_string(path.with_extension("sha256")).unwrap_or_default();
+        if hash.trim() != sha256_data(&data) {
+            debug!("removing corrupt download of {}", url);
+            remove_download(&path);
+            return None;
+        }
+
+        // eviction goes by modification time
+        if let Err(e) = File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now()))
+        {
+            debug!("failed touching {}: {}", path.display(), e);
+        }
+        Some(data)
+    }
+
+    pub(crate) fn store_download(&self, url: &str, data: &[u8]) -> io::Result<()> {
+        if !self.settings.keep_downloads {
+            return Ok(());
+        }
+        let path = self.download_path(url);
+        write_file(&path, data)?;
+        write_file(&path.with_extension("sha256"), sha256_data(data).as_bytes())?;
+        self.evict_downloads()
+    }
+
+    /// Removes the least recently used downloads until they fit the size limit.
+    fn evict_downloads(&self) -> io::Result<()> {
+        let folder = self.root.join(DOWNLOADS_FOLDER);
+        let mut downloads = Vec::new();
+        for entry in fs::read_dir(folder)?.flatten() {
+            let path = entry.path();
+            if path.extension().is_some() {
+                continue;
+            }
+            let metadata = entry.metadata()?;
+            downloads.push((metadata.modified()?, metadata.len(), path));
+        }
+        downloads.sort();
+
+        let limit = self.settings.downloads_limit_mib * 1024 * 1024;
+        let mut size: u64 = downloads.iter().map(|(_, len, _)| len).sum();
+        for (_, len, path) in downloads {
+            if size <= limit {
+                break;
+            }
+            debug!("evicting {}", path.display());
+            remove_download(&path);
+            size -= len;
+        }
+        Ok(())
+    }
+
+    /// Removes all cached responses and downloads, returns the bytes freed.
+    pub fn clear(&self) -> io::Result<u64> {
+        let mut bytes = 0;
+        for folder in [RESPONSES_FOLDER, DOWNLOADS_FOLDER] {
+            let path = self.root.join(folder);
+            if !path.exists() {
+                continue;
+            }
+            for entry in fs::read_dir(&path)?.flatten() {
+                bytes += entry.metadata()?.len();
+            }
+            fs::remove_dir_all(path)?;
+        }
+
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn cache_roundtrip() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        let cache = HttpCache::new(root, &CacheSettings::default());
+        let url = "https://mods.vintagestory.at/api/mod/test";
+
+        assert!(cache.load(url).is_none());
+        cache
+            .store(url, Some("\"abc\"".to_string()), None, b"{}")
+            .unwrap();
+        let response = cache.load(url).unwrap();
+        assert_eq!(response.body, b"{}");
+        assert_eq!(response.etag(), Some("\"abc\""));
+        assert!(response.is_fresh(Duration::from_secs(60)));
+        assert!(!response.is_fresh(Duration::ZERO));
+
+        cache.store_download(url, b"zip").unwrap();
+        assert_eq!(cache.load_download(url).unwrap(), b"zip");
+
+        assert!(cache.clear().unwrap() > 0);
+        assert!(cache.load(url).is_none());
+    }
+
+    #[test]
+    fn downloads_checked_and_evicted() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        let settings = CacheSettings {
+            downloads_limit_mib: 1,
+            ..CacheSettings::default()
+        };
+        let cache = HttpCache::new(root, &settings);
+        let [first, second] = ["first", "second"].map(|name| format!("https://mods/{}.zip", name));
+
+        cache.store_download(&first, b"zip").unwrap();
+        fs::write(cache.download_path(&first), b"zap").unwrap();
+        assert!(cache.load_download(&first).is_none());
+        assert!(!cache.download_path(&first).exists());
+
+        let data = vec![0; 600 * 1024];
+        cache.store_download(&first, &data).unwrap();
+        // modification times can be too coarse to order two writes in a row
+        File::options()
+            .write(true)
+            .open(cache.download_path(&first))
+            .unwrap()
+            .set_modified(UNIX_EPOCH)
+            .unwrap();
+        cache.store_download(&second, &data).unwrap();
+        assert!(cache.load_download(&first).is_none());
+        assert_eq!(cache.load_download(&second).unwrap(), data);
+    }
+}
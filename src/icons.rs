@@ -1,25 +1,32 @@
-use iced::widget::image;
 use log::{debug, error, info};
 use std::env;
 use std::fs::create_dir_all;
+use std::path::PathBuf;
 
 pub const DEFAULT_ICON: &[u8] = include_bytes!("../assets/default.png");
 pub const ICONS_FOLDER: &str = "icons";
 
-pub fn load_icon(name: &Option<String>) -> image::Handle {
-    if let Some(name) = name {
-        let root = env::current_dir().expect("Failed to get CWD");
-        let path = root.join("icons").join(name);
-        if path.is_file() {
-            return path.strip_prefix(root).expect("failed rel path").into();
-        }
-    }
-    image::Handle::from_bytes(DEFAULT_ICON)
+/// Path of an icon relative to the CWD, `None` if no icon is set or the file is missing.
+pub fn icon_path(name: &Option<String>) -> Option<PathBuf> {
+    let name = name.as_ref()?;
+    let root = env::current_dir().ok()?;
+    let path = root.join(ICONS_FOLDER).join(name);
+
+    path.is_file().then(|| {
+        path.strip_prefix(root)
+            .expect("failed rel path")
+            .to_path_buf()
+    })
 }
 
 pub fn load_icons() -> Vec<String> {
-    let root = env::current_dir().expect("Failed to get CWD");
-    let icons_folder = root.join(ICONS_FOLDER);
+    let icons_folder = match env::current_dir() {
+        Ok(root) => root.join(ICONS_FOLDER),
+        Err(e) => {
+            error!("failed reading icons: {}", e);
+            return Vec::new();
+        }
+    };
 
     if let Err(e) = create_dir_all(&icons_folder) {
         debug!("failed to ensure icons folder: {}", e);
@@ -1,15 +1,56 @@
-use crate::mods::{ModInfo, load_mods};
+use crate::archive::{unzip, zip_dir};
+use crate::backups::BackupSettings;
+use crate::game::{GameVersion, detect_version};
+use crate::mod_config::{MOD_CONFIG_FOLDER, config_files, find_config};
+use crate::mods::{ModInfo, ModSide, load_mods};
+use crate::server_config::{SERVER_CONFIG, WHITELIST};
+use crate::settings::CLIENT_SETTINGS;
+use crate::worlds::SAVES_FOLDER;
 use filenamify::filenamify;
 use log::{debug, error, info};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
 use std::ffi::{OsStr, OsString};
 use std::fs::create_dir_all;
-use std::path::PathBuf;
-use std::{env, fs};
+use std::path::{Path, PathBuf};
+use std::{env, fmt, fs, io};
 
 const INSTANCE_FOLDER: &str = "instances";
 const INSTANCE_TOML: &str = "instance.toml";
-const MODS_FOLDER: &str = "Mods";
+pub const MODS_FOLDER: &str = "Mods";
+const DATA_MARKERS: [&str; 4] = [
+    CLIENT_SETTINGS,
+    SERVER_CONFIG,
+    SAVES_FOLDER,
+    MOD_CONFIG_FOLDER,
+];
+
+/// Client instances start the game, server instances the dedicated server.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InstanceKind {
+    #[default]
+    Client,
+    Server,
+}
+
+impl InstanceKind {
+    pub const ALL: [InstanceKind; 2] = [InstanceKind::Client, InstanceKind::Server];
+
+    pub fn is_client(&self) -> bool {
+        *self == InstanceKind::Client
+    }
+}
+
+impl fmt::Display for InstanceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstanceKind::Client => write!(f, "Client"),
+            InstanceKind::Server => write!(f, "Server"),
+        }
+    }
+}
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Instance {
@@ -19,9 +60,31 @@ pub struct Instance {
     #[serde(skip)]
     pub mods: Vec<ModInfo>,
 
+    #[serde(skip)]
+    pub game_version: Option<GameVersion>,
+
     pub name: String,
     pub icon: Option<String>,
+    /// `Vintagestory` for clients, `VintagestoryServer` for servers.
     pub game_exe_path: Option<PathBuf>,
+
+    #[serde(default, skip_serializing_if = "InstanceKind::is_client")]
+    pub kind: InstanceKind,
+
+    /// Dotted clientsettings.json keys copied from the global config at every launch.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sync_settings: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "BackupSettings::is_default")]
+    pub backups: BackupSettings,
+
+    /// Extra mod folders passed as `--addModPath`, e.g. mods in development.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub add_mod_paths: Vec<PathBuf>,
+
+    /// Environment variables set for the game.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
 }
 
 impl Instance {
@@ -32,12 +95,214 @@ impl Instance {
     pub fn mods_count(&self) -> usize {
         self.mods.len()
     }
+
+    pub fn install_dir(&self) -> Option<&Path> {
+        self.game_exe_path.as_deref().and_then(Path::parent)
+    }
+
+    pub fn is_server(&self) -> bool {
+        self.kind == InstanceKind::Server
+    }
+
+    /// Adopted with [`AdoptMode::Link`], the folder is the data folder of another install.
+    pub fn is_linked(&self) -> bool {
+        fs::symlink_metadata(self.path()).is_ok_and(|metadata| metadata.is_symlink())
+    }
+
+    /// Dedicated servers do not load client-only mods.
+    pub fn loads_side(&self, side: ModSide) -> bool {
+        !(self.is_server() && side == ModSide::Client)
+    }
+
+    /// Mods the game loads, see [`Instance::loads_side`].
+    pub fn active_mods(&self) -> impl Iterator<Item = &ModInfo> {
+        self.mods
+            .iter()
+            .filter(|mod_info| self.loads_side(mod_info.side))
+    }
+
+    /// Loaded mods that do not match the game version, empty if the version is unknown.
+    pub fn incompatible_mods(&self) -> Vec<&ModInfo> {
+        let Some(game_version) = &self.game_version else {
+            return Vec::new();
+        };
+
+        self.active_mods()
+            .filter(|mod_info| !mod_info.compatibility(game_version).is_compatible())
+            .collect()
+    }
+
+    pub fn detect_game_version(&mut self) {
+        self.game_version = self.install_dir().and_then(detect_version);
+    }
+
+    /// Reads the `Mods` folder again after mods were changed.
+    pub fn reload_mods(&mut self) -> io::Result<()> {
+        self.mods = load_mods(&self.mods_path())?;
+        self.link_mod_configs();
+        Ok(())
+    }
+
+    pub fn link_mod_configs(&mut self) {
+        let config_files = config_files(&self.path());
+        for mod_info in &mut self.mods {
+            mod_info.config_path = find_config(&config_files, mod_info).cloned();
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum InstanceError {
     TomlMissing,
-    TomlParseError,
+    TomlParseError(toml::de::Error),
+    TomlWriteError(toml::ser::Error),
+    AlreadyExists,
+    NotDataFolder,
+    /// Recreating was asked for a folder whose instance.toml is fine.
+    NotRepairable,
+    Io(io::Error),
+}
+
+impl fmt::Display for InstanceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstanceError::TomlMissing => write!(f, "{} is missing", INSTANCE_TOML),
+            InstanceError::TomlParseError(e) => write!(f, "invalid {}: {}", INSTANCE_TOML, e),
+            InstanceError::TomlWriteError(e) => {
+                write!(f, "failed writing {}: {}", INSTANCE_TOML, e)
+            }
+            InstanceError::AlreadyExists => write!(f, "instance folder already exists"),
+            InstanceError::NotDataFolder => write!(f, "not a Vintage Story data folder"),
+            InstanceError::NotRepairable => {
+                write!(
+                    f,
+                    "{} is valid, the folder has another problem",
+                    INSTANCE_TOML
+                )
+            }
+            InstanceError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for InstanceError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            InstanceError::TomlMissing
+            | InstanceError::AlreadyExists
+            | InstanceError::NotDataFolder
+            | InstanceError::NotRepairable => None,
+            InstanceError::TomlParseError(e) => Some(e),
+            InstanceError::TomlWriteError(e) => Some(e),
+            InstanceError::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for InstanceError {
+    fn from(e: io::Error) -> Self {
+        InstanceError::Io(e)
+    }
+}
+
+/// Folder in `instances` that could not be loaded as an instance.
+#[derive(Debug)]
+pub struct BrokenInstance {
+    pub folder_name: OsString,
+    pub error: InstanceError,
+    pub has_toml: bool,
+    /// A Vintage Story data folder which was not created by Rustic.
+    pub is_data_folder: bool,
+}
+
+impl BrokenInstance {
+    fn new(folder_name: OsString, error: InstanceError) -> BrokenInstance {
+        let path = instance_path(&folder_name);
+        let has_toml = path.join(INSTANCE_TOML).is_file();
+        Self {
+            is_data_folder: !has_toml && is_data_folder(&path),
+            folder_name,
+            error,
+            has_toml,
+        }
+    }
+
+    pub fn folder_name_string(&self) -> String {
+        self.folder_name.to_string_lossy().to_string()
+    }
+
+    pub fn path(&self) -> PathBuf {
+        instance_path(&self.folder_name)
+    }
+
+    pub fn toml_path(&self) -> PathBuf {
+        self.path().join(INSTANCE_TOML)
+    }
+
+    /// Only a missing or invalid instance.toml is replaced, a valid one keeps settings that
+    /// would be lost.
+    pub fn can_recreate(&self) -> bool {
+        matches!(
+            self.error,
+            InstanceError::TomlMissing | InstanceError::TomlParseError(_)
+        )
+    }
+
+    /// Writes a fresh instance.toml named after the folder, an invalid one is kept as `.bak`.
+    pub fn recreate(&self) -> Result<Instance, InstanceError> {
+        if !self.can_recreate() {
+            return Err(InstanceError::NotRepairable);
+        }
+        if self.toml_path().is_file() {
+            fs::rename(
+                self.toml_path(),
+                self.toml_path().with_extension("toml.bak"),
+            )?;
+        }
+
+        let mut instance = Instance::new(&self.folder_name_string());
+        instance.folder_name = self.folder_name.clone();
+        instance.kind = detect_kind(&self.path());
+        instance.save()?;
+
+        Instance::load(&self.folder_name)
+    }
+
+    /// Turns an existing data folder into an instance, keeping all its content.
+    pub fn adopt(&self) -> Result<Instance, InstanceError> {
+        create_dir_all(self.path().join(MODS_FOLDER))?;
+        self.recreate()
+    }
+}
+
+pub fn is_data_folder(path: &Path) -> bool {
+    DATA_MARKERS.iter().any(|marker| path.join(marker).exists())
+}
+
+/// Data folders of dedicated servers have a serverconfig.json but no clientsettings.json.
+pub fn detect_kind(path: &Path) -> InstanceKind {
+    if path.join(SERVER_CONFIG).is_file() && !path.join(CLIENT_SETTINGS).exists() {
+        InstanceKind::Server
+    } else {
+        InstanceKind::Client
+    }
+}
+
+/// Names a shell accepts for variables, `[A-Za-z_][A-Za-z0-9_]*`.
+pub fn is_env_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn instances_root() -> io::Result<PathBuf> {
+    Ok(env::current_dir()?.join(INSTANCE_FOLDER))
+}
+
+/// Relative to the CWD if it cannot be read, using the path then fails with an IO error.
+fn instance_path(folder_name: &OsStr) -> PathBuf {
+    instances_root()
+        .unwrap_or_else(|_| PathBuf::from(INSTANCE_FOLDER))
+        .join(folder_name)
 }
 
 impl Instance {
@@ -47,84 +312,237 @@ impl Instance {
             name: name.to_string(),
             icon: None,
             game_exe_path: None,
+            kind: InstanceKind::Client,
             mods: Vec::new(),
+            game_version: None,
+            sync_settings: Vec::new(),
+            backups: BackupSettings::default(),
+            add_mod_paths: Vec::new(),
+            env: BTreeMap::new(),
         }
     }
 
     pub fn load(folder_name: &OsStr) -> Result<Instance, InstanceError> {
-        let root = env::current_dir().expect("Failed to get CWD");
-        let instance_path = root.join(INSTANCE_FOLDER).join(folder_name);
+        let instance_path = instances_root()?.join(folder_name);
         let toml_path = instance_path.join(INSTANCE_TOML);
-        let mods_path = instance_path.join(MODS_FOLDER);
 
-        toml_path
-            .try_exists()
-            .map_err(|_| InstanceError::TomlMissing)?;
-        let toml_data = fs::read_to_string(toml_path).map_err(|_| InstanceError::TomlParseError)?;
+        if !toml_path.try_exists()? {
+            return Err(InstanceError::TomlMissing);
+        }
+        let toml_data = fs::read_to_string(toml_path)?;
 
         let mut instance: Instance =
-            toml::from_str(&toml_data).map_err(|_| InstanceError::TomlParseError)?;
+            toml::from_str(&toml_data).map_err(InstanceError::TomlParseError)?;
 
         instance.folder_name = folder_name.to_os_string();
-        instance.mods = load_mods(&mods_path);
+        instance.reload_mods()?;
+        instance.detect_game_version();
 
         Ok(instance)
     }
 
-    pub fn save(&self) {
-        // TODO: show UI message if errors
-        create_dir_all(self.path()).expect("Failed to create instance dir");
+    /// Writes instance.toml. Callers regenerate the launchers, see
+    /// [`crate::shortcuts::update_shortcuts`].
+    pub fn save(&self) -> Result<(), InstanceError> {
+        create_dir_all(self.path())?;
+
+        let toml_data = toml::to_string_pretty(self).map_err(InstanceError::TomlWriteError)?;
+        fs::write(self.toml_path(), toml_data)?;
 
-        let toml_data = toml::to_string_pretty(self).unwrap();
-        fs::write(self.toml_path(), toml_data).unwrap();
+        Ok(())
+    }
+
+    /// Saves a new instance, an existing folder is never taken over.
+    pub fn create(&self) -> Result<(), InstanceError> {
+        if self.path().exists() {
+            return Err(InstanceError::AlreadyExists);
+        }
+        self.save()
     }
 
     pub fn path(&self) -> PathBuf {
-        let root = env::current_dir().expect("Failed to get CWD");
+        instance_path(&self.folder_name)
+    }
 
-        root.join(INSTANCE_FOLDER).join(&self.folder_name)
+    /// Fails instead of falling back to a relative path like [`Instance::path`], for paths
+    /// handed to the game, which runs in its install folder.
+    pub fn absolute_path(&self) -> io::Result<PathBuf> {
+        Ok(instances_root()?.join(&self.folder_name))
     }
 
     pub fn toml_path(&self) -> PathBuf {
         self.path().join(INSTANCE_TOML)
     }
+
+    pub fn mods_path(&self) -> PathBuf {
+        self.path().join(MODS_FOLDER)
+    }
+
+    pub fn settings_path(&self) -> PathBuf {
+        self.path().join(CLIENT_SETTINGS)
+    }
+
+    pub fn server_config_path(&self) -> PathBuf {
+        self.path().join(SERVER_CONFIG)
+    }
+
+    pub fn saves_path(&self) -> PathBuf {
+        self.path().join(SAVES_FOLDER)
+    }
+
+    pub fn whitelist_path(&self) -> PathBuf {
+        self.path().join(WHITELIST)
+    }
+
+    /// Zips the whole instance folder, including instance.toml, mods and game data.
+    pub fn export(&self, zip_path: &Path) -> io::Result<()> {
+        zip_dir(&self.path(), zip_path)
+    }
 }
 
-pub fn load_instances() -> Vec<Instance> {
-    let root = env::current_dir().expect("Failed to get CWD");
-    let instances_folder = root.join(INSTANCE_FOLDER);
+/// Unpacks an exported instance into a new instance folder named after `name`.
+pub fn import_instance(zip_path: &Path, name: &str) -> Result<Instance, InstanceError> {
+    let mut instance = Instance::new(name);
+    if instance.path().exists() {
+        return Err(InstanceError::AlreadyExists);
+    }
+
+    unzip(zip_path, &instance.path())?;
+    if !instance.toml_path().is_file() {
+        fs::remove_dir_all(instance.path())?;
+        return Err(InstanceError::TomlMissing);
+    }
+
+    instance = Instance::load(&instance.folder_name)?;
+    instance.name = name.to_string();
+    instance.save()?;
+
+    Ok(instance)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdoptMode {
+    Copy,
+    Link,
+}
+
+/// Creates an instance from an existing data folder, either as copy or as link to the folder.
+/// Linked folders are changed in place: instance.toml and the `Mods` folder are written into
+/// the original folder and mods installed through Rustic land there. Rustic leaves their
+/// `modPaths` alone and never moves their mods into the store.
+pub fn adopt_data_folder(
+    source: &Path,
+    name: &str,
+    mode: AdoptMode,
+) -> Result<Instance, InstanceError> {
+    let mut instance = Instance::new(name);
+    if instance.path().exists() {
+        return Err(InstanceError::AlreadyExists);
+    }
+    if !is_data_folder(source) {
+        return Err(InstanceError::NotDataFolder);
+    }
+    instance.kind = detect_kind(source);
+
+    match mode {
+        AdoptMode::Copy => copy_dir(source, &instance.path())?,
+        AdoptMode::Link => link_dir(source, &instance.path())?,
+    }
+    create_dir_all(instance.mods_path())?;
+    instance.save()?;
+
+    Instance::load(&instance.folder_name)
+}
+
+fn copy_dir(source: &Path, target: &Path) -> io::Result<()> {
+    create_dir_all(target)?;
+    for entry in fs::read_dir(source)?.flatten() {
+        let target = target.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
 
-    if let Err(e) = create_dir_all(&instances_folder) {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn link_dir(source: &Path, target: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(source, target)
+}
+
+#[cfg(windows)]
+fn link_dir(source: &Path, target: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_dir(source, target)
+}
+
+/// Finds an instance by folder name or display name.
+pub fn find_instance(query: &str) -> Option<Instance> {
+    let (instances, _) = load_instances();
+    instances
+        .into_iter()
+        .find(|instance| instance.folder_name == query || instance.name == query)
+}
+
+/// Every folder in `instances`, including the ones that fail to load.
+pub fn instance_folders() -> io::Result<Vec<PathBuf>> {
+    let instances_folder = instances_root()?;
+    if !instances_folder.exists() {
+        return Ok(Vec::new());
+    }
+
+    Ok(instances_folder
+        .read_dir()?
+        .flatten()
+        .map(|entry| entry.path())
+        // follows links, adopted data folders can be linked
+        .filter(|path| path.is_dir())
+        .collect())
+}
+
+/// Loads all instance folders, folders that fail to load are returned with their error.
+pub fn load_instances() -> (Vec<Instance>, Vec<BrokenInstance>) {
+    if let Err(e) = instances_root().and_then(create_dir_all) {
         debug!("failed to ensure instances folder: {}", e);
-        return Vec::new();
-    }
-
-    let instances: Result<Vec<_>, _> = instances_folder.read_dir().map(|read_dir| {
-        read_dir
-            .flatten()
-            .filter(|entry| entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false))
-            .map(|folder_entry| folder_entry.file_name())
-            .flat_map(|folder_name| match Instance::load(&folder_name) {
-                Ok(instance) => {
-                    debug!("loaded {}", folder_name.display());
-                    Some(instance)
-                }
-                Err(e) => {
-                    error!("failed loading {}: {:?}", folder_name.display(), e);
-                    None
-                }
-            })
+        return (Vec::new(), Vec::new());
+    }
+
+    let folder_names: Result<Vec<_>, _> = instance_folders().map(|paths| {
+        paths
+            .into_iter()
+            .filter_map(|path| Some(path.file_name()?.to_os_string()))
             .collect()
     });
 
-    match instances {
-        Ok(instances) => {
-            info!("loaded {} instances", instances.len());
-            instances
-        }
+    let folder_names = match folder_names {
+        Ok(folder_names) => folder_names,
         Err(e) => {
             error!("failed reading instances folder: {}", e);
-            Vec::new()
+            return (Vec::new(), Vec::new());
+        }
+    };
+
+    let mut instances = Vec::new();
+    let mut broken = Vec::new();
+    for folder_name in folder_names {
+        match Instance::load(&folder_name) {
+            Ok(instance) => {
+                debug!("loaded {}", folder_name.display());
+                instances.push(instance);
+            }
+            Err(error) => {
+                error!("failed loading {}: {}", folder_name.display(), error);
+                broken.push(BrokenInstance::new(folder_name, error));
+            }
         }
     }
+
+    info!(
+        "loaded {} instances, {} failed",
+        instances.len(),
+        broken.len()
+    );
+    (instances, broken)
 }
@@ -1 +1,164 @@
+use crate::backups::{Backup, BackupTrigger, back_up_on};
+use crate::console::{ConsoleEvent, ServerConsole};
+use crate::instance::Instance;
+use crate::server_config::set_server_mod_paths;
+use crate::settings::{
+    SettingsError, global_settings_path, read_json, set_mod_paths, sync_keys, write_json,
+};
+use log::{debug, error, info};
+use std::error::Error;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::{fmt, io, thread};
+use tokio::sync::mpsc::UnboundedReceiver;
 
+#[derive(Debug)]
+pub enum LaunchError {
+    NoGameExe,
+    BackupError(io::Error),
+    SettingsError(SettingsError),
+    SpawnError(PathBuf, io::Error),
+}
+
+impl fmt::Display for LaunchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LaunchError::NoGameExe => write!(f, "no game executable set"),
+            LaunchError::BackupError(e) => write!(f, "failed backing up saves: {}", e),
+            LaunchError::SettingsError(e) => write!(f, "failed preparing settings: {}", e),
+            LaunchError::SpawnError(path, e) => {
+                write!(f, "failed starting {}: {}", path.display(), e)
+            }
+        }
+    }
+}
+
+impl Error for LaunchError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LaunchError::NoGameExe => None,
+            LaunchError::BackupError(e) => Some(e),
+            LaunchError::SettingsError(e) => Some(e),
+            LaunchError::SpawnError(_, e) => Some(e),
+        }
+    }
+}
+
+/// Backs up the saves if the instance asks for it before launch. Separate from [`launch`] as
+/// zipping large worlds takes a while, the GUI runs it in the background.
+pub fn back_up_before_launch(instance: &Instance) -> Result<Option<Backup>, LaunchError> {
+    back_up_on(instance, BackupTrigger::Launch).map_err(LaunchError::BackupError)
+}
+
+/// Starts the game, call [`back_up_before_launch`] first.
+pub fn launch(instance: &Instance) -> Result<Child, LaunchError> {
+    launch_command(instance)?
+        .spawn()
+        .map_err(|e| spawn_error(instance, e))
+}
+
+/// Waits for a game started by [`launch`] on a background thread, so it doesn't stay a zombie
+/// once closed while Rustic keeps running.
+pub fn reap_in_background(mut child: Child) {
+    thread::spawn(move || match child.wait() {
+        Ok(status) => debug!("game {} exited with {}", child.id(), status),
+        Err(e) => error!("failed waiting for game {}: {}", child.id(), e),
+    });
+}
+
+/// Starts a server instance with piped stdio instead of inheriting the one of Rustic, for the
+/// console in the GUI.
+pub fn launch_server(
+    instance: &Instance,
+) -> Result<(ServerConsole, UnboundedReceiver<ConsoleEvent>), LaunchError> {
+    let child = launch_command(instance)?
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| spawn_error(instance, e))?;
+
+    Ok(ServerConsole::attach(child))
+}
+
+fn spawn_error(instance: &Instance, e: io::Error) -> LaunchError {
+    LaunchError::SpawnError(instance.game_exe_path.clone().unwrap_or_default(), e)
+}
+
+/// Prepares the instance folder and builds the command to start it.
+fn launch_command(instance: &Instance) -> Result<Command, LaunchError> {
+    let Some(game_exe_path) = &instance.game_exe_path else {
+        return Err(LaunchError::NoGameExe);
+    };
+    let data_path = instance
+        .absolute_path()
+        .map_err(|e| spawn_error(instance, e))?;
+
+    if instance.is_server() {
+        prepare_server_config(instance).map_err(LaunchError::SettingsError)?;
+    } else {
+        prepare_settings(instance).map_err(LaunchError::SettingsError)?;
+    }
+
+    let mut command = Command::new(game_exe_path);
+    command.arg("--dataPath").arg(data_path);
+    for mod_path in &instance.add_mod_paths {
+        command.arg("--addModPath").arg(mod_path);
+    }
+    command.envs(&instance.env);
+    if let Some(install_dir) = instance.install_dir() {
+        command.current_dir(install_dir);
+    }
+
+    info!("launching {}: {:?}", instance.folder_name_string(), command);
+    Ok(command)
+}
+
+/// Syncs the configured keys from the global config and points `modPaths` at the instance
+/// mods, in case the instance folder was moved. Linked instances keep their `modPaths`, the
+/// other install uses them as well. Without clientsettings.json the game creates a default
+/// one on first start.
+pub fn prepare_settings(instance: &Instance) -> Result<(), SettingsError> {
+    let settings_path = instance.settings_path();
+    if !settings_path.is_file() {
+        debug!("no {} yet", settings_path.display());
+        return Ok(());
+    }
+
+    let mut settings = read_json(&settings_path)?;
+    if !instance.sync_settings.is_empty() {
+        match global_settings_path().filter(|path| path.is_file()) {
+            Some(global_path) => {
+                sync_keys(
+                    &read_json(&global_path)?,
+                    &mut settings,
+                    &instance.sync_settings,
+                );
+            }
+            None => debug!("no global settings to sync from"),
+        }
+    }
+    if !instance.is_linked() {
+        set_mod_paths(&mut settings, &instance.mods_path());
+    }
+
+    write_json(&settings_path, &settings)
+}
+
+/// Points `ModPaths` of serverconfig.json at the instance mods, the server creates a default
+/// config on first start. Linked instances keep theirs, see [`prepare_settings`].
+pub fn prepare_server_config(instance: &Instance) -> Result<(), SettingsError> {
+    let config_path = instance.server_config_path();
+    if instance.is_linked() {
+        return Ok(());
+    }
+    if !config_path.is_file() {
+        debug!("no {} yet", config_path.display());
+        return Ok(());
+    }
+
+    let mut config = read_json(&config_path)?;
+    set_server_mod_paths(&mut config, &instance.mods_path());
+
+    write_json(&config_path, &config)
+}
@@ -0,0 +1,181 @@
+use crate::api::ModDb;
+use crate::http_cache::{HttpCache, cache_root};
+use crate::store::LinkMode;
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::{env, fmt, fs, io};
+
+pub const LAUNCHER_TOML: &str = "rustic.toml";
+
+/// Shared mod store, see [`crate::store`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StoreSettings {
+    pub enabled: bool,
+    pub link_mode: LinkMode,
+}
+
+/// ModDB response and download cache, see [`crate::http_cache`]. Responses older than their
+/// TTL are revalidated with ModDB, and still used while it cannot be reached.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CacheSettings {
+    pub enabled: bool,
+    /// Mod pages with their releases.
+    #[serde(with = "humantime_serde")]
+    pub mods_ttl: Duration,
+    /// Search results and tags.
+    #[serde(with = "humantime_serde")]
+    pub search_ttl: Duration,
+    /// Logos and screenshots.
+    #[serde(with = "humantime_serde")]
+    pub files_ttl: Duration,
+    /// Keep downloaded mod files, releases never change once uploaded.
+    pub keep_downloads: bool,
+    /// Size limit of kept downloads in MiB, the least recently used are removed above it.
+    pub downloads_limit_mib: u64,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            mods_ttl: Duration::from_secs(15 * 60),
+            search_ttl: Duration::from_secs(60 * 60),
+            files_ttl: Duration::from_secs(7 * 24 * 60 * 60),
+            keep_downloads: true,
+            downloads_limit_mib: 1024,
+        }
+    }
+}
+
+/// Limits for ModDB requests, see [`crate::throttle`]. Throttled (429) and failed (5xx)
+/// requests are retried with exponential backoff.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RequestSettings {
+    pub max_concurrent: usize,
+    pub per_second: u32,
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for each further one.
+    #[serde(with = "humantime_serde")]
+    pub backoff: Duration,
+    #[serde(with = "humantime_serde")]
+    pub max_backoff: Duration,
+    /// Whole request including the body, a hung connection counts as failed.
+    #[serde(with = "humantime_serde")]
+    pub timeout: Duration,
+}
+
+impl Default for RequestSettings {
+    fn default() -> Self {
+        Self {
+            max_concurrent: 4,
+            per_second: 10,
+            max_retries: 4,
+            backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Launchers for instances outside of Rustic, see [`crate::shortcuts`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ShortcutSettings {
+    /// `.desktop` files in the applications menu, Linux only.
+    pub desktop_entries: bool,
+    /// `launch.sh` in each instance folder, runs the game without Rustic.
+    pub launch_scripts: bool,
+}
+
+/// Settings of Rustic itself, stored in `rustic.toml` next to the `instances` folder.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LauncherSettings {
+    pub mod_store: StoreSettings,
+    pub cache: CacheSettings,
+    pub requests: RequestSettings,
+    pub shortcuts: ShortcutSettings,
+}
+
+#[derive(Debug)]
+pub enum LauncherSettingsError {
+    TomlParseError(toml::de::Error),
+    TomlWriteError(toml::ser::Error),
+    Io(io::Error),
+}
+
+impl fmt::Display for LauncherSettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LauncherSettingsError::TomlParseError(e) => {
+                write!(f, "invalid {}: {}", LAUNCHER_TOML, e)
+            }
+            LauncherSettingsError::TomlWriteError(e) => {
+                write!(f, "failed writing {}: {}", LAUNCHER_TOML, e)
+            }
+            LauncherSettingsError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for LauncherSettingsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LauncherSettingsError::TomlParseError(e) => Some(e),
+            LauncherSettingsError::TomlWriteError(e) => Some(e),
+            LauncherSettingsError::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for LauncherSettingsError {
+    fn from(e: io::Error) -> Self {
+        LauncherSettingsError::Io(e)
+    }
+}
+
+fn launcher_toml_path() -> io::Result<PathBuf> {
+    Ok(env::current_dir()?.join(LAUNCHER_TOML))
+}
+
+impl LauncherSettings {
+    /// Missing settings are the defaults.
+    pub fn load() -> Result<LauncherSettings, LauncherSettingsError> {
+        let path = launcher_toml_path()?;
+        if !path.is_file() {
+            return Ok(LauncherSettings::default());
+        }
+
+        toml::from_str(&fs::read_to_string(path)?).map_err(LauncherSettingsError::TomlParseError)
+    }
+
+    /// ModDB client using the cache and request settings, without the cache if it has no
+    /// folder.
+    pub fn moddb(&self) -> ModDb {
+        let moddb = ModDb::default().with_limits(&self.requests);
+        if !self.cache.enabled {
+            return moddb;
+        }
+        match cache_root() {
+            Ok(root) => moddb.with_cache(HttpCache::new(&root, &self.cache)),
+            Err(e) => {
+                error!("not caching ModDB: {}", e);
+                moddb
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<(), LauncherSettingsError> {
+        let toml_data =
+            toml::to_string_pretty(self).map_err(LauncherSettingsError::TomlWriteError)?;
+        fs::write(launcher_toml_path()?, toml_data)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,31 @@
+//! Instance, mod and launch logic of Rustic, without any GUI dependencies.
+//!
+//! Everything works relative to the current working directory, which holds the `instances`
+//! and `icons` folders. The `rustic-vs` binary (GUI and CLI) is built on top of this crate,
+//! use it with `default-features = false` to skip the GUI dependencies.
+
+pub mod api;
+pub mod archive;
+pub mod backups;
+pub mod console;
+pub mod game;
+pub mod http_cache;
+pub mod icons;
+pub mod instance;
+pub mod launcher;
+pub mod launcher_settings;
+pub mod lockfile;
+pub mod mod_config;
+pub mod mod_list;
+pub mod mods;
+pub mod server_config;
+pub mod server_sync;
+pub mod settings;
+pub mod shortcuts;
+pub mod snapshots;
+pub mod store;
+#[cfg(test)]
+mod test_util;
+pub mod throttle;
+pub mod updates;
+pub mod worlds;
@@ -0,0 +1,347 @@
+use crate::api::{ApiError, ModDb};
+use crate::instance::{Instance, MODS_FOLDER};
+use crate::mods::{ModInfo, mod_files, replace_mod_file, sha256_data, sha256_file};
+use crate::snapshots::{before_mods_change, stored_object};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::{fmt, fs, io};
+
+pub const INSTANCE_LOCK: &str = "instance.lock";
+
+/// Mod file pinned by the lockfile.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LockedMod {
+    #[serde(default)]
+    pub mod_id: String,
+    #[serde(default)]
+    pub version: String,
+    pub file: String,
+    pub size: u64,
+    pub sha256: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub release_id: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Lockfile {
+    #[serde(rename = "mod", default)]
+    pub mods: Vec<LockedMod>,
+}
+
+/// Difference between the lockfile and the `Mods` folder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Drift {
+    Missing(LockedMod),
+    Changed(LockedMod),
+    Extra(String),
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Drift::Missing(locked) => write!(f, "missing {}", locked.file),
+            Drift::Changed(locked) => write!(f, "changed {}", locked.file),
+            Drift::Extra(file) => write!(f, "not locked {}", file),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum LockError {
+    LockMissing,
+    TomlParseError(toml::de::Error),
+    TomlWriteError(toml::ser::Error),
+    NotDownloadable(String),
+    HashMismatch(String),
+    ApiError(ApiError),
+    Io(io::Error),
+}
+
+impl fmt::Display for LockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockError::LockMissing => write!(f, "{} is missing", INSTANCE_LOCK),
+            LockError::TomlParseError(e) => write!(f, "invalid {}: {}", INSTANCE_LOCK, e),
+            LockError::TomlWriteError(e) => write!(f, "failed writing {}: {}", INSTANCE_LOCK, e),
+            LockError::NotDownloadable(file) => {
+                write!(f, "{} has no ModDB release and no stored copy", file)
+            }
+            LockError::HashMismatch(file) => write!(f, "download of {} does not match", file),
+            LockError::ApiError(e) => write!(f, "{}", e),
+            LockError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for LockError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LockError::LockMissing | LockError::NotDownloadable(_) | LockError::HashMismatch(_) => {
+                None
+            }
+            LockError::TomlParseError(e) => Some(e),
+            LockError::TomlWriteError(e) => Some(e),
+            LockError::ApiError(e) => Some(e),
+            LockError::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for LockError {
+    fn from(e: io::Error) -> Self {
+        LockError::Io(e)
+    }
+}
+
+fn lock_path(instance: &Instance) -> PathBuf {
+    instance.path().join(INSTANCE_LOCK)
+}
+
+pub fn read_lockfile(instance: &Instance) -> Result<Lockfile, LockError> {
+    let path = lock_path(instance);
+    if !path.is_file() {
+        return Err(LockError::LockMissing);
+    }
+
+    toml::from_str(&fs::read_to_string(path)?).map_err(LockError::TomlParseError)
+}
+
+pub fn write_lockfile(instance: &Instance, lockfile: &Lockfile) -> Result<(), LockError> {
+    let toml_data = toml::to_string_pretty(lockfile).map_err(LockError::TomlWriteError)?;
+    fs::write(lock_path(instance), toml_data)?;
+
+    Ok(())
+}
+
+/// Pins the current `Mods` folder, release ids of unchanged files are kept from the old
+/// lockfile.
+pub fn lock_mods(instance: &Instance) -> Result<Lockfile, LockError> {
+    let previous = read_lockfile(instance).unwrap_or_default();
+    lock_folder(&instance.mods_path(), &instance.mods, &previous)
+}
+
+fn lock_folder(
+    mods_path: &Path,
+    installed: &[ModInfo],
+    previous: &Lockfile,
+) -> Result<Lockfile, LockError> {
+    let mut mods = Vec::new();
+    for (file, path) in mod_files(mods_path)? {
+        let sha256 = sha256_file(&path)?;
+        let mod_info = installed.iter().find(|mod_info| mod_info.zip_name == *file);
+        mods.push(LockedMod {
+            mod_id: mod_info.map(|m| m.mod_id.clone()).unwrap_or_default(),
+            version: mod_info.map(|m| m.version.clone()).unwrap_or_default(),
+            release_id: previous
+                .mods
+                .iter()
+                .find(|locked| locked.sha256 == sha256)
+                .and_then(|locked| locked.release_id),
+            size: fs::metadata(&path)?.len(),
+            file,
+            sha256,
+        });
+    }
+
+    Ok(Lockfile { mods })
+}
+
+/// Looks up ModDB releases by mod id and version for mods without a release id. Mods ModDB
+/// does not know stay without one.
+pub async fn resolve_release_ids(moddb: &ModDb, lockfile: &mut Lockfile) -> Result<(), ApiError> {
+    for locked in &mut lockfile.mods {
+        if locked.release_id.is_some() || locked.mod_id.is_empty() {
+            continue;
+        }
+
+        if let Some(mod_db_mod) = moddb.get_mod(&locked.mod_id).await? {
+            locked.release_id = mod_db_mod
+                .releases
+                .iter()
+                .find(|release| release.version == locked.version)
+                .map(|release| release.id);
+        }
+        debug!("{} release: {:?}", locked.mod_id, locked.release_id);
+    }
+
+    Ok(())
+}
+
+/// Compares the `Mods` folder with the lockfile, files are matched by name.
+pub fn verify(instance: &Instance, lockfile: &Lockfile) -> Result<Vec<Drift>, LockError> {
+    verify_folder(&instance.mods_path(), lockfile)
+}
+
+fn verify_folder(mods_path: &Path, lockfile: &Lockfile) -> Result<Vec<Drift>, LockError> {
+    let files = mod_files(mods_path)?;
+
+    let mut drift = Vec::new();
+    for locked in &lockfile.mods {
+        match files.iter().find(|(file, _)| *file == locked.file) {
+            Some((_, path)) => {
+                if sha256_file(path)? != locked.sha256 {
+                    drift.push(Drift::Changed(locked.clone()));
+                }
+            }
+            None => drift.push(Drift::Missing(locked.clone())),
+        }
+    }
+    for (file, _) in files {
+        if !lockfile.mods.iter().any(|locked| locked.file == file) {
+            drift.push(Drift::Extra(file));
+        }
+    }
+
+    Ok(drift)
+}
+
+/// Restores a locked file from the snapshot store, or downloads its release from ModDB.
+async fn fetch(data_path: &Path, moddb: &ModDb, locked: &LockedMod) -> Result<Vec<u8>, LockError> {
+    if let Some(path) = stored_object(data_path, &locked.sha256) {
+        debug!("restoring {} from the snapshot store", locked.file);
+        return Ok(fs::read(path)?);
+    }
+
+    let not_downloadable = || LockError::NotDownloadable(locked.file.clone());
+    let release_id = locked.release_id.ok_or_else(not_downloadable)?;
+    let release = moddb
+        .get_mod(&locked.mod_id)
+        .await
+        .map_err(LockError::ApiError)?
+        .and_then(|mod_db_mod| {
+            mod_db_mod
+                .releases
+                .into_iter()
+                .find(|release| release.id == release_id)
+        })
+        .ok_or_else(not_downloadable)?;
+
+    let data = moddb
+        .download(&release)
+        .await
+        .map_err(LockError::ApiError)?;
+    if sha256_data(&data) != locked.sha256 {
+        return Err(LockError::HashMismatch(locked.file.clone()));
+    }
+
+    Ok(data)
+}
+
+/// Makes the `Mods` folder match the lockfile, returns the drift that was fixed. See
+/// [`before_mods_change`] for what happens before anything changes.
+pub async fn sync(
+    instance: &Instance,
+    moddb: &ModDb,
+    lockfile: &Lockfile,
+) -> Result<Vec<Drift>, LockError> {
+    let drift = verify(instance, lockfile)?;
+    if !drift.is_empty() {
+        before_mods_change(instance, "sync lockfile")?;
+    }
+    fix_drift(&instance.path(), moddb, &drift).await?;
+
+    info!(
+        "synced {}: {} files",
+        instance.folder_name_string(),
+        drift.len()
+    );
+    Ok(drift)
+}
+
+async fn fix_drift(data_path: &Path, moddb: &ModDb, drift: &[Drift]) -> Result<(), LockError> {
+    let mods_path = data_path.join(MODS_FOLDER);
+    for item in drift {
+        match item {
+            Drift::Missing(locked) | Drift::Changed(locked) => {
+                let data = fetch(data_path, moddb, locked).await?;
+                replace_mod_file(&mods_path, None, &locked.file, &data)?;
+            }
+            Drift::Extra(file) => fs::remove_file(mods_path.join(file))?,
+        }
+        debug!("synced {}", item);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshots::record;
+    use crate::test_util::mod_info;
+    use tempfile::tempdir;
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn lock_keeps_release_ids() {
+        let temp_dir = tempdir().unwrap();
+        let mods_path = temp_dir.path();
+        let installed = [mod_info("carryon", "1.9.0")];
+        fs::write(mods_path.join(&installed[0].zip_name), "zip").unwrap();
+        fs::write(mods_path.join("unknown.zip"), "other").unwrap();
+
+        let lockfile = lock_folder(mods_path, &installed, &Lockfile::default()).unwrap();
+        assert_eq!(lockfile.mods.len(), 2);
+        assert_eq!(lockfile.mods[0].mod_id, "carryon");
+        assert_eq!(lockfile.mods[0].sha256, sha256_data(b"zip"));
+        assert_eq!(lockfile.mods[1].mod_id, "");
+
+        let mut previous = lockfile.clone();
+        previous.mods[0].release_id = Some(42);
+        let relocked = lock_folder(mods_path, &installed, &previous).unwrap();
+        assert_eq!(relocked.mods[0].release_id, Some(42));
+    }
+
+    #[test]
+    fn verify_and_sync_drift() {
+        let temp_dir = tempdir().unwrap();
+        let data_path = temp_dir.path();
+        let mods_path = data_path.join(MODS_FOLDER);
+        fs::create_dir_all(&mods_path).unwrap();
+        for (file, data) in [("carryon.zip", "1.9.0"), ("expandedfoods.zip", "1.7.3")] {
+            fs::write(mods_path.join(file), data).unwrap();
+        }
+        let lockfile = lock_folder(&mods_path, &[], &Lockfile::default()).unwrap();
+        record(data_path, None, "lock").unwrap();
+
+        fs::write(mods_path.join("carryon.zip"), "1.10.0").unwrap();
+        fs::remove_file(mods_path.join("expandedfoods.zip")).unwrap();
+        fs::write(mods_path.join("extrachests.zip"), "1.1.0").unwrap();
+
+        let drift = verify_folder(&mods_path, &lockfile).unwrap();
+        assert_eq!(
+            drift,
+            [
+                Drift::Changed(lockfile.mods[0].clone()),
+                Drift::Missing(lockfile.mods[1].clone()),
+                Drift::Extra("extrachests.zip".to_string()),
+            ]
+        );
+
+        // everything comes from the snapshot store, nothing listens there
+        let moddb = ModDb::new("http://127.0.0.1:1");
+        block_on(fix_drift(data_path, &moddb, &drift)).unwrap();
+        assert!(verify_folder(&mods_path, &lockfile).unwrap().is_empty());
+        assert_eq!(
+            fs::read_to_string(mods_path.join("carryon.zip")).unwrap(),
+            "1.9.0"
+        );
+
+        // a file without release and stored copy cannot be restored
+        let mut unknown = lockfile.mods[0].clone();
+        unknown.sha256 = sha256_data(b"1.8.0");
+        assert!(matches!(
+            block_on(fix_drift(data_path, &moddb, &[Drift::Changed(unknown)])),
+            Err(LockError::NotDownloadable(_))
+        ));
+    }
+}
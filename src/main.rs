@@ -1,24 +1,152 @@
-mod api;
-mod icons;
-mod instance;
-mod launcher;
-mod mods;
+mod backups_tab;
+mod cli;
+mod console_tab;
+mod mod_browser;
+mod mod_config_tab;
+mod mod_detail;
+mod mod_list_form;
+mod server_config_tab;
+mod server_sync_view;
+mod settings_tab;
+mod snapshots_tab;
 mod style;
+mod toast;
 mod ui;
+mod worlds_tab;
 
-use crate::icons::load_icons;
-use crate::instance::{Instance, load_instances};
+use crate::backups_tab::{
+    BackupsMessage, BackupsTab, back_up_job, restore_job, scheduled_backup_job,
+};
+use crate::cli::Cli;
+use crate::console_tab::{ConsoleMessage, ConsoleTab};
+use crate::mod_browser::{BrowserMessage, ModBrowser};
+use crate::mod_config_tab::{ModConfigEditor, ModConfigMessage};
+use crate::mod_detail::{ModDetail, ModDetailMessage};
+use crate::mod_list_form::{ImportTarget, ModListForm, ModListMessage};
+use crate::server_config_tab::{ServerConfigEditor, ServerConfigMessage};
+use crate::server_sync_view::{ServerSyncMessage, ServerSyncView, sync_job};
+use crate::settings_tab::{SettingsEditor, SettingsMessage};
+use crate::snapshots_tab::{SnapshotsMessage, SnapshotsTab, roll_back_job};
+use crate::toast::Toast;
+use crate::worlds_tab::{WorldsMessage, WorldsTab, duplicate_job};
+use clap::Parser;
 use iced::keyboard::key;
-use iced::widget::{button, column, horizontal_rule, horizontal_space, row, scrollable, stack};
+use iced::widget::{
+    button, column, container, horizontal_rule, horizontal_space, markdown, row, scrollable, stack,
+};
 use iced::{
     Center, Element, Event, Length, Padding, Size, Subscription, Task, Theme, event, keyboard,
     widget,
 };
-use log::LevelFilter;
+use log::{LevelFilter, error};
+use rustic_vs::api::ModDb;
+use rustic_vs::game::default_data_path;
+use rustic_vs::http_cache::{HttpCache, cache_root};
+use rustic_vs::icons::load_icons;
+use rustic_vs::instance::{
+    AdoptMode, BrokenInstance, Instance, InstanceKind, MODS_FOLDER, adopt_data_folder,
+    is_data_folder, load_instances,
+};
+use rustic_vs::launcher;
+use rustic_vs::launcher_settings::{
+    LAUNCHER_TOML, LauncherSettings, ShortcutSettings, StoreSettings,
+};
+use rustic_vs::lockfile::{
+    INSTANCE_LOCK, lock_mods, read_lockfile, resolve_release_ids, sync, verify, write_lockfile,
+};
+use rustic_vs::mod_list::{ImportReport, apply_mod_list};
+use rustic_vs::mods::{ModInfo, load_mods};
+use rustic_vs::settings::{SyncGroup, copy_global_settings};
+use rustic_vs::shortcuts::update_shortcuts;
+use rustic_vs::store::{LinkMode, StoreReport, collect_garbage, link_if_enabled, link_instance};
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
-use std::time::SystemTime;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::runtime::Handle;
+
+/// How often instances with a backup schedule are checked for a due backup.
+const BACKUP_CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
 
 pub fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    setup_logging(cli.command.is_some())?;
+
+    if let Some(command) = cli.command {
+        if let Err(e) = cli::run(command, cli.json) {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    Ok(iced::application("Rustic", Rustic::update, Rustic::view)
+        .subscription(Rustic::subscription)
+        .theme(|r: &Rustic| r.theme())
+        .window(iced::window::Settings {
+            size: Size::new(700.0, 450.0),
+            min_size: Some(Size::new(700.0, 450.0)),
+            ..iced::window::Settings::default()
+        })
+        .run()?)
+}
+
+/// Keeps toasts short for large mod folders.
+fn drift_summary(drift: impl ExactSizeIterator<Item = String>) -> String {
+    const MAX_LINES: usize = 5;
+
+    let count = drift.len();
+    let mut lines: Vec<String> = drift.take(MAX_LINES).collect();
+    if count > MAX_LINES {
+        lines.push(format!("and {} more", count - MAX_LINES));
+    }
+    lines.join("\n")
+}
+
+/// Runs blocking work like hashing or copying files on the blocking thread pool, a panic
+/// is passed on.
+async fn blocking<T: Send + 'static>(work: impl FnOnce() -> T + Send + 'static) -> T {
+    tokio::task::spawn_blocking(work)
+        .await
+        .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+}
+
+/// Links new mod files in the background, failing is only logged.
+fn link_task(store: &StoreSettings, instance: &Instance) -> Task<Message> {
+    let (store, instance) = (store.clone(), instance.clone());
+    Task::future(blocking(move || {
+        if let Err(e) = link_if_enabled(&store, &instance) {
+            error!("failed linking {}: {}", instance.folder_name_string(), e);
+        }
+    }))
+    .discard()
+}
+
+/// Launchers outside of Rustic are regenerated with the launcher settings, saving an instance
+/// does it on its own.
+fn shortcuts_error(settings: &ShortcutSettings, instance: &Instance) -> Option<Toast> {
+    let e = update_shortcuts(settings, instance).err()?;
+    error!(
+        "failed updating shortcuts of {}: {}",
+        instance.folder_name_string(),
+        e
+    );
+    Some(Toast::error(
+        format!("Failed updating shortcuts of {}", instance.name),
+        e,
+    ))
+}
+
+/// Headless commands log warnings to stderr, to keep stdout clean for their output.
+fn setup_logging(headless: bool) -> Result<(), Box<dyn Error>> {
+    let console = if headless {
+        fern::Dispatch::new()
+            .level(LevelFilter::Warn)
+            .chain(std::io::stderr())
+    } else {
+        fern::Dispatch::new().chain(std::io::stdout())
+    };
+
     fern::Dispatch::new()
         .format(|out, message, record| {
             out.finish(format_args!(
@@ -31,30 +159,52 @@ pub fn main() -> Result<(), Box<dyn Error>> {
         })
         .level(LevelFilter::Error)
         .level_for("rustic_vs", LevelFilter::Debug)
-        .chain(std::io::stdout())
+        .chain(console)
         .chain(fern::log_file("rustic-vs.log")?)
         .apply()?;
 
-    Ok(iced::application("Rustic", Rustic::update, Rustic::view)
-        .subscription(Rustic::subscription)
-        .theme(|r: &Rustic| r.theme())
-        .window(iced::window::Settings {
-            size: Size::new(600.0, 450.0),
-            min_size: Some(Size::new(600.0, 450.0)),
-            ..iced::window::Settings::default()
-        })
-        .run()?)
+    Ok(())
 }
 
 #[allow(dead_code)]
 struct Rustic {
     dark: bool,
     instances: Vec<Instance>,
+    broken_instances: Vec<BrokenInstance>,
+    toasts: Vec<Toast>,
     icons: Vec<String>,
     selected_index: Option<usize>,
     selected_icon: Option<String>,
     show_modal: Option<Modal>,
+    instance_tab: InstanceTab,
+    settings_editor: Option<Result<SettingsEditor, String>>,
+    server_config_editor: Option<Result<ServerConfigEditor, String>>,
+    mod_config_editor: Option<ModConfigEditor>,
+    mod_detail: Option<ModDetail>,
+    server_sync: Option<ServerSyncView>,
+    worlds_tab: Option<WorldsTab>,
+    backups_tab: Option<BackupsTab>,
+    snapshots_tab: Option<SnapshotsTab>,
+    /// Consoles of servers started from the GUI, by instance folder name.
+    consoles: HashMap<String, ConsoleTab>,
+    /// Instance folders with a background job running, see [`Rustic::run_job`].
+    running_jobs: HashSet<String>,
+    /// Linking all instances, cleaning up the store or clearing the cache.
+    store_job: bool,
     instance_name: String,
+    instance_kind: InstanceKind,
+    game_exe_path: String,
+    copy_global_settings: bool,
+    sync_settings: Vec<String>,
+    adopt_path: String,
+    adopt_mode: AdoptMode,
+    adopt_preview: Result<Vec<ModInfo>, String>,
+    adopting: bool,
+    launcher_settings: LauncherSettings,
+    launcher_settings_form: LauncherSettings,
+    mod_browser: Option<ModBrowser>,
+    mod_list_form: Option<ModListForm>,
+    moddb: ModDb,
 }
 
 #[allow(clippy::enum_variant_names)]
@@ -62,6 +212,21 @@ enum Modal {
     NewInstance,
     EditInstance(String),
     ViewInstance,
+    ConfirmLaunch(usize),
+    AdoptDataFolder,
+    LauncherSettings,
+    BrowseMods,
+    ImportModList,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstanceTab {
+    Mods,
+    Settings,
+    Worlds,
+    Backups,
+    Snapshots,
+    Console,
 }
 
 #[derive(Debug, Clone)]
@@ -71,29 +236,125 @@ pub enum Message {
     Event(Event),
     Refresh,
     ToggleDark,
+    Tick,
+    CloseToast(usize),
+    OpenPath(PathBuf),
+    OpenUrl(markdown::Url),
     // gui
     SelectInstance(usize),
+    SelectTab(InstanceTab),
+    Settings(SettingsMessage),
+    ServerConfig(ServerConfigMessage),
+    OpenModConfig(usize),
+    ModConfig(ModConfigMessage),
+    CloseModConfig,
+    OpenModDetail(usize),
+    BrowseModDetail(u64),
+    ModDetail(ModDetailMessage),
+    CloseModDetail,
+    OpenServerSync(usize),
+    ServerSync(ServerSyncMessage),
+    CloseServerSync,
+    Worlds(WorldsMessage),
+    Backups(BackupsMessage),
+    ScheduledBackups,
+    /// Background job finished for the instance folder, the flag tells if it changed `Mods`.
+    JobDone(String, bool, Option<Toast>),
+    Snapshots(SnapshotsMessage),
+    Console(String, ConsoleMessage),
+    LockMods(usize),
+    VerifyLock(usize),
+    SyncLock(usize),
+    Launch(usize),
+    LaunchBackedUp(String, Result<(), String>),
+    LaunchAnyway(usize),
+    RecreateInstance(usize),
+    AdoptFolder(usize),
     // modals
     HideModal,
     NewInstance,
     NewInstanceSubmit,
     EditInstance(usize),
     EditInstanceSubmit,
+    AdoptDataFolder,
+    AdoptDataFolderSubmit,
+    DataFolderAdopted(PathBuf, Result<Instance, String>),
+    LauncherSettings,
+    LauncherSettingsSubmit,
+    LinkAllInstances,
+    CollectGarbage,
+    ClearCache,
+    StoreJobDone(Vec<Toast>),
+    BrowseMods,
+    Browser(BrowserMessage),
+    ImportModList,
+    ModList(ModListMessage),
+    ImportModListSubmit,
+    /// Results of background work carry the instance folder, the list can change meanwhile.
+    ModListImported(String, Result<ImportReport, String>),
+    ModInstalled(String, u64, Result<String, String>),
     // form fields
     InstanceName(String),
+    InstanceKindSelected(InstanceKind),
     IconSelected(String),
+    GameExePath(String),
+    CopyGlobalSettings(bool),
+    SyncGroupToggled(SyncGroup, bool),
+    AdoptPath(String),
+    AdoptPreviewLoaded(String, Result<Vec<ModInfo>, String>),
+    AdoptModeSelected(AdoptMode),
+    StoreEnabled(bool),
+    StoreLinkMode(LinkMode),
+    CacheEnabled(bool),
+    DesktopEntries(bool),
+    LaunchScripts(bool),
 }
 
 impl Default for Rustic {
     fn default() -> Self {
+        let (instances, broken_instances) = load_instances();
+        let mut toasts = Vec::new();
+        let launcher_settings = LauncherSettings::load().unwrap_or_else(|e| {
+            error!("failed loading {}: {}", LAUNCHER_TOML, e);
+            toasts.push(Toast::error("Failed loading launcher settings", e));
+            LauncherSettings::default()
+        });
+
         Self {
             dark: true,
-            instances: load_instances(),
+            instances,
+            broken_instances,
+            toasts,
             icons: load_icons(),
             selected_index: None,
             selected_icon: None,
             show_modal: None,
+            instance_tab: InstanceTab::Mods,
+            settings_editor: None,
+            server_config_editor: None,
+            mod_config_editor: None,
+            mod_detail: None,
+            server_sync: None,
+            worlds_tab: None,
+            backups_tab: None,
+            snapshots_tab: None,
+            consoles: HashMap::new(),
+            running_jobs: HashSet::new(),
+            store_job: false,
             instance_name: String::new(),
+            instance_kind: InstanceKind::Client,
+            game_exe_path: String::new(),
+            copy_global_settings: false,
+            sync_settings: Vec::new(),
+            adopt_path: String::new(),
+            adopt_mode: AdoptMode::Copy,
+            adopt_preview: Ok(Vec::new()),
+            adopting: false,
+            launcher_settings_form: launcher_settings.clone(),
+            mod_browser: None,
+            mod_list_form: None,
+            moddb: launcher_settings.moddb(),
+            launcher_settings,
         }
     }
 }
@@ -105,7 +366,213 @@ impl Rustic {
 
     fn hide_modal(&mut self) {
         self.show_modal = None;
+        self.mod_detail = None;
+        self.server_sync = None;
+        self.mod_list_form = None;
         self.instance_name.clear();
+        self.instance_kind = InstanceKind::Client;
+        self.game_exe_path.clear();
+        self.copy_global_settings = false;
+        self.sync_settings.clear();
+    }
+
+    fn instance_index(&self, folder: &str) -> Option<usize> {
+        self.instances
+            .iter()
+            .position(|instance| instance.folder_name == folder)
+    }
+
+    /// Runs blocking work like zipping saves or copying mods outside of `update()`. One job
+    /// per instance at a time, the instance is looked up by folder again once it finished.
+    fn run_job(
+        &mut self,
+        index: usize,
+        mods_changed: bool,
+        job: impl FnOnce(&Instance) -> Option<Toast> + Send + 'static,
+    ) -> Task<Message> {
+        let instance = self.instances[index].clone();
+        let folder = instance.folder_name_string();
+        if !self.running_jobs.insert(folder.clone()) {
+            self.toasts.push(Toast::info(
+                format!("{} is busy", instance.name),
+                "wait for the running action to finish",
+            ));
+            return Task::none();
+        }
+
+        let store = self.launcher_settings.mod_store.clone();
+        Task::perform(
+            blocking(move || {
+                let toast = job(&instance);
+                if mods_changed && let Err(e) = link_if_enabled(&store, &instance) {
+                    error!("failed linking {}: {}", instance.folder_name_string(), e);
+                }
+                toast
+            }),
+            move |toast| Message::JobDone(folder.clone(), mods_changed, toast),
+        )
+    }
+
+    /// Reloads the mods after background work changed them and links them in the background,
+    /// returns the instance index if it still exists.
+    fn mods_changed(&mut self, folder: &str) -> (Option<usize>, Task<Message>) {
+        let Some(index) = self.instance_index(folder) else {
+            return (None, Task::none());
+        };
+        let instance = &mut self.instances[index];
+        if let Err(e) = instance.reload_mods() {
+            error!("failed reloading mods of {}: {}", folder, e);
+        }
+        (
+            Some(index),
+            link_task(&self.launcher_settings.mod_store, instance),
+        )
+    }
+
+    /// Runs store or cache work that is not tied to one instance, one at a time.
+    fn run_store_job(
+        &mut self,
+        job: impl FnOnce() -> Vec<Toast> + Send + 'static,
+    ) -> Task<Message> {
+        if self.store_job {
+            self.toasts.push(Toast::info(
+                "The store is busy",
+                "wait for the running action to finish",
+            ));
+            return Task::none();
+        }
+        self.store_job = true;
+        Task::perform(blocking(job), Message::StoreJobDone)
+    }
+
+    fn instance_name(&self, index: Option<usize>, folder: &str) -> String {
+        index
+            .map(|index| self.instances[index].name.clone())
+            .unwrap_or_else(|| folder.to_string())
+    }
+
+    /// Backs up the saves in the background if the instance asks for it, then starts it.
+    fn launch(&mut self, index: usize) -> Task<Message> {
+        let instance = self.instances[index].clone();
+        let folder = instance.folder_name_string();
+        if self
+            .consoles
+            .get(&folder)
+            .is_some_and(ConsoleTab::is_running)
+        {
+            return Task::done(Message::SelectInstance(index))
+                .chain(Task::done(Message::SelectTab(InstanceTab::Console)));
+        }
+
+        Task::perform(
+            async move {
+                launcher::back_up_before_launch(&instance)
+                    .map(|_| ())
+                    .map_err(|e| e.to_string())
+            },
+            move |result| Message::LaunchBackedUp(folder.clone(), result),
+        )
+    }
+
+    fn start(&mut self, index: usize) -> Task<Message> {
+        let instance = &self.instances[index];
+        if instance.is_server() {
+            return self.launch_server(index);
+        }
+        match launcher::launch(instance) {
+            Ok(child) => launcher::reap_in_background(child),
+            Err(e) => {
+                error!("failed launching {}: {}", instance.folder_name_string(), e);
+                self.toasts.push(Toast::error(
+                    format!("Failed launching {}", instance.name),
+                    e,
+                ));
+            }
+        }
+        Task::none()
+    }
+
+    /// Starts the server with its output streamed into a console and shows it.
+    fn launch_server(&mut self, index: usize) -> Task<Message> {
+        let instance = &self.instances[index];
+        let folder = instance.folder_name_string();
+        let show_console = Task::done(Message::SelectInstance(index))
+            .chain(Task::done(Message::SelectTab(InstanceTab::Console)));
+
+        match launcher::launch_server(instance) {
+            Ok((console, receiver)) => {
+                self.consoles
+                    .insert(folder.clone(), ConsoleTab::new(console));
+                let events = iced::futures::stream::unfold(receiver, |mut receiver| async move {
+                    receiver.recv().await.map(|event| (event, receiver))
+                });
+                Task::batch([
+                    Task::run(events, move |event| {
+                        Message::Console(folder.clone(), ConsoleMessage::Event(event))
+                    }),
+                    show_console,
+                ])
+            }
+            Err(e) => {
+                error!("failed launching {}: {}", folder, e);
+                self.toasts.push(Toast::error(
+                    format!("Failed launching {}", instance.name),
+                    e,
+                ));
+                Task::none()
+            }
+        }
+    }
+
+    fn repair_instance(&mut self, index: usize, adopt: bool) {
+        let broken = &self.broken_instances[index];
+        let result = if adopt {
+            broken.adopt()
+        } else {
+            broken.recreate()
+        };
+
+        match result {
+            Ok(instance) => {
+                self.broken_instances.remove(index);
+                self.toasts
+                    .push(Toast::info("Instance repaired", &instance.name));
+                self.toasts.extend(shortcuts_error(
+                    &self.launcher_settings.shortcuts,
+                    &instance,
+                ));
+                self.instances.push(instance);
+            }
+            Err(e) => {
+                error!("failed repairing {}: {}", broken.folder_name_string(), e);
+                self.toasts.push(Toast::error(
+                    format!("Failed repairing {}", broken.folder_name_string()),
+                    e,
+                ));
+            }
+        }
+    }
+
+    /// Reads the mods of the entered folder in the background, results for a folder that was
+    /// changed meanwhile are dropped.
+    fn update_adopt_preview(&self) -> Task<Message> {
+        let adopt_path = self.adopt_path.clone();
+        let path = PathBuf::from(adopt_path.trim());
+        Task::perform(
+            async move {
+                if is_data_folder(&path) {
+                    load_mods(&path.join(MODS_FOLDER)).map_err(|e| e.to_string())
+                } else {
+                    Err("not a Vintage Story data folder".to_string())
+                }
+            },
+            move |preview| Message::AdoptPreviewLoaded(adopt_path.clone(), preview),
+        )
+    }
+
+    fn form_game_exe_path(&self) -> Option<PathBuf> {
+        let path = self.game_exe_path.trim();
+        (!path.is_empty()).then(|| PathBuf::from(path))
     }
 
     fn update(&mut self, message: Message) -> Task<Message> {
@@ -124,6 +591,26 @@ impl Rustic {
                         widget::focus_next()
                     }
                 }
+                Event::Keyboard(keyboard::Event::KeyPressed {
+                    key: keyboard::Key::Named(named @ (key::Named::ArrowUp | key::Named::ArrowDown)),
+                    ..
+                }) => {
+                    let (Some(Modal::ViewInstance), InstanceTab::Console, Some(index)) =
+                        (&self.show_modal, self.instance_tab, self.selected_index)
+                    else {
+                        return Task::none();
+                    };
+                    let folder = self.instances[index].folder_name_string();
+                    let message = if named == key::Named::ArrowUp {
+                        ConsoleMessage::HistoryPrevious
+                    } else {
+                        ConsoleMessage::HistoryNext
+                    };
+                    match self.consoles.get_mut(&folder) {
+                        Some(console) => console.update(message),
+                        None => Task::none(),
+                    }
+                }
                 Event::Keyboard(keyboard::Event::KeyPressed {
                     key: keyboard::Key::Named(key::Named::Escape),
                     ..
@@ -136,7 +623,7 @@ impl Rustic {
                 _ => Task::none(),
             },
             Message::Refresh => {
-                self.instances = load_instances();
+                (self.instances, self.broken_instances) = load_instances();
                 self.selected_index = None;
                 Task::none()
             }
@@ -144,12 +631,406 @@ impl Rustic {
                 self.dark = !self.dark;
                 Task::none()
             }
+            Message::Tick => {
+                let now = Instant::now();
+                self.toasts.retain(|toast| !toast.is_expired(now));
+                Task::none()
+            }
+            Message::CloseToast(index) => {
+                if index < self.toasts.len() {
+                    self.toasts.remove(index);
+                }
+                Task::none()
+            }
+            Message::OpenPath(path) => {
+                if let Err(e) = open::that(&path) {
+                    error!("failed opening {}: {}", path.display(), e);
+                    self.toasts.push(Toast::error(
+                        format!("Failed opening {}", path.display()),
+                        e,
+                    ));
+                }
+                Task::none()
+            }
+            Message::OpenUrl(url) => {
+                if let Err(e) = open::that(url.as_str()) {
+                    error!("failed opening {}: {}", url, e);
+                    self.toasts
+                        .push(Toast::error(format!("Failed opening {}", url), e));
+                }
+                Task::none()
+            }
             // gui
             Message::SelectInstance(index) => {
                 self.selected_index = Some(index);
+                self.instance_tab = InstanceTab::Mods;
+                self.settings_editor = None;
+                self.server_config_editor = None;
+                self.mod_config_editor = None;
+                self.mod_detail = None;
+                self.server_sync = None;
+                self.worlds_tab = None;
+                self.backups_tab = None;
+                self.snapshots_tab = None;
                 self.show_modal = Some(Modal::ViewInstance);
                 Task::none()
             }
+            Message::SelectTab(tab) => {
+                self.instance_tab = tab;
+                if let (InstanceTab::Settings, Some(index)) = (tab, self.selected_index)
+                    && self.instances[index].is_server()
+                {
+                    let instance = &self.instances[index];
+                    self.server_config_editor = Some(if instance.server_config_path().is_file() {
+                        ServerConfigEditor::load(instance).map_err(|e| e.to_string())
+                    } else {
+                        Err("no serverconfig.json yet, start the server once".to_string())
+                    });
+                } else if let (InstanceTab::Settings, Some(index)) = (tab, self.selected_index) {
+                    let path = self.instances[index].settings_path();
+                    self.settings_editor = Some(if path.is_file() {
+                        SettingsEditor::load(&path).map_err(|e| e.to_string())
+                    } else {
+                        Err("no clientsettings.json yet, start the game once".to_string())
+                    });
+                }
+                if let (InstanceTab::Worlds, Some(index)) = (tab, self.selected_index) {
+                    self.worlds_tab = Some(WorldsTab::load(&self.instances[index].path()));
+                }
+                if let (InstanceTab::Backups, Some(index)) = (tab, self.selected_index) {
+                    self.backups_tab = Some(BackupsTab::load(&self.instances[index]));
+                }
+                if let (InstanceTab::Snapshots, Some(index)) = (tab, self.selected_index) {
+                    self.snapshots_tab = Some(SnapshotsTab::load(&self.instances[index]));
+                }
+                Task::none()
+            }
+            Message::OpenModDetail(mod_index) => {
+                let Some(index) = self.selected_index else {
+                    return Task::none();
+                };
+                let mod_info = self.instances[index].mods[mod_index].clone();
+                let (detail, task) = ModDetail::open(
+                    mod_info.mod_id.clone(),
+                    Some(mod_info),
+                    Some(index),
+                    &self.moddb,
+                );
+                self.mod_detail = Some(detail);
+                task
+            }
+            Message::BrowseModDetail(id) => {
+                let target = self.mod_browser.as_ref().and_then(ModBrowser::target);
+                let (detail, task) = ModDetail::open(id.to_string(), None, target, &self.moddb);
+                self.mod_detail = Some(detail);
+                task
+            }
+            Message::ModDetail(message) => match &mut self.mod_detail {
+                Some(detail) => detail.update(message, &self.instances, &self.moddb),
+                None => Task::none(),
+            },
+            Message::CloseModDetail => {
+                self.mod_detail = None;
+                Task::none()
+            }
+            Message::OpenServerSync(index) => {
+                self.server_sync = Some(ServerSyncView::open(&self.instances, index));
+                Task::none()
+            }
+            Message::ServerSync(ServerSyncMessage::Sync) => {
+                let (Some(view), Some(index)) = (&self.server_sync, self.selected_index) else {
+                    return Task::none();
+                };
+                let Some(server) = view
+                    .server_index()
+                    .map(|server| self.instances[server].clone())
+                else {
+                    return Task::none();
+                };
+                self.run_job(index, true, move |client| sync_job(&server, client))
+            }
+            Message::ServerSync(message) => {
+                if let (Some(view), Some(index)) = (&mut self.server_sync, self.selected_index) {
+                    view.update(message, &self.instances, index);
+                }
+                Task::none()
+            }
+            Message::CloseServerSync => {
+                self.server_sync = None;
+                Task::none()
+            }
+            Message::Worlds(WorldsMessage::Duplicate(world_index)) => {
+                let (Some(tab), Some(index)) = (&self.worlds_tab, self.selected_index) else {
+                    return Task::none();
+                };
+                match tab.duplicated(world_index) {
+                    Some(world) => self.run_job(index, false, move |_| duplicate_job(&world)),
+                    None => Task::none(),
+                }
+            }
+            Message::Worlds(message) => {
+                if let Some(e) = self.worlds_tab.as_mut().and_then(|tab| tab.update(message)) {
+                    error!("world action failed: {}", e);
+                    self.toasts.push(Toast::error("World action failed", e));
+                }
+                Task::none()
+            }
+            Message::Settings(message) => {
+                if let (Some(Ok(editor)), Some(index)) =
+                    (&mut self.settings_editor, self.selected_index)
+                {
+                    let instance = &self.instances[index];
+                    match editor.update(message, &instance.settings_path()) {
+                        Some(Ok(changes)) => self.toasts.push(Toast::info(
+                            "Settings saved",
+                            format!("{} changes in {}", changes, instance.name),
+                        )),
+                        Some(Err(e)) => {
+                            error!("failed saving settings: {}", e);
+                            self.toasts.push(Toast::error("Failed saving settings", e));
+                        }
+                        None => {}
+                    }
+                }
+                Task::none()
+            }
+            Message::ServerConfig(message) => {
+                if let (Some(Ok(editor)), Some(index)) =
+                    (&mut self.server_config_editor, self.selected_index)
+                {
+                    let instance = &self.instances[index];
+                    match editor.update(message, &instance.server_config_path()) {
+                        Some(Ok(())) => self
+                            .toasts
+                            .push(Toast::info("Server config saved", &instance.name)),
+                        Some(Err(e)) => {
+                            error!("failed saving server config: {}", e);
+                            self.toasts
+                                .push(Toast::error("Failed saving server config", e));
+                        }
+                        None => {}
+                    }
+                }
+                Task::none()
+            }
+            Message::OpenModConfig(mod_index) => {
+                if let Some(index) = self.selected_index {
+                    let mod_info = &self.instances[index].mods[mod_index];
+                    match ModConfigEditor::load(mod_info) {
+                        Ok(editor) => self.mod_config_editor = Some(editor),
+                        Err(e) => {
+                            error!("failed loading config of {}: {}", mod_info.name, e);
+                            self.toasts.push(Toast::error(
+                                format!("Failed loading config of {}", mod_info.name),
+                                e,
+                            ));
+                        }
+                    }
+                }
+                Task::none()
+            }
+            Message::ModConfig(message) => {
+                if let Some(editor) = &mut self.mod_config_editor {
+                    match editor.update(message) {
+                        Some(Ok(())) => self
+                            .toasts
+                            .push(Toast::info("Config saved", &editor.mod_name)),
+                        Some(Err(e)) => {
+                            error!("failed saving config of {}: {}", editor.mod_name, e);
+                            self.toasts.push(Toast::error(
+                                format!("Failed saving config of {}", editor.mod_name),
+                                e,
+                            ));
+                        }
+                        None => {}
+                    }
+                }
+                Task::none()
+            }
+            Message::CloseModConfig => {
+                self.mod_config_editor = None;
+                Task::none()
+            }
+            Message::Backups(BackupsMessage::BackUpNow) => match self.selected_index {
+                Some(index) => self.run_job(index, false, back_up_job),
+                None => Task::none(),
+            },
+            Message::Backups(BackupsMessage::ConfirmRestore) => {
+                let (Some(tab), Some(index)) = (&mut self.backups_tab, self.selected_index) else {
+                    return Task::none();
+                };
+                match tab.confirmed_restore() {
+                    Some(backup) => {
+                        self.run_job(index, false, move |instance| restore_job(instance, &backup))
+                    }
+                    None => Task::none(),
+                }
+            }
+            Message::Backups(message) => {
+                if let (Some(tab), Some(index)) = (&mut self.backups_tab, self.selected_index)
+                    && let Some(toast) = tab.update(message, &mut self.instances[index])
+                {
+                    self.toasts.push(toast);
+                }
+                Task::none()
+            }
+            Message::Snapshots(SnapshotsMessage::ConfirmRollBack) => {
+                let (Some(tab), Some(index)) = (&mut self.snapshots_tab, self.selected_index)
+                else {
+                    return Task::none();
+                };
+                match tab.confirmed_roll_back() {
+                    Some(snapshot) => self.run_job(index, true, move |instance| {
+                        roll_back_job(instance, &snapshot)
+                    }),
+                    None => Task::none(),
+                }
+            }
+            Message::Snapshots(message) => {
+                if let (Some(tab), Some(index)) = (&mut self.snapshots_tab, self.selected_index)
+                    && let Some(toast) = tab.update(message, &mut self.instances[index])
+                {
+                    self.toasts.push(toast);
+                }
+                Task::none()
+            }
+            Message::Console(folder, message) => match self.consoles.get_mut(&folder) {
+                Some(console) => console.update(message),
+                None => Task::none(),
+            },
+            Message::LockMods(index) => {
+                let moddb = self.moddb.clone();
+                self.run_job(index, false, move |instance| {
+                    let locked = lock_mods(instance).and_then(|mut lockfile| {
+                        let resolved =
+                            Handle::current().block_on(resolve_release_ids(&moddb, &mut lockfile));
+                        if let Err(e) = resolved {
+                            error!("failed looking up release ids: {}", e);
+                        }
+                        write_lockfile(instance, &lockfile)?;
+                        Ok(lockfile.mods.len())
+                    });
+                    Some(match locked {
+                        Ok(count) => Toast::info("Mods locked", format!("{} mods pinned", count)),
+                        Err(e) => {
+                            error!("failed writing {}: {}", INSTANCE_LOCK, e);
+                            Toast::error("Failed locking mods", e)
+                        }
+                    })
+                })
+            }
+            Message::VerifyLock(index) => self.run_job(index, false, |instance| {
+                let result =
+                    read_lockfile(instance).and_then(|lockfile| verify(instance, &lockfile));
+                Some(match result {
+                    Ok(drift) if drift.is_empty() => {
+                        Toast::info("Mods match the lockfile", &instance.name)
+                    }
+                    Ok(drift) => Toast::error(
+                        format!("{} differs from the lockfile", instance.name),
+                        drift_summary(drift.iter().map(ToString::to_string)),
+                    ),
+                    Err(e) => Toast::error("Failed verifying mods", e),
+                })
+            }),
+            Message::SyncLock(index) => {
+                let moddb = self.moddb.clone();
+                self.run_job(index, true, move |instance| {
+                    let synced = read_lockfile(instance).and_then(|lockfile| {
+                        Handle::current().block_on(sync(instance, &moddb, &lockfile))
+                    });
+                    Some(match synced {
+                        Ok(drift) if drift.is_empty() => {
+                            Toast::info("Mods already match the lockfile", &instance.name)
+                        }
+                        Ok(drift) => Toast::info(
+                            format!("Synced {}", instance.name),
+                            drift_summary(drift.iter().map(ToString::to_string)),
+                        ),
+                        Err(e) => {
+                            error!("failed syncing {}: {}", instance.folder_name_string(), e);
+                            Toast::error("Failed syncing mods", e)
+                        }
+                    })
+                })
+            }
+            Message::ScheduledBackups => {
+                let due: Vec<usize> = (0..self.instances.len())
+                    .filter(|&index| {
+                        let instance = &self.instances[index];
+                        instance.backups.schedule.is_some()
+                            && !self.running_jobs.contains(&instance.folder_name_string())
+                    })
+                    .collect();
+                let jobs: Vec<_> = due
+                    .into_iter()
+                    .map(|index| self.run_job(index, false, scheduled_backup_job))
+                    .collect();
+                Task::batch(jobs)
+            }
+            Message::JobDone(folder, mods_changed, toast) => {
+                self.running_jobs.remove(&folder);
+                self.toasts.extend(toast);
+                let Some(index) = self.instance_index(&folder) else {
+                    return Task::none();
+                };
+                let instance = &mut self.instances[index];
+                if mods_changed && let Err(e) = instance.reload_mods() {
+                    error!("failed reloading mods of {}: {}", folder, e);
+                }
+                if self.selected_index == Some(index) {
+                    let instance = &self.instances[index];
+                    if let Some(tab) = &mut self.backups_tab {
+                        tab.reload(instance);
+                    }
+                    if let Some(tab) = &mut self.snapshots_tab {
+                        tab.reload(instance);
+                    }
+                    if let Some(tab) = &mut self.worlds_tab {
+                        tab.reload();
+                    }
+                    if let Some(view) = &mut self.server_sync {
+                        view.compare(&self.instances, index);
+                    }
+                }
+                Task::none()
+            }
+            Message::Launch(index) => {
+                if self.instances[index].incompatible_mods().is_empty() {
+                    self.launch(index)
+                } else {
+                    self.show_modal = Some(Modal::ConfirmLaunch(index));
+                    Task::none()
+                }
+            }
+            Message::LaunchAnyway(index) => {
+                self.hide_modal();
+                self.launch(index)
+            }
+            Message::LaunchBackedUp(folder, result) => {
+                let Some(index) = self.instance_index(&folder) else {
+                    return Task::none();
+                };
+                match result {
+                    Ok(()) => self.start(index),
+                    Err(e) => {
+                        error!("failed launching {}: {}", folder, e);
+                        self.toasts.push(Toast::error(
+                            format!("Failed launching {}", self.instances[index].name),
+                            e,
+                        ));
+                        Task::none()
+                    }
+                }
+            }
+            Message::RecreateInstance(index) => {
+                self.repair_instance(index, false);
+                Task::none()
+            }
+            Message::AdoptFolder(index) => {
+                self.repair_instance(index, true);
+                Task::none()
+            }
             // modals
             Message::HideModal => {
                 self.hide_modal();
@@ -164,37 +1045,379 @@ impl Rustic {
                 if !cleaned_name.is_empty() {
                     let mut new_instance = Instance::new(cleaned_name);
                     new_instance.icon = self.selected_icon.clone();
+                    new_instance.game_exe_path = self.form_game_exe_path();
+                    new_instance.kind = self.instance_kind;
+                    new_instance.sync_settings = self.sync_settings.clone();
+                    new_instance.detect_game_version();
+
+                    match new_instance.create() {
+                        Ok(()) => {
+                            if self.copy_global_settings
+                                && new_instance.kind.is_client()
+                                && let Err(e) = copy_global_settings(
+                                    &new_instance.path(),
+                                    &new_instance.mods_path(),
+                                )
+                            {
+                                error!("failed copying global settings: {}", e);
+                                self.toasts
+                                    .push(Toast::error("Failed copying global settings", e));
+                            }
+                            self.toasts.extend(shortcuts_error(
+                                &self.launcher_settings.shortcuts,
+                                &new_instance,
+                            ));
 
-                    new_instance.save();
-                    self.instances.push(new_instance);
-                    self.hide_modal();
+                            self.instances.push(new_instance);
+                            self.hide_modal();
 
-                    self.selected_index = None;
-                    self.selected_icon = None;
+                            self.selected_index = None;
+                            self.selected_icon = None;
+                        }
+                        Err(e) => {
+                            error!("failed creating {}: {}", cleaned_name, e);
+                            self.toasts
+                                .push(Toast::error("Failed creating instance", e));
+                        }
+                    }
                 }
                 Task::none()
             }
             Message::EditInstance(index) => {
                 self.selected_index = Some(index);
                 self.instance_name = self.instances[index].name.clone();
+                self.instance_kind = self.instances[index].kind;
                 self.selected_icon = self.instances[index].icon.clone();
+                self.game_exe_path = self.instances[index]
+                    .game_exe_path
+                    .as_ref()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_default();
+                self.sync_settings = self.instances[index].sync_settings.clone();
                 self.show_modal = Some(Modal::EditInstance(self.instance_name.clone()));
                 Task::none()
             }
             Message::EditInstanceSubmit => {
                 let cleaned_name = self.instance_name.trim();
-                if !cleaned_name.is_empty() {
-                    if let Some(index) = self.selected_index {
-                        if let Some(instance) = self.instances.get_mut(index) {
-                            instance.name = cleaned_name.to_string();
-                            instance.icon = self.selected_icon.clone();
+                let game_exe_path = self.form_game_exe_path();
+                if !cleaned_name.is_empty()
+                    && let Some(index) = self.selected_index
+                    && let Some(instance) = self.instances.get_mut(index)
+                {
+                    let mut edited = instance.clone();
+                    edited.name = cleaned_name.to_string();
+                    edited.icon = self.selected_icon.clone();
+                    edited.game_exe_path = game_exe_path;
+                    edited.kind = self.instance_kind;
+                    edited.sync_settings = self.sync_settings.clone();
+                    edited.detect_game_version();
 
-                            instance.save();
+                    match edited.save() {
+                        Ok(()) => {
+                            self.toasts.extend(shortcuts_error(
+                                &self.launcher_settings.shortcuts,
+                                &edited,
+                            ));
+                            *instance = edited;
                             self.hide_modal();
 
                             self.selected_index = None;
                             self.selected_icon = None;
                         }
+                        Err(e) => {
+                            error!("failed saving {}: {}", edited.folder_name_string(), e);
+                            self.toasts.push(Toast::error("Failed saving instance", e));
+                        }
+                    }
+                }
+                Task::none()
+            }
+            Message::LauncherSettings => {
+                self.launcher_settings_form = self.launcher_settings.clone();
+                self.show_modal = Some(Modal::LauncherSettings);
+                Task::none()
+            }
+            Message::LauncherSettingsSubmit => {
+                let settings = self.launcher_settings_form.clone();
+                match settings.save() {
+                    Ok(()) => {
+                        self.moddb = settings.moddb();
+                        let shortcuts_changed =
+                            settings.shortcuts != self.launcher_settings.shortcuts;
+                        self.launcher_settings = settings;
+                        if shortcuts_changed {
+                            self.toasts
+                                .extend(self.instances.iter().filter_map(|instance| {
+                                    shortcuts_error(&self.launcher_settings.shortcuts, instance)
+                                }));
+                        }
+                        self.hide_modal();
+                    }
+                    Err(e) => {
+                        error!("failed saving {}: {}", LAUNCHER_TOML, e);
+                        self.toasts
+                            .push(Toast::error("Failed saving launcher settings", e));
+                    }
+                }
+                Task::none()
+            }
+            Message::LinkAllInstances => {
+                let link_mode = self.launcher_settings_form.mod_store.link_mode;
+                let instances = self.instances.clone();
+                self.run_store_job(move || {
+                    let mut toasts = Vec::new();
+                    let mut report = StoreReport::default();
+                    for instance in &instances {
+                        match link_instance(instance, link_mode) {
+                            Ok(instance_report) => {
+                                report.files += instance_report.files;
+                                report.bytes += instance_report.bytes;
+                            }
+                            Err(e) => {
+                                error!("failed linking {}: {}", instance.folder_name_string(), e);
+                                toasts.push(Toast::error(
+                                    format!("Failed linking {}", instance.name),
+                                    e,
+                                ));
+                            }
+                        }
+                    }
+                    toasts.push(Toast::info(
+                        "Instances linked to the store",
+                        format!(
+                            "{} duplicate files, {} saved",
+                            report.files,
+                            ui::format_size(report.bytes)
+                        ),
+                    ));
+                    toasts
+                })
+            }
+            Message::CollectGarbage => self.run_store_job(|| {
+                vec![match collect_garbage() {
+                    Ok(report) => Toast::info(
+                        "Store cleaned up",
+                        format!(
+                            "{} unused files, {} freed",
+                            report.files,
+                            ui::format_size(report.bytes)
+                        ),
+                    ),
+                    Err(e) => {
+                        error!("failed collecting garbage: {}", e);
+                        Toast::error("Failed cleaning up the store", e)
+                    }
+                }]
+            }),
+            Message::ClearCache => {
+                let settings = self.launcher_settings.cache.clone();
+                self.run_store_job(move || {
+                    let cleared =
+                        cache_root().and_then(|root| HttpCache::new(&root, &settings).clear());
+                    vec![match cleared {
+                        Ok(bytes) => Toast::info("Cache cleared", ui::format_size(bytes)),
+                        Err(e) => {
+                            error!("failed clearing cache: {}", e);
+                            Toast::error("Failed clearing the cache", e)
+                        }
+                    }]
+                })
+            }
+            Message::StoreJobDone(toasts) => {
+                self.store_job = false;
+                self.toasts.extend(toasts);
+                Task::none()
+            }
+            Message::CacheEnabled(enabled) => {
+                self.launcher_settings_form.cache.enabled = enabled;
+                Task::none()
+            }
+            Message::DesktopEntries(enabled) => {
+                self.launcher_settings_form.shortcuts.desktop_entries = enabled;
+                Task::none()
+            }
+            Message::LaunchScripts(enabled) => {
+                self.launcher_settings_form.shortcuts.launch_scripts = enabled;
+                Task::none()
+            }
+            Message::StoreEnabled(enabled) => {
+                self.launcher_settings_form.mod_store.enabled = enabled;
+                Task::none()
+            }
+            Message::StoreLinkMode(link_mode) => {
+                self.launcher_settings_form.mod_store.link_mode = link_mode;
+                Task::none()
+            }
+            Message::BrowseMods => {
+                self.show_modal = Some(Modal::BrowseMods);
+                if self.mod_browser.is_none() {
+                    let target = self
+                        .selected_index
+                        .or((!self.instances.is_empty()).then_some(0));
+                    let (browser, task) = ModBrowser::open(target, &self.moddb);
+                    self.mod_browser = Some(browser);
+                    return task;
+                }
+                Task::none()
+            }
+            Message::Browser(message) => match &mut self.mod_browser {
+                Some(browser) => browser.update(message, &self.instances, &self.moddb),
+                None => Task::none(),
+            },
+            Message::ModInstalled(folder, id, result) => {
+                if let Some(browser) = &mut self.mod_browser {
+                    browser.installed(id);
+                }
+                let (index, link) = self.mods_changed(&folder);
+                if let Some(detail) = &mut self.mod_detail {
+                    detail.installed(index.map(|index| &self.instances[index]));
+                }
+                let name = self.instance_name(index, &folder);
+                self.toasts.push(match result {
+                    Ok(installed) => Toast::info(format!("Installed into {}", name), installed),
+                    Err(e) => {
+                        error!("failed installing into {}: {}", folder, e);
+                        Toast::error("Failed installing mod", e)
+                    }
+                });
+                link
+            }
+            Message::ImportModList => {
+                self.mod_list_form = Some(ModListForm::new(&self.instances, self.selected_index));
+                self.show_modal = Some(Modal::ImportModList);
+                Task::none()
+            }
+            Message::ModList(message) => {
+                if let Some(form) = &mut self.mod_list_form {
+                    form.update(message);
+                }
+                Task::none()
+            }
+            Message::ImportModListSubmit => {
+                let Some(form) = self.mod_list_form.as_mut().filter(|form| form.can_submit())
+                else {
+                    return Task::none();
+                };
+                let index = match &form.target {
+                    ImportTarget::Existing(index, _) => *index,
+                    ImportTarget::New => {
+                        let mut new_instance = Instance::new(form.name.trim());
+                        new_instance.detect_game_version();
+                        if let Err(e) = new_instance.create() {
+                            error!("failed creating {}: {}", form.name, e);
+                            self.toasts
+                                .push(Toast::error("Failed creating instance", e));
+                            return Task::none();
+                        }
+                        self.toasts.extend(shortcuts_error(
+                            &self.launcher_settings.shortcuts,
+                            &new_instance,
+                        ));
+                        // a second submit updates the new instance
+                        form.target =
+                            ImportTarget::Existing(self.instances.len(), new_instance.name.clone());
+                        self.instances.push(new_instance);
+                        self.instances.len() - 1
+                    }
+                };
+                form.importing = true;
+
+                let (instance, moddb) = (self.instances[index].clone(), self.moddb.clone());
+                let folder = instance.folder_name_string();
+                let mod_list = form.mods.clone();
+                Task::perform(
+                    async move {
+                        apply_mod_list(&moddb, &instance, &mod_list)
+                            .await
+                            .map_err(|e| e.to_string())
+                    },
+                    move |result| Message::ModListImported(folder.clone(), result),
+                )
+            }
+            Message::ModListImported(folder, result) => {
+                let (index, link) = self.mods_changed(&folder);
+                let name = self.instance_name(index, &folder);
+                match result {
+                    Ok(report) => {
+                        self.toasts.push(Toast::info(
+                            format!("Imported mod list into {}", name),
+                            format!(
+                                "{} installed, {} kept, {} removed",
+                                report.installed.len(),
+                                report.kept,
+                                report.removed.len()
+                            ),
+                        ));
+                        if !report.missing.is_empty() {
+                            self.toasts.push(Toast::error(
+                                format!("{} mods not found", report.missing.len()),
+                                drift_summary(report.missing.iter().map(ToString::to_string)),
+                            ));
+                        }
+                        if matches!(self.show_modal, Some(Modal::ImportModList)) {
+                            self.hide_modal();
+                        }
+                    }
+                    Err(e) => {
+                        error!("failed importing mod list: {}", e);
+                        self.toasts
+                            .push(Toast::error("Failed importing mod list", e));
+                        if let Some(form) = &mut self.mod_list_form {
+                            form.importing = false;
+                        }
+                    }
+                }
+                link
+            }
+            Message::AdoptDataFolder => {
+                self.adopt_path = default_data_path()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_default();
+                self.instance_name = "Vintage Story".to_string();
+                self.show_modal = Some(Modal::AdoptDataFolder);
+                self.update_adopt_preview()
+            }
+            Message::AdoptDataFolderSubmit => {
+                let cleaned_name = self.instance_name.trim().to_string();
+                if cleaned_name.is_empty() || self.adopting {
+                    return Task::none();
+                }
+                self.adopting = true;
+
+                let source = PathBuf::from(self.adopt_path.trim());
+                let mode = self.adopt_mode;
+                Task::perform(
+                    {
+                        let source = source.clone();
+                        async move {
+                            adopt_data_folder(&source, &cleaned_name, mode)
+                                .map_err(|e| e.to_string())
+                        }
+                    },
+                    move |result| Message::DataFolderAdopted(source.clone(), result),
+                )
+            }
+            Message::DataFolderAdopted(source, result) => {
+                self.adopting = false;
+                match result {
+                    Ok(instance) => {
+                        self.toasts.push(Toast::info(
+                            "Data folder adopted",
+                            format!("{} with {} mods", instance.name, instance.mods_count()),
+                        ));
+                        self.toasts.extend(shortcuts_error(
+                            &self.launcher_settings.shortcuts,
+                            &instance,
+                        ));
+                        self.instances.push(instance);
+                        if matches!(self.show_modal, Some(Modal::AdoptDataFolder)) {
+                            self.hide_modal();
+                        }
+                    }
+                    Err(e) => {
+                        error!("failed adopting {}: {}", source.display(), e);
+                        self.toasts
+                            .push(Toast::error("Failed adopting data folder", e));
                     }
                 }
                 Task::none()
@@ -204,22 +1427,75 @@ impl Rustic {
                 self.instance_name = name;
                 Task::none()
             }
+            Message::InstanceKindSelected(kind) => {
+                self.instance_kind = kind;
+                Task::none()
+            }
             Message::IconSelected(name) => {
                 self.selected_icon = Some(name);
                 Task::none()
             }
+            Message::GameExePath(path) => {
+                self.game_exe_path = path;
+                Task::none()
+            }
+            Message::CopyGlobalSettings(enabled) => {
+                self.copy_global_settings = enabled;
+                Task::none()
+            }
+            Message::SyncGroupToggled(group, enabled) => {
+                group.toggle(&mut self.sync_settings, enabled);
+                Task::none()
+            }
+            Message::AdoptPath(path) => {
+                self.adopt_path = path;
+                self.update_adopt_preview()
+            }
+            Message::AdoptPreviewLoaded(path, preview) => {
+                if path == self.adopt_path {
+                    self.adopt_preview = preview;
+                }
+                Task::none()
+            }
+            Message::AdoptModeSelected(mode) => {
+                self.adopt_mode = mode;
+                Task::none()
+            }
         }
     }
 
     fn subscription(&self) -> Subscription<Message> {
-        event::listen().map(Message::Event)
+        let mut subscriptions = vec![event::listen().map(Message::Event)];
+
+        if !self.toasts.is_empty() {
+            subscriptions.push(iced::time::every(Duration::from_secs(1)).map(|_| Message::Tick));
+        }
+        if self
+            .instances
+            .iter()
+            .any(|instance| instance.backups.schedule.is_some())
+        {
+            subscriptions
+                .push(iced::time::every(BACKUP_CHECK_INTERVAL).map(|_| Message::ScheduledBackups));
+        }
+
+        Subscription::batch(subscriptions)
     }
 
-    fn view(&self) -> Element<Message> {
+    fn view(&self) -> Element<'_, Message> {
         let menu = row![
             button("New Instance")
                 .style(button::primary)
                 .on_press(Message::NewInstance),
+            button("Adopt Data Folder")
+                .style(button::secondary)
+                .on_press(Message::AdoptDataFolder),
+            button("Browse Mods")
+                .style(button::secondary)
+                .on_press(Message::BrowseMods),
+            button("Import Mods")
+                .style(button::secondary)
+                .on_press(Message::ImportModList),
             button("Folders").style(button::secondary), // TODO: dropdown
             button("Refresh")
                 .on_press(Message::Refresh)
@@ -228,7 +1504,9 @@ impl Rustic {
             button("L/D")
                 .on_press(Message::ToggleDark)
                 .style(button::secondary),
-            button("Settings").style(button::danger),
+            button("Settings")
+                .on_press(Message::LauncherSettings)
+                .style(button::secondary),
         ]
         .spacing(10)
         .align_y(Center);
@@ -237,7 +1515,11 @@ impl Rustic {
             button(
                 ui::instance_row_base(instance)
                     .push(horizontal_space())
-                    .push(button("Play").style(button::primary)),
+                    .push(
+                        button("Play")
+                            .style(button::primary)
+                            .on_press(Message::Launch(index)),
+                    ),
             )
             .style(style::instance_button)
             .width(Length::Fill)
@@ -245,9 +1527,19 @@ impl Rustic {
             .on_press(Message::SelectInstance(index))
         });
 
-        let instance_list = column(instance_widgets.map(Element::from))
-            .spacing(10)
-            .padding(10);
+        let broken_widgets = self
+            .broken_instances
+            .iter()
+            .enumerate()
+            .map(|(index, broken)| ui::broken_instance_row(broken, index));
+
+        let instance_list = column(
+            instance_widgets
+                .map(Element::from)
+                .chain(broken_widgets.map(Element::from)),
+        )
+        .spacing(10)
+        .padding(10);
 
         let content = column![
             menu.padding(10),
@@ -257,8 +1549,16 @@ impl Rustic {
         .width(Length::Fill)
         .height(Length::Fill);
 
-        match &self.show_modal {
-            Some(Modal::ViewInstance) => ui::modal(content, ui::instance_view(self), Message::None),
+        let view = match &self.show_modal {
+            Some(Modal::ViewInstance) => match ui::instance_view(self) {
+                Some(view) => ui::modal(content, view, Message::None),
+                None => stack![content].into(),
+            },
+            Some(Modal::ConfirmLaunch(index)) => ui::modal(
+                content,
+                ui::confirm_launch(&self.instances[*index], *index),
+                Message::None,
+            ),
             Some(Modal::NewInstance) => ui::modal(
                 content,
                 ui::instance_form(
@@ -279,7 +1579,30 @@ impl Rustic {
                 ),
                 Message::None,
             ),
+            Some(Modal::AdoptDataFolder) => ui::modal(content, ui::adopt_form(self), Message::None),
+            Some(Modal::LauncherSettings) => {
+                ui::modal(content, ui::launcher_settings_form(self), Message::None)
+            }
+            Some(Modal::ImportModList) => match &self.mod_list_form {
+                Some(form) => ui::modal(content, form.view(&self.instances), Message::None),
+                None => stack![content].into(),
+            },
+            Some(Modal::BrowseMods) => match (&self.mod_detail, &self.mod_browser) {
+                (Some(detail), _) => ui::modal(
+                    content,
+                    container(detail.view(&self.instances, self.theme().palette()))
+                        .width(Length::Fill)
+                        .style(style::rounded_container),
+                    Message::None,
+                ),
+                (None, Some(browser)) => {
+                    ui::modal(content, browser.view(&self.instances), Message::None)
+                }
+                (None, None) => stack![content].into(),
+            },
             None => stack![content].into(),
-        }
+        };
+
+        stack![view, ui::toasts(&self.toasts)].into()
     }
 }
@@ -0,0 +1,462 @@
+use crate::ui::icon_handle;
+use crate::{Message, style};
+use iced::alignment::{Horizontal, Vertical};
+use iced::task;
+use iced::widget::{
+    Container, button, center, column, container, horizontal_rule, horizontal_space, image,
+    pick_list, row, scrollable, text, text_input,
+};
+use iced::{Element, Length, Task};
+use log::{debug, error};
+use rustic_vs::api::{ModDb, ModSummary, SearchQuery, SortOrder, Tag};
+use rustic_vs::http_cache::Cached;
+use rustic_vs::instance::Instance;
+use rustic_vs::mods::ModSide;
+use rustic_vs::updates::install_release;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+const PAGE_SIZE: usize = 20;
+
+#[derive(Debug, Clone)]
+pub enum BrowserMessage {
+    Text(String),
+    Search,
+    AddTag(Tag),
+    RemoveTag(u64),
+    Sort(SortOrder),
+    Page(usize),
+    Target(InstanceChoice),
+    TagsLoaded(Result<Vec<Tag>, String>),
+    Results(u64, Result<Cached<Vec<ModSummary>>, String>),
+    Details(u64, Option<Vec<String>>, Option<Vec<u8>>),
+    Install(u64),
+}
+
+/// Instance to install into, as pick list entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstanceChoice {
+    index: usize,
+    name: String,
+}
+
+impl fmt::Display for InstanceChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// Loaded lazily for the mods of the current page.
+#[derive(Debug)]
+struct Details {
+    versions: Option<Vec<String>>,
+    logo: Option<image::Handle>,
+}
+
+/// Search and install mods from ModDB.
+pub struct ModBrowser {
+    query: SearchQuery,
+    tags: Vec<Tag>,
+    /// `None` while searching.
+    results: Option<Result<Vec<ModSummary>, String>>,
+    /// Results came from the cache, ModDB could not be reached.
+    stale: bool,
+    /// Answers of older searches are dropped.
+    search_id: u64,
+    page: usize,
+    details: HashMap<u64, Details>,
+    target: Option<usize>,
+    installing: HashSet<u64>,
+    /// Running requests of the current search and page, aborted when they are outdated.
+    search_request: Option<task::Handle>,
+    details_request: Option<task::Handle>,
+}
+
+impl ModBrowser {
+    pub fn open(target: Option<usize>, moddb: &ModDb) -> (ModBrowser, Task<Message>) {
+        let mut browser = Self {
+            query: SearchQuery::default(),
+            tags: Vec::new(),
+            results: None,
+            stale: false,
+            search_id: 0,
+            page: 0,
+            details: HashMap::new(),
+            target,
+            installing: HashSet::new(),
+            search_request: None,
+            details_request: None,
+        };
+
+        let tags_moddb = moddb.clone();
+        let tags = Task::perform(
+            async move { tags_moddb.tags().await.map_err(|e| e.to_string()) },
+            |result| Message::Browser(BrowserMessage::TagsLoaded(result)),
+        );
+        let search = browser.search(moddb);
+        (browser, Task::batch([tags, search]))
+    }
+
+    fn search(&mut self, moddb: &ModDb) -> Task<Message> {
+        if let Some(request) = self.search_request.take() {
+            request.abort();
+        }
+        self.search_id += 1;
+        self.results = None;
+        self.page = 0;
+
+        let (search_id, query, moddb) = (self.search_id, self.query.clone(), moddb.clone());
+        let (task, handle) = Task::perform(
+            async move { moddb.search(&query).await.map_err(|e| e.to_string()) },
+            move |result| Message::Browser(BrowserMessage::Results(search_id, result)),
+        )
+        .abortable();
+        self.search_request = Some(handle.abort_on_drop());
+        task
+    }
+
+    fn page_mods(&self) -> &[ModSummary] {
+        match &self.results {
+            Some(Ok(mods)) => {
+                let start = (self.page * PAGE_SIZE).min(mods.len());
+                &mods[start..(start + PAGE_SIZE).min(mods.len())]
+            }
+            _ => &[],
+        }
+    }
+
+    /// Fetches releases and logos of the current page that are not loaded yet.
+    fn load_details(&mut self, moddb: &ModDb) -> Task<Message> {
+        if let Some(request) = self.details_request.take() {
+            request.abort();
+        }
+        let missing: Vec<ModSummary> = self
+            .page_mods()
+            .iter()
+            .filter(|summary| !self.details.contains_key(&summary.id))
+            .cloned()
+            .collect();
+
+        let (task, handle) = Task::batch(missing.into_iter().map(|summary| {
+            let moddb = moddb.clone();
+            Task::perform(
+                async move {
+                    let versions = match moddb.get_mod(&summary.id.to_string()).await {
+                        Ok(mod_db_mod) => mod_db_mod.map(|m| m.supported_versions()),
+                        Err(e) => {
+                            debug!("failed loading {}: {}", summary.name, e);
+                            None
+                        }
+                    };
+                    let logo = match &summary.logo {
+                        Some(url) => moddb.get_file(url).await.ok(),
+                        None => None,
+                    };
+                    (summary.id, versions, logo)
+                },
+                |(id, versions, logo)| {
+                    Message::Browser(BrowserMessage::Details(id, versions, logo))
+                },
+            )
+        }))
+        .abortable();
+        self.details_request = Some(handle.abort_on_drop());
+        task
+    }
+
+    /// Install results are handled by [`Message::ModInstalled`].
+    pub fn update(
+        &mut self,
+        message: BrowserMessage,
+        instances: &[Instance],
+        moddb: &ModDb,
+    ) -> Task<Message> {
+        match message {
+            BrowserMessage::Text(text) => {
+                self.query.text = text;
+                Task::none()
+            }
+            BrowserMessage::Search => self.search(moddb),
+            BrowserMessage::AddTag(tag) => {
+                if !self.query.tags.contains(&tag) {
+                    self.query.tags.push(tag);
+                }
+                self.search(moddb)
+            }
+            BrowserMessage::RemoveTag(id) => {
+                self.query.tags.retain(|tag| tag.id != id);
+                self.search(moddb)
+            }
+            BrowserMessage::Sort(sort) => {
+                self.query.sort = sort;
+                self.search(moddb)
+            }
+            BrowserMessage::Page(page) => {
+                self.page = page;
+                self.load_details(moddb)
+            }
+            BrowserMessage::Target(choice) => {
+                self.target = Some(choice.index);
+                Task::none()
+            }
+            BrowserMessage::TagsLoaded(result) => {
+                match result {
+                    Ok(tags) => self.tags = tags,
+                    Err(e) => error!("failed loading ModDB tags: {}", e),
+                }
+                Task::none()
+            }
+            BrowserMessage::Results(search_id, result) => {
+                if search_id != self.search_id {
+                    return Task::none();
+                }
+                if let Err(e) = &result {
+                    error!("failed searching ModDB: {}", e);
+                }
+                self.stale = result.as_ref().is_ok_and(|cached| cached.stale);
+                self.results = Some(result.map(|cached| cached.value));
+                self.load_details(moddb)
+            }
+            BrowserMessage::Details(id, versions, logo) => {
+                self.details.insert(
+                    id,
+                    Details {
+                        versions,
+                        logo: logo.map(image::Handle::from_bytes),
+                    },
+                );
+                Task::none()
+            }
+            BrowserMessage::Install(id) => {
+                let Some(instance) = self.target.and_then(|index| instances.get(index)) else {
+                    return Task::none();
+                };
+                self.installing.insert(id);
+
+                let (folder, instance) = (instance.folder_name_string(), instance.clone());
+                let moddb = moddb.clone();
+                Task::perform(
+                    async move {
+                        let mod_db_mod = moddb
+                            .get_mod(&id.to_string())
+                            .await
+                            .map_err(|e| e.to_string())?
+                            .ok_or("not found on ModDB")?;
+                        let release = mod_db_mod
+                            .latest_release(instance.game_version.as_ref())
+                            .ok_or("no release for the game version")?;
+                        install_release(&moddb, &instance, release)
+                            .await
+                            .map_err(|e| e.to_string())?;
+                        Ok(format!("{} {}", mod_db_mod.name, release.version))
+                    },
+                    move |result| Message::ModInstalled(folder.clone(), id, result),
+                )
+            }
+        }
+    }
+
+    pub fn target(&self) -> Option<usize> {
+        self.target
+    }
+
+    pub fn installed(&mut self, id: u64) {
+        self.installing.remove(&id);
+    }
+
+    pub fn view<'a>(&'a self, instances: &'a [Instance]) -> Container<'a, Message> {
+        let target = self.target.and_then(|index| instances.get(index));
+        let choices: Vec<InstanceChoice> = instances
+            .iter()
+            .enumerate()
+            .map(|(index, instance)| InstanceChoice {
+                index,
+                name: instance.name.clone(),
+            })
+            .collect();
+        let selected = target.map(|instance| InstanceChoice {
+            index: self.target.unwrap_or_default(),
+            name: instance.name.clone(),
+        });
+
+        let search = row![
+            text_input("Search mods", &self.query.text)
+                .on_input(|text| Message::Browser(BrowserMessage::Text(text)))
+                .on_submit(Message::Browser(BrowserMessage::Search)),
+            pick_list(SortOrder::ALL, Some(self.query.sort), |sort| {
+                Message::Browser(BrowserMessage::Sort(sort))
+            }),
+            pick_list(
+                self.tags
+                    .iter()
+                    .filter(|tag| !self.query.tags.contains(tag))
+                    .cloned()
+                    .collect::<Vec<_>>(),
+                None::<Tag>,
+                |tag| Message::Browser(BrowserMessage::AddTag(tag)),
+            )
+            .placeholder("Add tag"),
+        ]
+        .spacing(10)
+        .align_y(Vertical::Center);
+
+        let filters = row(self.query.tags.iter().map(|tag| {
+            button(text(format!("{} ✕", tag.name)).size(12))
+                .style(button::secondary)
+                .on_press(Message::Browser(BrowserMessage::RemoveTag(tag.id)))
+                .into()
+        }))
+        .spacing(5)
+        .push(horizontal_space())
+        .push(text("Install into:"))
+        .push(
+            pick_list(choices, selected, |choice| {
+                Message::Browser(BrowserMessage::Target(choice))
+            })
+            .placeholder("instance"),
+        )
+        .spacing(10)
+        .align_y(Vertical::Center);
+
+        let results: Element<Message> = match &self.results {
+            None => center(text("searching…")).height(100).into(),
+            Some(Err(e)) => center(text(e).style(text::danger)).height(100).into(),
+            Some(Ok(mods)) if mods.is_empty() => center(text("no mods found")).height(100).into(),
+            Some(Ok(_)) => scrollable(
+                column(
+                    self.page_mods()
+                        .iter()
+                        .enumerate()
+                        .map(|(index, summary)| self.card(summary, index, target)),
+                )
+                .padding(10),
+            )
+            .height(Length::Fill)
+            .into(),
+        };
+
+        let pages = match &self.results {
+            Some(Ok(mods)) => mods.len().div_ceil(PAGE_SIZE),
+            _ => 0,
+        };
+        let pagination = row![
+            horizontal_space(),
+            button("<").style(button::secondary).on_press_maybe(
+                (self.page > 0).then(|| Message::Browser(BrowserMessage::Page(self.page - 1)))
+            ),
+            text(format!("{} / {}", self.page + 1, pages.max(1))),
+            button(">").style(button::secondary).on_press_maybe(
+                (self.page + 1 < pages)
+                    .then(|| Message::Browser(BrowserMessage::Page(self.page + 1)))
+            ),
+            horizontal_space(),
+        ]
+        .spacing(10)
+        .align_y(Vertical::Center);
+
+        container(column![
+            row![text("Browse ModDB").size(20)]
+                .push_maybe(self.stale.then(|| {
+                    text("offline, showing cached results")
+                        .size(12)
+                        .style(text::secondary)
+                }))
+                .push(horizontal_space())
+                .push(
+                    button("X")
+                        .style(button::secondary)
+                        .on_press(Message::HideModal),
+                )
+                .spacing(10)
+                .align_y(Vertical::Center)
+                .padding(10),
+            horizontal_rule(1),
+            column![search, filters].spacing(10).padding(10),
+            horizontal_rule(1),
+            results,
+            pagination.padding(10),
+        ])
+        .width(Length::Fill)
+        .style(style::rounded_container)
+    }
+
+    fn card<'a>(
+        &'a self,
+        summary: &'a ModSummary,
+        index: usize,
+        target: Option<&Instance>,
+    ) -> Element<'a, Message> {
+        let details = self.details.get(&summary.id);
+        let logo = details
+            .and_then(|details| details.logo.clone())
+            .unwrap_or_else(|| icon_handle(&None));
+        let versions = match details.and_then(|details| details.versions.as_ref()) {
+            Some(versions) if versions.is_empty() => "Game: unknown".to_string(),
+            Some(versions) => format!("Game: {}", versions.join(", ")),
+            None => "Game: …".to_string(),
+        };
+        let is_installed = target.is_some_and(|instance| summary.is_installed(&instance.mods));
+        let installing = self.installing.contains(&summary.id);
+        let client_only = target
+            .is_some_and(|instance| !instance.loads_side(ModSide::from(summary.side.as_str())));
+
+        let install = button(
+            text(if installing {
+                "Installing…"
+            } else if client_only {
+                "Client only"
+            } else {
+                "Install"
+            })
+            .size(12)
+            .align_x(Horizontal::Center),
+        )
+        .width(80)
+        .on_press_maybe(
+            (target.is_some() && !installing && !client_only)
+                .then_some(Message::Browser(BrowserMessage::Install(summary.id))),
+        );
+
+        container(
+            row![
+                image(logo).width(64).height(64),
+                column![
+                    row![
+                        button(text(&summary.name).size(16))
+                            .style(button::text)
+                            .padding(0)
+                            .on_press(Message::BrowseModDetail(summary.id))
+                    ]
+                    .push_maybe(is_installed.then(|| {
+                        container(text("installed").size(12))
+                            .padding([1, 5])
+                            .style(style::badge_success)
+                    }))
+                    .spacing(10)
+                    .align_y(Vertical::Center),
+                    text(format!(
+                        "by {} • {} downloads • {}",
+                        summary.author, summary.downloads, versions
+                    ))
+                    .size(12),
+                    text(summary.summary.as_deref().unwrap_or_default()).size(12),
+                ]
+                .push_maybe(
+                    (!summary.tags.is_empty()).then(|| text(format!(
+                        "Tags: {}",
+                        summary.tags.join(", ")
+                    ))
+                    .size(12))
+                )
+                .spacing(3)
+                .width(Length::Fill),
+                install,
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+        )
+        .padding([5, 10])
+        .style(style::striped(index))
+        .into()
+    }
+}
@@ -0,0 +1,271 @@
+use crate::mods::ModInfo;
+use crate::settings::{SettingsError, read_json, write_json};
+use log::debug;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const MOD_CONFIG_FOLDER: &str = "ModConfig";
+
+/// Suffix some mods add to their config file name.
+const CONFIG_SUFFIX: &str = "config";
+
+/// All JSON files in the `ModConfig` folder of a data folder.
+pub fn config_files(data_path: &Path) -> Vec<PathBuf> {
+    let Ok(read_dir) = fs::read_dir(data_path.join(MOD_CONFIG_FOLDER)) else {
+        return Vec::new();
+    };
+
+    read_dir
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "json"))
+        .collect()
+}
+
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Mods name their config files freely, e.g. `carryon.json` or `CarryOnConfig.json`, so the
+/// normalized file stem, with or without a `Config` suffix, must equal the normalized mod id
+/// or name.
+pub fn find_config<'a>(config_files: &'a [PathBuf], mod_info: &ModInfo) -> Option<&'a PathBuf> {
+    let candidates: Vec<String> = [&mod_info.mod_id, &mod_info.name]
+        .iter()
+        .map(|name| normalize(name))
+        .filter(|name| !name.is_empty())
+        .collect();
+
+    config_files.iter().find(|path| {
+        let stem = normalize(&path.file_stem().unwrap_or_default().to_string_lossy());
+        let without_suffix = stem.strip_suffix(CONFIG_SUFFIX).unwrap_or(&stem);
+        candidates
+            .iter()
+            .any(|candidate| *candidate == stem || candidate == without_suffix)
+    })
+}
+
+pub fn load_config(path: &Path) -> Result<Value, SettingsError> {
+    read_json(path)
+}
+
+/// Writes the config after checking it still has the structure of the file, the previous
+/// version is kept as `.bak`.
+pub fn save_config(path: &Path, value: &Value) -> Result<(), SettingsError> {
+    if path.is_file() {
+        if let Some(key) = structure_change(&read_json(path)?, value, &mut Vec::new()) {
+            return Err(SettingsError::StructureChanged(key));
+        }
+
+        let mut backup = path.as_os_str().to_os_string();
+        backup.push(".bak");
+        debug!("backing up {}", path.display());
+        fs::copy(path, backup)?;
+    }
+
+    write_json(path, value)
+}
+
+/// First key where `value` differs from the keys and leaf types of `original`. `null` and
+/// empty containers in the original may become anything.
+fn structure_change(original: &Value, value: &Value, path: &mut Vec<String>) -> Option<String> {
+    let changed = |path: &[String]| Some(path.join("."));
+    match (original, value) {
+        (Value::Object(original), Value::Object(map)) if !original.is_empty() => {
+            if original.len() != map.len() {
+                return changed(path);
+            }
+            original.iter().find_map(|(key, original)| {
+                path.push(key.clone());
+                let change = match map.get(key) {
+                    Some(value) => structure_change(original, value, path),
+                    None => changed(path),
+                };
+                path.pop();
+                change
+            })
+        }
+        (Value::Array(original), Value::Array(values)) if !original.is_empty() => {
+            if original.len() != values.len() {
+                return changed(path);
+            }
+            original
+                .iter()
+                .zip(values)
+                .enumerate()
+                .find_map(|(index, (original, value))| {
+                    path.push(index.to_string());
+                    let change = structure_change(original, value, path);
+                    path.pop();
+                    change
+                })
+        }
+        (Value::Null, _)
+        | (Value::Bool(_), Value::Bool(_))
+        | (Value::Number(_), Value::Number(_))
+        | (Value::String(_), Value::String(_)) => None,
+        (Value::Object(original), _) if original.is_empty() => None,
+        (Value::Array(original), _) if original.is_empty() => None,
+        _ => changed(path),
+    }
+}
+
+/// Leaf of a config, `path` holds object keys and array indices.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigLeaf {
+    pub path: Vec<String>,
+    pub value: Value,
+}
+
+impl ConfigLeaf {
+    pub fn key(&self) -> String {
+        self.path.join(".")
+    }
+}
+
+/// Flattens a config into its leaves in document order, empty objects and arrays are leaves.
+pub fn leaves(value: &Value) -> Vec<ConfigLeaf> {
+    let mut leaves = Vec::new();
+    collect_leaves(&mut leaves, Vec::new(), value);
+    leaves
+}
+
+fn collect_leaves(leaves: &mut Vec<ConfigLeaf>, path: Vec<String>, value: &Value) {
+    let children: Vec<(String, &Value)> = match value {
+        Value::Object(map) if !map.is_empty() => map
+            .iter()
+            .map(|(key, value)| (key.clone(), value))
+            .collect(),
+        Value::Array(values) if !values.is_empty() => values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| (index.to_string(), value))
+            .collect(),
+        _ => {
+            leaves.push(ConfigLeaf {
+                path,
+                value: value.clone(),
+            });
+            return;
+        }
+    };
+
+    for (key, child) in children {
+        let mut child_path = path.clone();
+        child_path.push(key);
+        collect_leaves(leaves, child_path, child);
+    }
+}
+
+/// Replaces the leaf at `path`, returns `false` if the path does not exist.
+pub fn set_leaf(value: &mut Value, path: &[String], new_value: Value) -> bool {
+    let mut current = value;
+    for part in path {
+        let next = match current {
+            Value::Object(map) => map.get_mut(part),
+            Value::Array(values) => part.parse::<usize>().ok().and_then(|i| values.get_mut(i)),
+            _ => None,
+        };
+        match next {
+            Some(next) => current = next,
+            None => return false,
+        }
+    }
+    *current = new_value;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::mod_info;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn named(mod_id: &str, name: &str) -> ModInfo {
+        ModInfo {
+            mod_id: mod_id.to_string(),
+            name: name.to_string(),
+            ..mod_info(mod_id, "1.0.0")
+        }
+    }
+
+    #[test]
+    fn find_config_by_id_or_name() {
+        let files = [
+            "ModConfig/CarryOnConfig.json",
+            "ModConfig/primitive-survival.json",
+        ]
+        .map(PathBuf::from);
+
+        assert_eq!(
+            find_config(&files, &named("carryon", "Carry On")),
+            Some(&files[0])
+        );
+        assert_eq!(
+            find_config(&files, &named("", "Primitive Survival")),
+            Some(&files[1])
+        );
+        assert_eq!(find_config(&files, &named("ps", "")), None);
+        // only exact names, not names containing the id
+        assert_eq!(find_config(&files, &named("carry", "")), None);
+        assert_eq!(find_config(&files, &named("survival", "")), None);
+    }
+
+    #[test]
+    fn save_keeps_structure() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("config.json");
+        fs::write(
+            &path,
+            r#"{ "range": { "min": 1 }, "items": [], "extra": null }"#,
+        )
+        .unwrap();
+
+        let edited = json!({ "range": { "min": 2.5 }, "items": ["a"], "extra": { "b": 1 } });
+        save_config(&path, &edited).unwrap();
+        assert_eq!(load_config(&path).unwrap(), edited);
+
+        let renamed = json!({ "range": { "max": 2 }, "items": ["a"], "extra": null });
+        let Err(SettingsError::StructureChanged(key)) = save_config(&path, &renamed) else {
+            panic!("renamed key saved");
+        };
+        assert_eq!(key, "range.min");
+        let retyped = json!({ "range": { "min": "2" }, "items": ["a"], "extra": null });
+        assert!(save_config(&path, &retyped).is_err());
+
+        assert!(temp_dir.path().join("config.json.bak").is_file());
+    }
+
+    #[test]
+    fn leaves_roundtrip() {
+        let mut config = json!({
+            "enabled": true,
+            "range": { "min": 1, "max": 2.5 },
+            "items": ["a", "b"],
+            "empty": {}
+        });
+
+        let keys: Vec<String> = leaves(&config).iter().map(ConfigLeaf::key).collect();
+        assert_eq!(
+            keys,
+            [
+                "enabled",
+                "range.min",
+                "range.max",
+                "items.0",
+                "items.1",
+                "empty"
+            ]
+        );
+
+        let path = ["items", "1"].map(String::from);
+        assert!(set_leaf(&mut config, &path, json!("c")));
+        assert!(!set_leaf(&mut config, &["missing".to_string()], json!(1)));
+        assert_eq!(config["items"], json!(["a", "c"]));
+    }
+}
@@ -0,0 +1,188 @@
+use crate::{Message, style};
+use iced::alignment::Vertical;
+use iced::widget::{
+    Column, Row, button, checkbox, column, container, horizontal_space, row, scrollable, text,
+    text_input,
+};
+use iced::{Element, Length, Padding};
+use rustic_vs::mod_config::{ConfigLeaf, leaves, load_config, save_config, set_leaf};
+use rustic_vs::mods::ModInfo;
+use rustic_vs::settings::SettingsError;
+use serde_json::Value;
+use std::path::PathBuf;
+
+const INDENT: u16 = 15;
+
+#[derive(Debug, Clone)]
+pub enum ModConfigMessage {
+    Input(usize, String),
+    Toggle(usize, bool),
+    Save,
+}
+
+/// Form over all leaves of a mod config, values are kept as text until they are saved.
+pub struct ModConfigEditor {
+    pub mod_name: String,
+    path: PathBuf,
+    config: Value,
+    leaves: Vec<ConfigLeaf>,
+    inputs: Vec<String>,
+    error: Option<String>,
+}
+
+fn leaf_input(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Bool(_) => String::new(),
+        value => value.to_string(),
+    }
+}
+
+/// Parses the input back into the type of the original value, `null` and empty containers
+/// take any JSON5 value.
+fn parse_leaf(leaf: &ConfigLeaf, input: &str) -> Result<Value, String> {
+    let invalid = |kind| format!("{} '{}' is not {}", leaf.key(), input, kind);
+    match &leaf.value {
+        Value::String(_) => Ok(Value::String(input.to_string())),
+        Value::Bool(b) => Ok(Value::Bool(*b)),
+        Value::Number(n) if n.is_f64() => input
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(Value::Number)
+            .ok_or_else(|| invalid("a number")),
+        // mods often read whole numbers as floats, so they may become fractions
+        Value::Number(_) => {
+            let input = input.trim();
+            match input.parse::<i64>() {
+                Ok(n) => Ok(Value::from(n)),
+                Err(_) => input
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(serde_json::Number::from_f64)
+                    .map(Value::Number)
+                    .ok_or_else(|| invalid("a number")),
+            }
+        }
+        _ => json5::from_str(input).map_err(|_| invalid("valid JSON")),
+    }
+}
+
+impl ModConfigEditor {
+    pub fn load(mod_info: &ModInfo) -> Result<ModConfigEditor, SettingsError> {
+        let path = mod_info.config_path.clone().unwrap_or_default();
+        let config = load_config(&path)?;
+        let leaves = leaves(&config);
+
+        Ok(Self {
+            mod_name: mod_info.name.clone(),
+            inputs: leaves.iter().map(|leaf| leaf_input(&leaf.value)).collect(),
+            leaves,
+            config,
+            path,
+            error: None,
+        })
+    }
+
+    fn to_config(&self) -> Result<Value, String> {
+        let mut config = self.config.clone();
+        for (leaf, input) in self.leaves.iter().zip(&self.inputs) {
+            set_leaf(&mut config, &leaf.path, parse_leaf(leaf, input)?);
+        }
+        Ok(config)
+    }
+
+    /// Returns `Some` with the save result when the config was written.
+    pub fn update(&mut self, message: ModConfigMessage) -> Option<Result<(), SettingsError>> {
+        match message {
+            ModConfigMessage::Input(index, value) => self.inputs[index] = value,
+            ModConfigMessage::Toggle(index, value) => self.leaves[index].value = Value::Bool(value),
+            ModConfigMessage::Save => match self.to_config() {
+                Ok(config) => {
+                    let result = save_config(&self.path, &config);
+                    if result.is_ok() {
+                        self.config = config;
+                        self.error = None;
+                    }
+                    return Some(result);
+                }
+                Err(e) => self.error = Some(e),
+            },
+        }
+        None
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let mut tree = Column::new().spacing(5).padding(10);
+        let mut previous: &[String] = &[];
+
+        for (index, (leaf, input)) in self.leaves.iter().zip(&self.inputs).enumerate() {
+            let (name, parents) = leaf.path.split_last().map_or_else(
+                || ("<value>", &[][..]),
+                |(name, parents)| (name.as_str(), parents),
+            );
+
+            // headers for every object or array not shared with the previous leaf
+            let shared = previous
+                .iter()
+                .zip(parents)
+                .take_while(|(a, b)| a == b)
+                .count();
+            for (depth, parent) in parents.iter().enumerate().skip(shared) {
+                tree = tree.push(
+                    container(text(parent).size(16))
+                        .padding(Padding::from([5, 0]).left(depth as u16 * INDENT)),
+                );
+            }
+            previous = parents;
+
+            let widget: Element<Message> = match leaf.value {
+                Value::Bool(value) => checkbox("", value)
+                    .on_toggle(move |value| {
+                        Message::ModConfig(ModConfigMessage::Toggle(index, value))
+                    })
+                    .into(),
+                _ => text_input("", input)
+                    .style(if parse_leaf(leaf, input).is_ok() {
+                        text_input::default
+                    } else {
+                        style::text_input_warning
+                    })
+                    .on_input(move |value| {
+                        Message::ModConfig(ModConfigMessage::Input(index, value))
+                    })
+                    .into(),
+            };
+            tree = tree.push(config_row(name, parents.len(), widget));
+        }
+
+        column![
+            scrollable(tree).height(Length::Fill),
+            row![text(self.path.display().to_string()).size(12)]
+                .push_maybe(
+                    self.error
+                        .as_ref()
+                        .map(|e| text(e).size(12).style(text::danger))
+                )
+                .push(horizontal_space())
+                .push(button("Save").on_press(Message::ModConfig(ModConfigMessage::Save)))
+                .push(
+                    button("Back")
+                        .style(button::secondary)
+                        .on_press(Message::CloseModConfig)
+                )
+                .spacing(10)
+                .padding(10)
+                .align_y(Vertical::Center),
+        ]
+        .into()
+    }
+}
+
+fn config_row<'a>(label: &'a str, depth: usize, widget: Element<'a, Message>) -> Row<'a, Message> {
+    row![text(label).width(180), widget]
+        .spacing(10)
+        .padding(Padding::ZERO.left(depth as u16 * INDENT))
+        .align_y(Vertical::Center)
+}
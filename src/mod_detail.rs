@@ -0,0 +1,383 @@
+use crate::Message;
+use crate::ui::norm_str;
+use iced::alignment::Vertical;
+use iced::task;
+use iced::theme::Palette;
+use iced::widget::{
+    Column, button, column, container, horizontal_rule, horizontal_space, image, markdown, row,
+    scrollable, text,
+};
+use iced::{Element, Length, Task};
+use log::{debug, error};
+use rustic_vs::api::{ModDb, ModDbMod, Release, html_to_markdown};
+use rustic_vs::http_cache::Cached;
+use rustic_vs::instance::Instance;
+use rustic_vs::mods::{ModInfo, ModSide};
+use rustic_vs::updates::install_release;
+
+#[derive(Debug, Clone)]
+pub enum ModDetailMessage {
+    Loaded(Result<Cached<Option<ModDbMod>>, String>),
+    Screenshot(usize, Option<Vec<u8>>),
+    Install(u64),
+}
+
+fn parse_html(html: &str) -> Vec<markdown::Item> {
+    markdown::parse(&html_to_markdown(html)).collect()
+}
+
+fn markdown_view(items: &[markdown::Item], palette: Palette) -> Element<'_, Message> {
+    markdown::view(
+        items,
+        markdown::Settings::with_text_size(12),
+        markdown::Style::from_palette(palette),
+    )
+    .map(Message::OpenUrl)
+}
+
+/// Installed mod data and its ModDB page, opened from the mods list or the mod browser.
+pub struct ModDetail {
+    mod_info: Option<ModInfo>,
+    target: Option<usize>,
+    /// `None` while loading.
+    mod_db_mod: Option<Result<Option<ModDbMod>, String>>,
+    /// ModDB could not be reached, the page is from the cache.
+    stale: bool,
+    description: Vec<markdown::Item>,
+    changelogs: Vec<Vec<markdown::Item>>,
+    screenshots: Vec<Option<image::Handle>>,
+    installing: Option<u64>,
+    /// Closing the view cancels loading the page and screenshots.
+    requests: Vec<task::Handle>,
+}
+
+impl ModDetail {
+    /// Looks the mod up by mod id or ModDB id, releases are installed into `target`.
+    pub fn open(
+        lookup: String,
+        mod_info: Option<ModInfo>,
+        target: Option<usize>,
+        moddb: &ModDb,
+    ) -> (ModDetail, Task<Message>) {
+        let mut detail = Self {
+            mod_info,
+            target,
+            mod_db_mod: None,
+            stale: false,
+            description: Vec::new(),
+            changelogs: Vec::new(),
+            screenshots: Vec::new(),
+            installing: None,
+            requests: Vec::new(),
+        };
+        if lookup.is_empty() {
+            detail.mod_db_mod = Some(Ok(None));
+            return (detail, Task::none());
+        }
+
+        let moddb = moddb.clone();
+        let (task, handle) = Task::perform(
+            async move {
+                moddb
+                    .get_mod_cached(&lookup)
+                    .await
+                    .map_err(|e| e.to_string())
+            },
+            |result| Message::ModDetail(ModDetailMessage::Loaded(result)),
+        )
+        .abortable();
+        detail.requests.push(handle.abort_on_drop());
+        (detail, task)
+    }
+
+    pub fn update(
+        &mut self,
+        message: ModDetailMessage,
+        instances: &[Instance],
+        moddb: &ModDb,
+    ) -> Task<Message> {
+        match message {
+            ModDetailMessage::Loaded(result) => {
+                let result = result.map(|cached| {
+                    self.stale = cached.stale;
+                    cached.value
+                });
+                let mut task = Task::none();
+                match &result {
+                    Ok(Some(mod_db_mod)) => {
+                        self.description = parse_html(&mod_db_mod.text);
+                        self.changelogs = mod_db_mod
+                            .releases
+                            .iter()
+                            .map(|release| parse_html(&release.changelog))
+                            .collect();
+                        self.screenshots = vec![None; mod_db_mod.screenshots.len()];
+                        let (screenshots, handle) =
+                            Task::batch(mod_db_mod.screenshots.iter().enumerate().map(
+                                |(index, screenshot)| {
+                                    let (url, moddb) = (screenshot.url.clone(), moddb.clone());
+                                    Task::perform(
+                                        async move {
+                                            match moddb.get_file(&url).await {
+                                                Ok(data) => Some(data),
+                                                Err(e) => {
+                                                    debug!("failed loading {}: {}", url, e);
+                                                    None
+                                                }
+                                            }
+                                        },
+                                        move |data| {
+                                            Message::ModDetail(ModDetailMessage::Screenshot(
+                                                index, data,
+                                            ))
+                                        },
+                                    )
+                                },
+                            ))
+                            .abortable();
+                        self.requests.push(handle.abort_on_drop());
+                        task = screenshots;
+                    }
+                    Ok(None) => {}
+                    Err(e) => error!("failed loading mod details: {}", e),
+                }
+                self.mod_db_mod = Some(result);
+                task
+            }
+            ModDetailMessage::Screenshot(index, data) => {
+                if let Some(screenshot) = self.screenshots.get_mut(index) {
+                    *screenshot = data.map(image::Handle::from_bytes);
+                }
+                Task::none()
+            }
+            ModDetailMessage::Install(release_id) => {
+                let (Some(index), Some(Ok(Some(mod_db_mod)))) = (self.target, &self.mod_db_mod)
+                else {
+                    return Task::none();
+                };
+                let Some(release) = mod_db_mod
+                    .releases
+                    .iter()
+                    .find(|release| release.id == release_id)
+                    .cloned()
+                else {
+                    return Task::none();
+                };
+                self.installing = Some(release_id);
+
+                let (id, name) = (mod_db_mod.id, mod_db_mod.name.clone());
+                let (instance, moddb) = (instances[index].clone(), moddb.clone());
+                let folder = instance.folder_name_string();
+                Task::perform(
+                    async move {
+                        install_release(&moddb, &instance, &release)
+                            .await
+                            .map_err(|e| e.to_string())?;
+                        Ok(format!("{} {}", name, release.version))
+                    },
+                    move |result| Message::ModInstalled(folder.clone(), id, result),
+                )
+            }
+        }
+    }
+
+    /// Picks up the installed release after [`Message::ModInstalled`].
+    pub fn installed(&mut self, instance: Option<&Instance>) {
+        self.installing = None;
+        if let Some(instance) = instance
+            && let Some(mod_info) = &mut self.mod_info
+            && let Some(reloaded) = instance
+                .mods
+                .iter()
+                .find(|m| m.mod_id.eq_ignore_ascii_case(&mod_info.mod_id))
+        {
+            *mod_info = reloaded.clone();
+        }
+    }
+
+    /// Version of the mod in the target instance.
+    fn installed_version<'a>(&'a self, instances: &'a [Instance]) -> Option<&'a str> {
+        if let Some(mod_info) = &self.mod_info {
+            return Some(&mod_info.version);
+        }
+        let Some(Ok(Some(mod_db_mod))) = &self.mod_db_mod else {
+            return None;
+        };
+        let instance = instances.get(self.target?)?;
+        instance
+            .mods
+            .iter()
+            .find(|mod_info| {
+                mod_db_mod.releases.iter().any(|release| {
+                    !release.mod_id.is_empty()
+                        && mod_info.mod_id.eq_ignore_ascii_case(&release.mod_id)
+                })
+            })
+            .map(|mod_info| mod_info.version.as_str())
+    }
+
+    /// `palette` colors the links in descriptions and changelogs.
+    pub fn view<'a>(&'a self, instances: &'a [Instance], palette: Palette) -> Element<'a, Message> {
+        let name = match (&self.mod_info, &self.mod_db_mod) {
+            (_, Some(Ok(Some(mod_db_mod)))) => mod_db_mod.name.as_str(),
+            (Some(mod_info), _) => mod_info.name.as_str(),
+            _ => "",
+        };
+
+        let mut content = Column::new().spacing(10).padding(10);
+
+        if let Some(mod_info) = &self.mod_info {
+            let mut dependencies: Vec<String> = mod_info
+                .dependencies
+                .iter()
+                .map(|(mod_id, version)| format!("{}@{}", mod_id, version))
+                .collect();
+            dependencies.sort();
+
+            content = content.push(
+                column![
+                    text("Installed").size(16),
+                    field("Mod id", &mod_info.mod_id),
+                    field("Version", &mod_info.version),
+                    field("File", &mod_info.zip_name.to_string_lossy()),
+                    field("Authors", &mod_info.authors.join(", ")),
+                    field("Website", &mod_info.website),
+                    field("Side", &mod_info.side.to_string()),
+                    field("Requires", &dependencies.join(", ")),
+                    text(norm_str(&mod_info.description)).size(12),
+                ]
+                .spacing(3),
+            );
+        }
+
+        content = content.push(
+            row![text("ModDB").size(16)]
+                .push_maybe(self.stale.then(|| {
+                    text("offline, showing cached page")
+                        .size(12)
+                        .style(text::secondary)
+                }))
+                .spacing(10)
+                .align_y(Vertical::Center),
+        );
+        match &self.mod_db_mod {
+            None => content = content.push(text("loading…").size(12)),
+            Some(Err(e)) => content = content.push(text(e).size(12).style(text::danger)),
+            Some(Ok(None)) => content = content.push(text("not found on ModDB").size(12)),
+            Some(Ok(Some(mod_db_mod))) => {
+                content = content
+                    .push(
+                        column![
+                            field("Author", &mod_db_mod.author),
+                            field("Downloads", &mod_db_mod.downloads.to_string()),
+                            field("Side", &mod_db_mod.side),
+                            field("Tags", &mod_db_mod.tags.join(", ")),
+                        ]
+                        .spacing(3),
+                    )
+                    .push(markdown_view(&self.description, palette));
+
+                if !self.screenshots.is_empty() {
+                    content = content.push(
+                        scrollable(
+                            row(self.screenshots.iter().map(|screenshot| match screenshot {
+                                Some(handle) => image(handle.clone()).height(120).into(),
+                                None => container(text("…")).width(160).height(120).into(),
+                            }))
+                            .spacing(10),
+                        )
+                        .direction(scrollable::Direction::Horizontal(
+                            scrollable::Scrollbar::default(),
+                        )),
+                    );
+                }
+
+                content = content.push(text("Releases").size(16)).push(
+                    column(mod_db_mod.releases.iter().zip(&self.changelogs).map(
+                        |(release, changelog)| {
+                            self.release_row(
+                                release,
+                                markdown_view(changelog, palette),
+                                self.installed_version(instances),
+                                self.loaded_by_target(instances, mod_db_mod),
+                            )
+                        },
+                    ))
+                    .spacing(10),
+                );
+            }
+        }
+
+        column![
+            row![
+                button("<")
+                    .style(button::secondary)
+                    .on_press(Message::CloseModDetail),
+                text(name).size(20),
+            ]
+            .spacing(10)
+            .padding(10)
+            .align_y(Vertical::Center),
+            horizontal_rule(1),
+            scrollable(content).height(Length::Fill),
+        ]
+        .into()
+    }
+
+    /// Server instances do not take client-only mods.
+    fn loaded_by_target(&self, instances: &[Instance], mod_db_mod: &ModDbMod) -> bool {
+        self.target
+            .and_then(|index| instances.get(index))
+            .is_none_or(|instance| instance.loads_side(ModSide::from(mod_db_mod.side.as_str())))
+    }
+
+    fn release_row<'a>(
+        &'a self,
+        release: &'a Release,
+        changelog: Element<'a, Message>,
+        installed_version: Option<&str>,
+        loaded: bool,
+    ) -> Element<'a, Message> {
+        let is_installed = installed_version == Some(release.version.as_str());
+        let installing = self.installing == Some(release.id);
+
+        column![
+            row![
+                text(&release.version),
+                text(format!("{} • {}", release.created, release.tags.join(", "))).size(12),
+                horizontal_space(),
+                button(
+                    text(if installing {
+                        "Installing…"
+                    } else if is_installed {
+                        "Installed"
+                    } else if !loaded {
+                        "Client only"
+                    } else {
+                        "Install"
+                    })
+                    .size(12)
+                )
+                .style(button::secondary)
+                .on_press_maybe(
+                    (self.target.is_some() && self.installing.is_none() && !is_installed && loaded)
+                        .then_some(Message::ModDetail(ModDetailMessage::Install(release.id)))
+                ),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            changelog,
+        ]
+        .spacing(3)
+        .into()
+    }
+}
+
+fn field<'a>(label: &'a str, value: &str) -> Element<'a, Message> {
+    row![
+        text(format!("{}:", label)).size(12).width(80),
+        text(value.to_string()).size(12),
+    ]
+    .spacing(10)
+    .into()
+}
@@ -0,0 +1,254 @@
+use crate::api::{ApiError, ModDb};
+use crate::instance::Instance;
+use crate::mods::{ModInfo, mod_files, replace_mod_file};
+use crate::snapshots::before_mods_change;
+use log::{debug, info};
+use std::path::Path;
+use std::{fmt, io};
+
+/// Mods that come with the game, servers list them with the others.
+const GAME_MODS: [&str; 3] = ["game", "survival", "creative"];
+
+/// `modid@version` entry of a mod list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModRef {
+    pub mod_id: String,
+    pub version: String,
+}
+
+impl fmt::Display for ModRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.mod_id, self.version)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingMod {
+    pub mod_ref: ModRef,
+    pub reason: String,
+}
+
+impl fmt::Display for MissingMod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.mod_ref, self.reason)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    pub installed: Vec<ModRef>,
+    pub kept: usize,
+    pub removed: Vec<String>,
+    pub missing: Vec<MissingMod>,
+}
+
+/// Finds all `modid@version` entries in the text, separated by whitespace, commas or
+/// semicolons. Anything else is ignored, so pasted log lines work as they are.
+pub fn parse_mod_list(text: &str) -> Vec<ModRef> {
+    let mut mods: Vec<ModRef> = Vec::new();
+
+    let tokens = text.split(|c: char| c.is_whitespace() || matches!(c, ',' | ';'));
+    for token in tokens {
+        let token = token.trim_matches(|c: char| matches!(c, '"' | '\'' | '(' | ')' | '[' | ']'));
+        let Some((mod_id, version)) = token.split_once('@') else {
+            continue;
+        };
+        let version = version.trim_start_matches('v');
+        let is_id = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_alphanumeric() || c == '_');
+        if !is_id(mod_id) || !version.starts_with(|c: char| c.is_ascii_digit()) {
+            continue;
+        }
+        if GAME_MODS.contains(&mod_id.to_lowercase().as_str())
+            || mods.iter().any(|m| m.mod_id.eq_ignore_ascii_case(mod_id))
+        {
+            continue;
+        }
+
+        mods.push(ModRef {
+            mod_id: mod_id.to_string(),
+            version: version.to_string(),
+        });
+    }
+
+    mods
+}
+
+/// Makes the `Mods` folder contain exactly the listed mods, downloading missing or different
+/// versions from ModDB. Everything else is moved to the trash, except the installed version
+/// of listed mods ModDB cannot provide right now, they are reported. See
+/// [`before_mods_change`] for what happens first.
+pub async fn apply_mod_list(
+    moddb: &ModDb,
+    instance: &Instance,
+    mod_list: &[ModRef],
+) -> Result<ImportReport, ApiError> {
+    before_mods_change(instance, "import mod list").map_err(ApiError::Io)?;
+    let report = apply_to_folder(
+        moddb,
+        &instance.mods_path(),
+        &instance.mods,
+        mod_list,
+        |path| trash::delete(path).map_err(io::Error::other),
+    )
+    .await?;
+
+    info!(
+        "imported mod list into {}: {} installed, {} kept, {} removed, {} missing",
+        instance.folder_name_string(),
+        report.installed.len(),
+        report.kept,
+        report.removed.len(),
+        report.missing.len()
+    );
+    Ok(report)
+}
+
+/// `remove` takes unlisted files out of the folder.
+async fn apply_to_folder(
+    moddb: &ModDb,
+    mods_path: &Path,
+    installed: &[ModInfo],
+    mod_list: &[ModRef],
+    remove: impl Fn(&Path) -> io::Result<()>,
+) -> Result<ImportReport, ApiError> {
+    let mut report = ImportReport::default();
+    let mut keep = Vec::new();
+
+    for mod_ref in mod_list {
+        let same_mod = |mod_info: &&ModInfo| mod_info.mod_id.eq_ignore_ascii_case(&mod_ref.mod_id);
+        if let Some(mod_info) = installed
+            .iter()
+            .filter(same_mod)
+            .find(|mod_info| mod_info.version == mod_ref.version)
+        {
+            keep.push(mod_info.zip_name.to_string_lossy().to_string());
+            report.kept += 1;
+            continue;
+        }
+
+        let mut missing = |reason: &str| {
+            // a failed lookup or download must not remove the version that works
+            keep.extend(
+                installed
+                    .iter()
+                    .filter(same_mod)
+                    .map(|mod_info| mod_info.zip_name.to_string_lossy().to_string()),
+            );
+            report.missing.push(MissingMod {
+                mod_ref: mod_ref.clone(),
+                reason: reason.to_string(),
+            });
+        };
+        let release = match moddb.get_mod(&mod_ref.mod_id).await {
+            Ok(Some(mod_db_mod)) => mod_db_mod
+                .releases
+                .into_iter()
+                .find(|release| release.version == mod_ref.version),
+            Ok(None) => {
+                missing("not on ModDB");
+                continue;
+            }
+            Err(e) => {
+                missing(&e.to_string());
+                continue;
+            }
+        };
+        let Some(release) = release else {
+            missing("version not on ModDB");
+            continue;
+        };
+
+        match moddb.download(&release).await {
+            Ok(data) => {
+                replace_mod_file(mods_path, None, &release.filename, &data)
+                    .map_err(ApiError::Io)?;
+                debug!("installed {}", mod_ref);
+                keep.push(release.filename);
+                report.installed.push(mod_ref.clone());
+            }
+            Err(e) => missing(&e.to_string()),
+        }
+    }
+
+    for (file, path) in mod_files(mods_path).map_err(ApiError::Io)? {
+        if !keep.contains(&file) {
+            debug!("removing {}", file);
+            remove(&path).map_err(ApiError::Io)?;
+            report.removed.push(file);
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::launcher_settings::RequestSettings;
+    use crate::test_util::mod_info;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn keeps_mods_moddb_cannot_provide() {
+        let temp_dir = tempdir().unwrap();
+        let mods_path = temp_dir.path();
+        let installed = [
+            mod_info("carryon", "1.9.0"),
+            mod_info("expandedfoods", "1.7.3"),
+            mod_info("extrachests", "1.1.0"),
+        ];
+        for mod_info in &installed {
+            fs::write(mods_path.join(&mod_info.zip_name), "zip").unwrap();
+        }
+        // nothing listens there, every lookup fails
+        let moddb = ModDb::new("http://127.0.0.1:1").with_limits(&RequestSettings {
+            max_retries: 0,
+            ..RequestSettings::default()
+        });
+        let mod_list = parse_mod_list("carryon@1.9.0 expandedfoods@1.7.4");
+
+        let report = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(apply_to_folder(
+                &moddb,
+                mods_path,
+                &installed,
+                &mod_list,
+                |path| fs::remove_file(path),
+            ))
+            .unwrap();
+
+        assert_eq!(report.kept, 1);
+        assert!(report.installed.is_empty());
+        assert_eq!(report.removed, ["extrachests_1.1.0.zip"]);
+        assert_eq!(report.missing.len(), 1);
+        assert_eq!(report.missing[0].mod_ref.mod_id, "expandedfoods");
+        let files: Vec<_> = mod_files(mods_path)
+            .unwrap()
+            .into_iter()
+            .map(|(file, _)| file)
+            .collect();
+        assert_eq!(files, ["carryon_1.9.0.zip", "expandedfoods_1.7.3.zip"]);
+    }
+
+    #[test]
+    fn parse_log_line() {
+        let mods = parse_mod_list(
+            "12.3.2025 18:00:01 [Notification] Mods: game@1.20.4, survival@1.20.4, \
+             carryon@1.9.0, primitivesurvival@v3.7.5;\n\"expandedfoods@1.7.4\" carryon@1.9.0 \
+             mail@example.com",
+        );
+
+        assert_eq!(
+            mods.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            [
+                "carryon@1.9.0",
+                "primitivesurvival@3.7.5",
+                "expandedfoods@1.7.4"
+            ]
+        );
+    }
+}
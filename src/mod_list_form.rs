@@ -0,0 +1,174 @@
+use crate::Message;
+use crate::style;
+use crate::ui::{form_row, form_text_input};
+use iced::alignment::Horizontal;
+use iced::widget::{
+    Container, button, column, container, horizontal_rule, horizontal_space, pick_list, row, text,
+    text_editor, text_input,
+};
+use iced::{Element, Length};
+use rustic_vs::instance::Instance;
+use rustic_vs::mod_list::{ModRef, parse_mod_list};
+use std::{fmt, fs};
+
+#[derive(Debug, Clone)]
+pub enum ModListMessage {
+    Edit(text_editor::Action),
+    FilePath(String),
+    LoadFile,
+    Target(ImportTarget),
+    Name(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportTarget {
+    New,
+    Existing(usize, String),
+}
+
+impl fmt::Display for ImportTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportTarget::New => write!(f, "New instance"),
+            ImportTarget::Existing(_, name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// Pasted or loaded `modid@version` list and the instance it is imported into.
+pub struct ModListForm {
+    list: text_editor::Content,
+    file_path: String,
+    pub target: ImportTarget,
+    pub name: String,
+    pub mods: Vec<ModRef>,
+    error: Option<String>,
+    pub importing: bool,
+}
+
+impl ModListForm {
+    pub fn new(instances: &[Instance], selected: Option<usize>) -> ModListForm {
+        Self {
+            list: text_editor::Content::new(),
+            file_path: String::new(),
+            target: match selected {
+                Some(index) => ImportTarget::Existing(index, instances[index].name.clone()),
+                None => ImportTarget::New,
+            },
+            name: String::new(),
+            mods: Vec::new(),
+            error: None,
+            importing: false,
+        }
+    }
+
+    pub fn update(&mut self, message: ModListMessage) {
+        match message {
+            ModListMessage::Edit(action) => {
+                self.list.perform(action);
+                self.mods = parse_mod_list(&self.list.text());
+            }
+            ModListMessage::FilePath(path) => self.file_path = path,
+            ModListMessage::LoadFile => match fs::read_to_string(self.file_path.trim()) {
+                Ok(list) => {
+                    self.mods = parse_mod_list(&list);
+                    self.list = text_editor::Content::with_text(&list);
+                    self.error = None;
+                }
+                Err(e) => self.error = Some(e.to_string()),
+            },
+            ModListMessage::Target(target) => self.target = target,
+            ModListMessage::Name(name) => self.name = name,
+        }
+    }
+
+    pub fn can_submit(&self) -> bool {
+        !self.importing
+            && !self.mods.is_empty()
+            && (self.target != ImportTarget::New || !self.name.trim().is_empty())
+    }
+
+    pub fn view<'a>(&'a self, instances: &[Instance]) -> Container<'a, Message> {
+        let targets: Vec<ImportTarget> = std::iter::once(ImportTarget::New)
+            .chain(
+                instances
+                    .iter()
+                    .enumerate()
+                    .map(|(index, instance)| ImportTarget::Existing(index, instance.name.clone())),
+            )
+            .collect();
+
+        let status: Element<Message> = match &self.error {
+            Some(e) => text(e).size(12).style(text::danger).into(),
+            None => text(format!("{} mods found", self.mods.len()))
+                .size(12)
+                .into(),
+        };
+
+        container(column![
+            row![text("Import mod list").size(20)].padding(10),
+            horizontal_rule(1),
+            column![
+                text("modid@version entries, e.g. pasted from the server log:").size(12),
+                text_editor(&self.list)
+                    .on_action(|action| Message::ModList(ModListMessage::Edit(action)))
+                    .height(120),
+                form_row(
+                    "File:",
+                    row![
+                        text_input("<path to list>", &self.file_path)
+                            .on_input(|path| Message::ModList(ModListMessage::FilePath(path)))
+                            .on_submit(Message::ModList(ModListMessage::LoadFile)),
+                        button("Load")
+                            .style(button::secondary)
+                            .on_press(Message::ModList(ModListMessage::LoadFile)),
+                    ]
+                    .spacing(5)
+                    .into()
+                ),
+                form_row(
+                    "Into:",
+                    pick_list(targets, Some(self.target.clone()), |target| {
+                        Message::ModList(ModListMessage::Target(target))
+                    })
+                    .width(Length::Fill)
+                    .into()
+                ),
+            ]
+            .push_maybe((self.target == ImportTarget::New).then(|| {
+                form_text_input(
+                    "Name:",
+                    "<enter name>",
+                    &self.name,
+                    |name| Message::ModList(ModListMessage::Name(name)),
+                    Message::ImportModListSubmit,
+                )
+            }))
+            .push(status)
+            .push(
+                row![
+                    horizontal_space(),
+                    button(
+                        text(if self.importing {
+                            "Importing…"
+                        } else {
+                            "Import"
+                        })
+                        .align_x(Horizontal::Center)
+                    )
+                    .width(90)
+                    .on_press_maybe(self.can_submit().then_some(Message::ImportModListSubmit)),
+                    button(text("Cancel").align_x(Horizontal::Center))
+                        .width(90)
+                        .style(button::secondary)
+                        .on_press(Message::HideModal),
+                ]
+                .spacing(10),
+            )
+            .padding(10)
+            .spacing(10),
+        ])
+        .width(400)
+        .style(style::rounded_container)
+    }
+}
@@ -1,16 +1,22 @@
-use log::debug;
-use serde::Deserialize;
-use std::ffi::OsString;
+use crate::game::GameVersion;
+use log::{debug, error};
+use serde::{Deserialize, Deserializer};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::error::Error;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
 use std::fs;
 use std::fs::File;
+use std::io;
 use std::io::Read;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::result::Result;
 use zip::ZipArchive;
+use zip::result::ZipError;
 
 const MODINFO_JSON: &str = "modinfo.json";
 
-#[allow(dead_code)]
 #[derive(Debug, Deserialize, Clone, Default)]
 pub struct ModInfo {
     #[serde(skip)]
@@ -27,28 +33,166 @@ pub struct ModInfo {
 
     #[serde(alias = "Version")]
     pub version: String,
+
+    #[serde(alias = "Authors", default, deserialize_with = "one_or_many")]
+    pub authors: Vec<String>,
+
+    #[serde(alias = "Website", default)]
+    pub website: String,
+
+    #[serde(alias = "Dependencies", default)]
+    pub dependencies: HashMap<String, String>,
+
+    #[serde(alias = "Side", default, deserialize_with = "lenient_side")]
+    pub side: ModSide,
+
+    /// Universal mods the client may join without, e.g. server side tweaks.
+    #[serde(
+        rename = "requiredOnClient",
+        alias = "RequiredOnClient",
+        default = "yes"
+    )]
+    pub required_on_client: bool,
+
+    /// Config file in the instance ModConfig folder, see [`crate::mod_config::find_config`].
+    #[serde(skip)]
+    pub config_path: Option<PathBuf>,
+}
+
+fn yes() -> bool {
+    true
+}
+
+/// Some mods list a single author as plain string.
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(author) => vec![author],
+        OneOrMany::Many(authors) => authors,
+    })
+}
+
+/// Where a mod runs, dedicated servers skip client-only mods.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ModSide {
+    #[default]
+    Universal,
+    Client,
+    Server,
+}
+
+impl From<&str> for ModSide {
+    /// Accepts modinfo.json values (`Universal`, `Client`, `Server`) and ModDB values (`both`,
+    /// `client`, `server`), anything unknown counts as universal.
+    fn from(side: &str) -> Self {
+        match side.trim().to_lowercase().as_str() {
+            "client" => ModSide::Client,
+            "server" => ModSide::Server,
+            _ => ModSide::Universal,
+        }
+    }
+}
+
+impl fmt::Display for ModSide {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModSide::Universal => write!(f, "Universal"),
+            ModSide::Client => write!(f, "Client"),
+            ModSide::Server => write!(f, "Server"),
+        }
+    }
+}
+
+/// The game writes `Universal`, but mods are not consistent about the case.
+fn lenient_side<'de, D: Deserializer<'de>>(deserializer: D) -> Result<ModSide, D::Error> {
+    Ok(ModSide::from(String::deserialize(deserializer)?.as_str()))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Compatibility {
+    Compatible,
+    RequiresNewer(GameVersion),
+    OlderMajor(GameVersion),
+}
+
+impl Compatibility {
+    pub fn is_compatible(&self) -> bool {
+        matches!(self, Compatibility::Compatible)
+    }
+}
+
+impl ModInfo {
+    /// Minimum game version from `dependencies.game`, empty or `*` means any version.
+    pub fn game_dependency(&self) -> Option<GameVersion> {
+        self.dependencies
+            .iter()
+            .find(|(mod_id, _)| mod_id.eq_ignore_ascii_case("game"))
+            .and_then(|(_, version)| version.parse().ok())
+    }
+
+    /// Vintage Story bumps the second number for major releases (1.19 -> 1.20), mods built for
+    /// an older one are very likely broken.
+    pub fn compatibility(&self, game_version: &GameVersion) -> Compatibility {
+        match self.game_dependency() {
+            Some(required) if required > *game_version => Compatibility::RequiresNewer(required),
+            Some(required)
+                if (required.major, required.minor) < (game_version.major, game_version.minor) =>
+            {
+                Compatibility::OlderMajor(required)
+            }
+            _ => Compatibility::Compatible,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum ModError {
     ZipMissing,
-    ZipParseError,
-    InfoParseError(#[expect(unused)] json5::Error),
+    Io(io::Error),
+    ZipParseError(ZipError),
+    InfoParseError(json5::Error),
+}
+
+impl fmt::Display for ModError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModError::ZipMissing => write!(f, "not a mod file"),
+            ModError::Io(e) => write!(f, "failed reading mod: {}", e),
+            ModError::ZipParseError(e) => write!(f, "invalid zip: {}", e),
+            ModError::InfoParseError(e) => write!(f, "invalid {}: {}", MODINFO_JSON, e),
+        }
+    }
+}
+
+impl Error for ModError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ModError::ZipMissing => None,
+            ModError::Io(e) => Some(e),
+            ModError::ZipParseError(e) => Some(e),
+            ModError::InfoParseError(e) => Some(e),
+        }
+    }
 }
+
 impl ModInfo {
     fn from_zip(zip_path: &PathBuf) -> Result<ModInfo, ModError> {
         let Some(filename) = zip_path.file_name() else {
             return Err(ModError::ZipMissing);
         };
-        let file = File::open(zip_path).map_err(|_| ModError::ZipParseError)?;
-        let mut zip = ZipArchive::new(file).unwrap();
+        let file = File::open(zip_path).map_err(ModError::Io)?;
+        let mut zip = ZipArchive::new(file).map_err(ModError::ZipParseError)?;
 
         match zip.by_name(MODINFO_JSON) {
             Ok(mut json_file) => {
                 let mut json = String::new();
-                json_file
-                    .read_to_string(&mut json)
-                    .map_err(|_| ModError::ZipParseError)?;
+                json_file.read_to_string(&mut json).map_err(ModError::Io)?;
                 let mut mod_info: ModInfo =
                     json5::from_str(&json).map_err(ModError::InfoParseError)?;
 
@@ -64,17 +208,16 @@ impl ModInfo {
     }
 }
 
-pub fn load_mods(folder_path: &PathBuf) -> Vec<ModInfo> {
+/// Parses all mods in the folder, a missing folder has no mods. Mods that fail to parse are
+/// skipped, only failing to read the folder itself is an error.
+pub fn load_mods(folder_path: &PathBuf) -> io::Result<Vec<ModInfo>> {
     if !folder_path.exists() {
-        return Vec::new();
+        return Ok(Vec::new());
     }
 
     let mut mods = Vec::new();
 
-    for entry in fs::read_dir(folder_path)
-        .expect("failed to read mods folder")
-        .flatten()
-    {
+    for entry in fs::read_dir(folder_path)?.flatten() {
         let zip_name = entry.file_name();
         match ModInfo::from_zip(&entry.path()) {
             Ok(mod_info) => {
@@ -82,10 +225,171 @@ pub fn load_mods(folder_path: &PathBuf) -> Vec<ModInfo> {
                 mods.push(mod_info);
             }
             Err(e) => {
-                debug!("failed parsing {}: {:?}", zip_name.display(), e)
+                error!("failed parsing {}: {}", zip_name.display(), e)
             }
         }
     }
 
-    mods
+    Ok(mods)
+}
+
+/// Top level files of the mods folder with their file names, sub folders are skipped.
+pub fn mod_files(folder_path: &Path) -> io::Result<Vec<(String, PathBuf)>> {
+    if !folder_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut files: Vec<_> = fs::read_dir(folder_path)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter_map(|path| Some((path.file_name()?.to_str()?.to_string(), path)))
+        .collect();
+    files.sort();
+
+    Ok(files)
+}
+
+/// Mod versions are mostly semver, anything else is never newer as the order is unknown.
+pub fn is_newer(candidate: &str, installed: &str) -> bool {
+    match (
+        candidate.parse::<GameVersion>(),
+        installed.parse::<GameVersion>(),
+    ) {
+        (Ok(candidate), Ok(installed)) => candidate > installed,
+        _ => false,
+    }
+}
+
+/// File names come from ModDB or lockfiles, they must not point outside the mods folder.
+fn check_file_name(zip_name: &str) -> io::Result<()> {
+    // `..` and `.` have no file name
+    let is_plain = Path::new(zip_name).file_name() == Some(OsStr::new(zip_name))
+        && !zip_name.contains(['/', '\\']);
+    if is_plain {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid mod file name {:?}", zip_name),
+        ))
+    }
+}
+
+/// Writes a new mod zip into the mods folder and removes the zip it replaces. The file is
+/// written next to its target and renamed, which never writes through a link into the store.
+pub fn replace_mod_file(
+    folder_path: &Path,
+    old_zip_name: Option<&OsStr>,
+    zip_name: &str,
+    data: &[u8],
+) -> io::Result<()> {
+    check_file_name(zip_name)?;
+    fs::create_dir_all(folder_path)?;
+    let part_path = folder_path.join(format!("{}.part", zip_name));
+    fs::write(&part_path, data)?;
+    fs::rename(part_path, folder_path.join(zip_name))?;
+
+    if let Some(old_zip_name) = old_zip_name
+        && old_zip_name != zip_name
+    {
+        fs::remove_file(folder_path.join(old_zip_name))?;
+    }
+
+    Ok(())
+}
+
+/// Hex encoded SHA-256 of a file, used to identify mod zips independent of their name.
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+
+    Ok(to_hex(&hasher.finalize()))
+}
+
+pub fn sha256_data(data: &[u8]) -> String {
+    to_hex(&Sha256::digest(data))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compatibility_with_game() {
+        let game_version: GameVersion = "1.20.4".parse().unwrap();
+        let parse = |s: &str| s.parse::<GameVersion>().unwrap();
+        for (dependencies, dependency, compatibility) in [
+            (
+                vec![("game", "1.20.0")],
+                Some(parse("1.20.0")),
+                Compatibility::Compatible,
+            ),
+            (
+                vec![("Game", "1.20.4")],
+                Some(parse("1.20.4")),
+                Compatibility::Compatible,
+            ),
+            (
+                vec![("game", "1.21.0-rc.1")],
+                Some(parse("1.21.0-rc.1")),
+                Compatibility::RequiresNewer(parse("1.21.0-rc.1")),
+            ),
+            (
+                vec![("game", "1.19.8")],
+                Some(parse("1.19.8")),
+                Compatibility::OlderMajor(parse("1.19.8")),
+            ),
+            (vec![("game", "*")], None, Compatibility::Compatible),
+            (vec![("game", "")], None, Compatibility::Compatible),
+            (
+                vec![("survival", "1.19.8")],
+                None,
+                Compatibility::Compatible,
+            ),
+            (vec![], None, Compatibility::Compatible),
+        ] {
+            let mod_info = ModInfo {
+                dependencies: dependencies
+                    .iter()
+                    .map(|(mod_id, version)| (mod_id.to_string(), version.to_string()))
+                    .collect(),
+                ..ModInfo::default()
+            };
+            assert_eq!(mod_info.game_dependency(), dependency, "{:?}", dependencies);
+            assert_eq!(
+                mod_info.compatibility(&game_version),
+                compatibility,
+                "{:?}",
+                dependencies
+            );
+        }
+    }
+
+    #[test]
+    fn unparsable_versions_are_not_newer() {
+        assert!(is_newer("1.10.0", "1.9.2"));
+        assert!(!is_newer("1.9.2", "1.10.0"));
+        assert!(!is_newer("1.0.0-beta", "nightly"));
+        assert!(!is_newer("nightly", "1.0.0"));
+    }
+
+    #[test]
+    fn rejects_file_names_outside_mods() {
+        for zip_name in [
+            "../evil.zip",
+            "/tmp/evil.zip",
+            "a/b.zip",
+            "a\\b.zip",
+            "..",
+            ".",
+        ] {
+            assert!(check_file_name(zip_name).is_err(), "{}", zip_name);
+        }
+        assert!(check_file_name("CarryOn_v1.9.0.zip").is_ok());
+    }
 }
@@ -0,0 +1,313 @@
+use crate::settings::{SettingsError, get_key, read_json, set_key, write_json};
+use serde_json::{Value, json};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use std::{fmt, fs, io};
+
+pub const SERVER_CONFIG: &str = "serverconfig.json";
+/// Relative to the data folder.
+pub const WHITELIST: &str = "Playerdata/playerswhitelisted.json";
+
+const PORT: &str = "Port";
+const MAX_CLIENTS: &str = "MaxClients";
+const WORLD: &str = "WorldConfig.SaveFileLocation";
+const WHITELIST_MODE: &str = "WhitelistMode";
+/// Used before 1.19 instead of `WhitelistMode`.
+const ONLY_WHITELISTED: &str = "OnlyWhitelisted";
+pub const MOD_PATHS: &str = "ModPaths";
+
+/// Players are added with `/whitelist add` on the server or in Rustic, see [`Whitelist`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WhitelistMode {
+    /// On for dedicated servers, off for singleplayer worlds opened to LAN.
+    Default,
+    On,
+    Off,
+}
+
+impl WhitelistMode {
+    pub const ALL: [WhitelistMode; 3] = [
+        WhitelistMode::Default,
+        WhitelistMode::On,
+        WhitelistMode::Off,
+    ];
+
+    fn from_i64(value: i64) -> Option<WhitelistMode> {
+        match value {
+            0 => Some(WhitelistMode::Default),
+            1 => Some(WhitelistMode::On),
+            2 => Some(WhitelistMode::Off),
+            _ => None,
+        }
+    }
+
+    fn to_i64(self) -> i64 {
+        match self {
+            WhitelistMode::Default => 0,
+            WhitelistMode::On => 1,
+            WhitelistMode::Off => 2,
+        }
+    }
+}
+
+impl fmt::Display for WhitelistMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WhitelistMode::Default => write!(f, "Default"),
+            WhitelistMode::On => write!(f, "On"),
+            WhitelistMode::Off => write!(f, "Off"),
+        }
+    }
+}
+
+/// Typed view of the settings edited in the GUI, `None` for keys missing in the file.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ServerSettings {
+    pub port: Option<i64>,
+    pub max_clients: Option<i64>,
+    /// Path of the world save, created by the server if missing.
+    pub world: Option<String>,
+    pub whitelist_mode: Option<WhitelistMode>,
+}
+
+/// serverconfig.json, written by the server on first start. All other keys pass through.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerConfig {
+    pub raw: Value,
+}
+
+impl ServerConfig {
+    pub fn load(path: &Path) -> Result<ServerConfig, SettingsError> {
+        Ok(Self {
+            raw: read_json(path)?,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), SettingsError> {
+        write_json(path, &self.raw)
+    }
+
+    pub fn settings(&self) -> ServerSettings {
+        let int = |key| get_key(&self.raw, key).and_then(Value::as_i64);
+        let whitelist_mode = match int(WHITELIST_MODE) {
+            Some(mode) => WhitelistMode::from_i64(mode),
+            None => get_key(&self.raw, ONLY_WHITELISTED)
+                .and_then(Value::as_bool)
+                .map(|only| {
+                    if only {
+                        WhitelistMode::On
+                    } else {
+                        WhitelistMode::Off
+                    }
+                }),
+        };
+
+        ServerSettings {
+            port: int(PORT),
+            max_clients: int(MAX_CLIENTS),
+            world: get_key(&self.raw, WORLD)
+                .and_then(Value::as_str)
+                .map(String::from),
+            whitelist_mode,
+        }
+    }
+
+    /// Writes the typed settings back, `None` leaves the key as it is.
+    pub fn apply(&mut self, settings: &ServerSettings) {
+        for (key, value) in [(PORT, settings.port), (MAX_CLIENTS, settings.max_clients)] {
+            if let Some(value) = value {
+                set_key(&mut self.raw, key, Value::from(value));
+            }
+        }
+        if let Some(world) = &settings.world {
+            set_key(&mut self.raw, WORLD, Value::from(world.as_str()));
+        }
+        if let Some(mode) = settings.whitelist_mode {
+            if get_key(&self.raw, WHITELIST_MODE).is_none()
+                && get_key(&self.raw, ONLY_WHITELISTED).is_some()
+            {
+                set_key(
+                    &mut self.raw,
+                    ONLY_WHITELISTED,
+                    Value::from(mode != WhitelistMode::Off),
+                );
+            } else {
+                set_key(&mut self.raw, WHITELIST_MODE, Value::from(mode.to_i64()));
+            }
+        }
+    }
+}
+
+/// How long players added in Rustic stay whitelisted, the server does the same.
+const WHITELIST_YEARS: u64 = 50;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WhitelistedPlayer {
+    pub name: String,
+    pub uid: String,
+}
+
+/// `Playerdata/playerswhitelisted.json`, the server matches players by uid and only reads it
+/// on start. Keys Rustic does not edit pass through.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Whitelist {
+    pub entries: Vec<Value>,
+}
+
+impl Whitelist {
+    /// A missing file is an empty whitelist.
+    pub fn load(path: &Path) -> Result<Whitelist, SettingsError> {
+        if !path.is_file() {
+            return Ok(Whitelist::default());
+        }
+
+        Ok(Self {
+            entries: match read_json(path)? {
+                Value::Array(entries) => entries,
+                _ => {
+                    let e = io::Error::new(io::ErrorKind::InvalidData, "whitelist is no list");
+                    return Err(e.into());
+                }
+            },
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), SettingsError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_json(path, &Value::from(self.entries.clone()))
+    }
+
+    pub fn players(&self) -> Vec<WhitelistedPlayer> {
+        let string = |entry: &Value, key| {
+            entry
+                .get(key)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string()
+        };
+
+        self.entries
+            .iter()
+            .map(|entry| WhitelistedPlayer {
+                name: string(entry, "PlayerName"),
+                uid: string(entry, "PlayerUID"),
+            })
+            .collect()
+    }
+
+    /// Adds the player unless the uid is listed already, returns whether it was added.
+    pub fn add(&mut self, name: &str, uid: &str) -> bool {
+        if self.players().iter().any(|player| player.uid == uid) {
+            return false;
+        }
+
+        let until = SystemTime::now() + Duration::from_secs(WHITELIST_YEARS * 365 * 24 * 60 * 60);
+        self.entries.push(json!({
+            "PlayerName": name,
+            "PlayerUID": uid,
+            "IssuedByPlayerName": "Rustic",
+            "IssuedByPlayerUID": null,
+            "UntilDate": humantime::format_rfc3339_seconds(until).to_string(),
+            "Reason": "",
+        }));
+        true
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.entries.len() {
+            self.entries.remove(index);
+        }
+    }
+}
+
+/// Same as [`crate::settings::set_mod_paths`] for the server, which keeps them in its own
+/// config.
+pub fn set_server_mod_paths(config: &mut Value, mods_path: &Path) {
+    set_key(
+        config,
+        MOD_PATHS,
+        Value::from(vec![
+            "Mods".to_string(),
+            mods_path.to_string_lossy().to_string(),
+        ]),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn settings_roundtrip() {
+        let mut config = ServerConfig {
+            raw: json!({
+                "ServerName": "Test",
+                "Port": 42420,
+                "OnlyWhitelisted": true,
+                "WorldConfig": { "SaveFileLocation": "/data/Saves/default.vcdbs", "Seed": null },
+            }),
+        };
+
+        let mut settings = config.settings();
+        assert_eq!(
+            settings,
+            ServerSettings {
+                port: Some(42420),
+                max_clients: None,
+                world: Some("/data/Saves/default.vcdbs".to_string()),
+                whitelist_mode: Some(WhitelistMode::On),
+            }
+        );
+
+        settings.max_clients = Some(8);
+        settings.world = Some("/data/Saves/other.vcdbs".to_string());
+        settings.whitelist_mode = Some(WhitelistMode::Off);
+        config.apply(&settings);
+        assert_eq!(
+            config.raw,
+            json!({
+                "ServerName": "Test",
+                "Port": 42420,
+                "MaxClients": 8,
+                "OnlyWhitelisted": false,
+                "WorldConfig": { "SaveFileLocation": "/data/Saves/other.vcdbs", "Seed": null },
+            })
+        );
+    }
+
+    #[test]
+    fn whitelist_keeps_entries() {
+        let mut whitelist = Whitelist {
+            entries: vec![json!({
+                "PlayerName": "Tyron",
+                "PlayerUID": "abc123",
+                "IssuedByPlayerName": "admin",
+                "Reason": "team",
+            })],
+        };
+
+        assert!(!whitelist.add("Tyron", "abc123"));
+        assert!(whitelist.add("Saraty", "def456"));
+        assert_eq!(
+            whitelist.players(),
+            [
+                WhitelistedPlayer {
+                    name: "Tyron".to_string(),
+                    uid: "abc123".to_string(),
+                },
+                WhitelistedPlayer {
+                    name: "Saraty".to_string(),
+                    uid: "def456".to_string(),
+                },
+            ]
+        );
+        assert!(whitelist.entries[1]["UntilDate"].is_string());
+
+        whitelist.remove(1);
+        assert_eq!(whitelist.entries[0]["Reason"], "team");
+        assert_eq!(whitelist.entries.len(), 1);
+    }
+}
@@ -0,0 +1,285 @@
+use crate::Message;
+use iced::alignment::Vertical;
+use iced::widget::{
+    Row, button, column, horizontal_space, pick_list, row, scrollable, text, text_input,
+};
+use iced::{Element, Length};
+use rustic_vs::instance::Instance;
+use rustic_vs::server_config::{ServerConfig, ServerSettings, Whitelist, WhitelistMode};
+use rustic_vs::settings::SettingsError;
+use rustic_vs::worlds::{WORLD_EXTENSION, load_worlds};
+use std::path::{MAIN_SEPARATOR, Path, PathBuf};
+
+#[derive(Debug, Clone)]
+pub enum ServerConfigMessage {
+    Port(String),
+    MaxClients(String),
+    World(String),
+    WhitelistMode(WhitelistMode),
+    PlayerName(String),
+    PlayerUid(String),
+    AddPlayer,
+    RemovePlayer(usize),
+    Save,
+}
+
+/// Form of the settings tab for server instances, numbers are kept as text until saved.
+pub struct ServerConfigEditor {
+    config: ServerConfig,
+    saves_path: PathBuf,
+    worlds: Vec<String>,
+    port: String,
+    max_clients: String,
+    /// World name in `Saves`, or a full path for worlds elsewhere.
+    world: String,
+    whitelist_mode: Option<WhitelistMode>,
+    whitelist: Result<Whitelist, String>,
+    whitelist_path: PathBuf,
+    player_name: String,
+    player_uid: String,
+    error: Option<String>,
+}
+
+fn setting_row<'a>(label: &'a str, widget: Element<'a, Message>) -> Row<'a, Message> {
+    row![text(label).width(110), widget]
+        .spacing(10)
+        .align_y(Vertical::Center)
+}
+
+fn parse_opt(label: &str, value: &str) -> Result<Option<i64>, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value
+        .parse()
+        .map(Some)
+        .map_err(|_| format!("{} '{}' is not a number", label, value))
+}
+
+impl ServerConfigEditor {
+    pub fn load(instance: &Instance) -> Result<ServerConfigEditor, SettingsError> {
+        let config = ServerConfig::load(&instance.server_config_path())?;
+        let settings = config.settings();
+        let saves_path = instance.saves_path();
+
+        let world = match settings.world.as_deref().map(Path::new) {
+            Some(path) if path.parent() == Some(saves_path.as_path()) => path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            Some(path) => path.display().to_string(),
+            None => String::new(),
+        };
+
+        Ok(Self {
+            config,
+            worlds: load_worlds(&instance.path())
+                .map(|worlds| worlds.into_iter().map(|world| world.name).collect())
+                .unwrap_or_default(),
+            saves_path,
+            port: settings.port.map(|v| v.to_string()).unwrap_or_default(),
+            max_clients: settings
+                .max_clients
+                .map(|v| v.to_string())
+                .unwrap_or_default(),
+            world,
+            whitelist_mode: settings.whitelist_mode,
+            whitelist: Whitelist::load(&instance.whitelist_path()).map_err(|e| e.to_string()),
+            whitelist_path: instance.whitelist_path(),
+            player_name: String::new(),
+            player_uid: String::new(),
+            error: None,
+        })
+    }
+
+    fn world_path(&self) -> Option<String> {
+        let world = self.world.trim();
+        if world.is_empty() {
+            None
+        } else if world.contains(MAIN_SEPARATOR) || world.contains('/') {
+            Some(world.to_string())
+        } else {
+            let file_name = format!("{}.{}", world, WORLD_EXTENSION);
+            Some(
+                self.saves_path
+                    .join(file_name)
+                    .to_string_lossy()
+                    .to_string(),
+            )
+        }
+    }
+
+    fn to_settings(&self) -> Result<ServerSettings, String> {
+        Ok(ServerSettings {
+            port: parse_opt("Port", &self.port)?,
+            max_clients: parse_opt("Max clients", &self.max_clients)?,
+            world: self.world_path(),
+            whitelist_mode: self.whitelist_mode,
+        })
+    }
+
+    fn add_player(&mut self) {
+        let Ok(whitelist) = &mut self.whitelist else {
+            return;
+        };
+        let uid = self.player_uid.trim();
+        if uid.is_empty() {
+            self.error = Some("enter the uid of the player".to_string());
+        } else if whitelist.add(self.player_name.trim(), uid) {
+            self.error = None;
+            self.player_name.clear();
+            self.player_uid.clear();
+        } else {
+            self.error = Some(format!("{} is whitelisted already", uid));
+        }
+    }
+
+    fn save(&mut self, path: &Path, settings: &ServerSettings) -> Result<(), SettingsError> {
+        let mut config = self.config.clone();
+        config.apply(settings);
+        config.save(path)?;
+        self.config = config;
+
+        match &self.whitelist {
+            Ok(whitelist) => whitelist.save(&self.whitelist_path),
+            Err(_) => Ok(()),
+        }
+    }
+
+    /// Returns `Some` with the save result when the config and whitelist were written.
+    pub fn update(
+        &mut self,
+        message: ServerConfigMessage,
+        path: &Path,
+    ) -> Option<Result<(), SettingsError>> {
+        match message {
+            ServerConfigMessage::Port(value) => self.port = value,
+            ServerConfigMessage::MaxClients(value) => self.max_clients = value,
+            ServerConfigMessage::World(value) => self.world = value,
+            ServerConfigMessage::WhitelistMode(mode) => self.whitelist_mode = Some(mode),
+            ServerConfigMessage::PlayerName(value) => self.player_name = value,
+            ServerConfigMessage::PlayerUid(value) => self.player_uid = value,
+            ServerConfigMessage::AddPlayer => self.add_player(),
+            ServerConfigMessage::RemovePlayer(index) => {
+                if let Ok(whitelist) = &mut self.whitelist {
+                    whitelist.remove(index);
+                }
+            }
+            ServerConfigMessage::Save => match self.to_settings() {
+                Ok(settings) => {
+                    self.error = None;
+                    return Some(self.save(path, &settings));
+                }
+                Err(e) => self.error = Some(e),
+            },
+        }
+        None
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let input = |value, on_input: fn(String) -> ServerConfigMessage| {
+            text_input("<not set>", value)
+                .on_input(move |value| Message::ServerConfig(on_input(value)))
+                .into()
+        };
+
+        let world = row![
+            text_input("<world name in Saves>", &self.world)
+                .on_input(|value| Message::ServerConfig(ServerConfigMessage::World(value))),
+            pick_list(self.worlds.as_slice(), None::<String>, |world| {
+                Message::ServerConfig(ServerConfigMessage::World(world))
+            })
+            .placeholder("Saves")
+            .width(120),
+        ]
+        .spacing(5);
+
+        column![
+            scrollable(
+                column![
+                    setting_row("Port:", input(&self.port, ServerConfigMessage::Port)),
+                    setting_row(
+                        "Max clients:",
+                        input(&self.max_clients, ServerConfigMessage::MaxClients)
+                    ),
+                    setting_row("World:", world.into()),
+                    setting_row(
+                        "Whitelist:",
+                        pick_list(WhitelistMode::ALL, self.whitelist_mode, |mode| {
+                            Message::ServerConfig(ServerConfigMessage::WhitelistMode(mode))
+                        })
+                        .placeholder("<not set>")
+                        .into()
+                    ),
+                    setting_row("Players:", self.whitelist_view()),
+                ]
+                .push_maybe(
+                    self.error
+                        .as_ref()
+                        .map(|e| text(e).size(12).style(text::danger))
+                )
+                .spacing(5)
+                .padding(10),
+            )
+            .height(Length::Fill),
+            row![
+                horizontal_space(),
+                button("Save").on_press(Message::ServerConfig(ServerConfigMessage::Save)),
+            ]
+            .padding(10),
+        ]
+        .into()
+    }
+    fn whitelist_view(&self) -> Element<'_, Message> {
+        let whitelist = match &self.whitelist {
+            Ok(whitelist) => whitelist,
+            Err(e) => return text(e).size(12).style(text::danger).into(),
+        };
+
+        let players = whitelist.players();
+        let rows = players.into_iter().enumerate().map(|(index, player)| {
+            row![
+                text(player.name).width(Length::Fill),
+                text(player.uid).size(12).width(Length::Fill),
+                button(text("Remove").size(12))
+                    .style(button::danger)
+                    .on_press(Message::ServerConfig(ServerConfigMessage::RemovePlayer(
+                        index
+                    ))),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center)
+            .into()
+        });
+
+        column(rows)
+            .push(
+                row![
+                    text_input("<player name>", &self.player_name).on_input(|value| {
+                        Message::ServerConfig(ServerConfigMessage::PlayerName(value))
+                    }),
+                    text_input("<player uid>", &self.player_uid)
+                        .on_input(|value| {
+                            Message::ServerConfig(ServerConfigMessage::PlayerUid(value))
+                        })
+                        .on_submit(Message::ServerConfig(ServerConfigMessage::AddPlayer)),
+                    button(text("Add").size(12))
+                        .style(button::secondary)
+                        .on_press(Message::ServerConfig(ServerConfigMessage::AddPlayer)),
+                ]
+                .spacing(10)
+                .align_y(Vertical::Center),
+            )
+            .push(
+                text(
+                    "The server reads the whitelist on start, uids are in its log when a \
+                     player joins. /whitelist add on the running server works as well.",
+                )
+                .size(12),
+            )
+            .spacing(5)
+            .into()
+    }
+}
@@ -0,0 +1,199 @@
+//! Brings a client instance in line with a server instance, so joining does not fail with a
+//! mod version mismatch. Mod files are copied from the server, ModDB is not needed.
+
+use crate::instance::Instance;
+use crate::mods::{ModInfo, ModSide, replace_mod_file};
+use crate::snapshots::before_mods_change;
+use log::{debug, info};
+use std::{fmt, fs, io};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncStatus {
+    Matches,
+    /// Required on the client, which does not have it.
+    Missing,
+    /// Different versions, the server version is installed on sync.
+    Mismatch,
+    /// Not required on the client, which does not have it.
+    Optional,
+    /// Universal mod only the client has, it is left alone.
+    Extra,
+}
+
+impl SyncStatus {
+    pub fn needs_sync(&self) -> bool {
+        matches!(self, SyncStatus::Missing | SyncStatus::Mismatch)
+    }
+}
+
+impl fmt::Display for SyncStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncStatus::Matches => write!(f, "matches"),
+            SyncStatus::Missing => write!(f, "missing"),
+            SyncStatus::Mismatch => write!(f, "mismatch"),
+            SyncStatus::Optional => write!(f, "optional"),
+            SyncStatus::Extra => write!(f, "client only"),
+        }
+    }
+}
+
+/// One mod of the server or client, side by side.
+#[derive(Debug, Clone)]
+pub struct ModComparison {
+    pub mod_id: String,
+    pub server: Option<ModInfo>,
+    pub client: Option<ModInfo>,
+    pub status: SyncStatus,
+}
+
+impl ModComparison {
+    pub fn name(&self) -> &str {
+        self.server
+            .as_ref()
+            .or(self.client.as_ref())
+            .map(|mod_info| mod_info.name.as_str())
+            .unwrap_or(&self.mod_id)
+    }
+}
+
+fn find<'a>(instance: &'a Instance, mod_id: &str) -> Option<&'a ModInfo> {
+    instance
+        .mods
+        .iter()
+        .find(|mod_info| mod_info.mod_id.eq_ignore_ascii_case(mod_id))
+}
+
+/// Mods the server loads that matter to clients, server-side mods are skipped. Client-side
+/// mods of the client are not listed either.
+pub fn compare_mods(server: &Instance, client: &Instance) -> Vec<ModComparison> {
+    let mut comparisons: Vec<ModComparison> = server
+        .active_mods()
+        .filter(|mod_info| !mod_info.mod_id.is_empty() && mod_info.side != ModSide::Server)
+        .map(|server_mod| {
+            let client_mod = find(client, &server_mod.mod_id);
+            let status = match client_mod {
+                Some(client_mod) if client_mod.version == server_mod.version => SyncStatus::Matches,
+                Some(_) => SyncStatus::Mismatch,
+                None if server_mod.required_on_client => SyncStatus::Missing,
+                None => SyncStatus::Optional,
+            };
+            ModComparison {
+                mod_id: server_mod.mod_id.clone(),
+                server: Some(server_mod.clone()),
+                client: client_mod.cloned(),
+                status,
+            }
+        })
+        .collect();
+
+    comparisons.extend(
+        client
+            .mods
+            .iter()
+            .filter(|mod_info| {
+                !mod_info.mod_id.is_empty()
+                    && mod_info.side == ModSide::Universal
+                    && find(server, &mod_info.mod_id).is_none()
+            })
+            .map(|client_mod| ModComparison {
+                mod_id: client_mod.mod_id.clone(),
+                server: None,
+                client: Some(client_mod.clone()),
+                status: SyncStatus::Extra,
+            }),
+    );
+    comparisons.sort_by_key(|comparison| comparison.name().to_lowercase());
+
+    comparisons
+}
+
+/// Copies missing and mismatched mods from the server into the client, replacing other
+/// versions, see [`before_mods_change`] for what happens first. Returns the synced mods.
+pub fn sync_client_mods(server: &Instance, client: &Instance) -> io::Result<Vec<ModComparison>> {
+    let changes: Vec<ModComparison> = compare_mods(server, client)
+        .into_iter()
+        .filter(|comparison| comparison.status.needs_sync())
+        .collect();
+    if changes.is_empty() {
+        return Ok(changes);
+    }
+
+    before_mods_change(client, &format!("sync with {}", server.name))?;
+    let client_mods_path = client.mods_path();
+    for change in &changes {
+        let Some(server_mod) = &change.server else {
+            continue;
+        };
+        let data = fs::read(server.mods_path().join(&server_mod.zip_name))?;
+        replace_mod_file(
+            &client_mods_path,
+            change
+                .client
+                .as_ref()
+                .map(|client_mod| client_mod.zip_name.as_os_str()),
+            &server_mod.zip_name.to_string_lossy(),
+            &data,
+        )?;
+        debug!("synced {}@{}", server_mod.mod_id, server_mod.version);
+    }
+
+    info!(
+        "synced {} mods of {} with {}",
+        changes.len(),
+        client.folder_name_string(),
+        server.folder_name_string()
+    );
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instance::InstanceKind;
+    use crate::test_util;
+
+    fn mod_info(mod_id: &str, version: &str, side: ModSide, required: bool) -> ModInfo {
+        ModInfo {
+            side,
+            required_on_client: required,
+            ..test_util::mod_info(mod_id, version)
+        }
+    }
+
+    #[test]
+    fn compare_server_and_client() {
+        let mut server = Instance::new("server");
+        server.kind = InstanceKind::Server;
+        server.mods = vec![
+            mod_info("carryon", "1.9.0", ModSide::Universal, true),
+            mod_info("expandedfoods", "1.7.4", ModSide::Universal, true),
+            mod_info("servertweaks", "1.0.0", ModSide::Server, true),
+            mod_info("hudclock", "2.0.0", ModSide::Client, true),
+            mod_info("betterruins", "0.4.0", ModSide::Universal, false),
+            mod_info("primitivesurvival", "3.7.5", ModSide::Universal, true),
+        ];
+        let mut client = Instance::new("client");
+        client.mods = vec![
+            mod_info("carryon", "1.9.0", ModSide::Universal, true),
+            mod_info("expandedfoods", "1.7.3", ModSide::Universal, true),
+            mod_info("hudclock", "1.0.0", ModSide::Client, true),
+            mod_info("extrachests", "1.1.0", ModSide::Universal, true),
+        ];
+
+        let statuses: Vec<_> = compare_mods(&server, &client)
+            .iter()
+            .map(|comparison| (comparison.mod_id.clone(), comparison.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                ("betterruins".to_string(), SyncStatus::Optional),
+                ("carryon".to_string(), SyncStatus::Matches),
+                ("expandedfoods".to_string(), SyncStatus::Mismatch),
+                ("extrachests".to_string(), SyncStatus::Extra),
+                ("primitivesurvival".to_string(), SyncStatus::Missing),
+            ]
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use crate::toast::Toast;
+use crate::{Message, drift_summary, style};
+use iced::alignment::Vertical;
+use iced::widget::{
+    button, column, container, horizontal_rule, horizontal_space, pick_list, row, scrollable, text,
+};
+use iced::{Element, Length};
+use log::error;
+use rustic_vs::instance::Instance;
+use rustic_vs::mods::ModInfo;
+use rustic_vs::server_sync::{ModComparison, SyncStatus, compare_mods, sync_client_mods};
+use std::fmt;
+
+#[derive(Debug, Clone)]
+pub enum ServerSyncMessage {
+    Server(ServerChoice),
+    Sync,
+}
+
+/// Server instance to sync with, as pick list entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerChoice {
+    index: usize,
+    name: String,
+}
+
+impl fmt::Display for ServerChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+fn server_choices(instances: &[Instance]) -> Vec<ServerChoice> {
+    instances
+        .iter()
+        .enumerate()
+        .filter(|(_, instance)| instance.is_server())
+        .map(|(index, instance)| ServerChoice {
+            index,
+            name: instance.name.clone(),
+        })
+        .collect()
+}
+
+/// Mods of a client instance next to the ones of a server instance, opened from the mods list.
+pub struct ServerSyncView {
+    server: Option<ServerChoice>,
+    comparisons: Vec<ModComparison>,
+}
+
+impl ServerSyncView {
+    pub fn open(instances: &[Instance], client: usize) -> ServerSyncView {
+        let mut view = Self {
+            server: server_choices(instances).into_iter().next(),
+            comparisons: Vec::new(),
+        };
+        view.compare(instances, client);
+        view
+    }
+
+    /// Compares again, e.g. after a sync finished.
+    pub fn compare(&mut self, instances: &[Instance], client: usize) {
+        self.comparisons = match &self.server {
+            Some(server) => compare_mods(&instances[server.index], &instances[client]),
+            None => Vec::new(),
+        };
+    }
+
+    /// Server to sync with on [`ServerSyncMessage::Sync`], see [`sync_job`].
+    pub fn server_index(&self) -> Option<usize> {
+        self.server.as_ref().map(|server| server.index)
+    }
+
+    pub fn update(&mut self, message: ServerSyncMessage, instances: &[Instance], client: usize) {
+        match message {
+            ServerSyncMessage::Server(server) => self.server = Some(server),
+            // runs in the background, see `sync_job`
+            ServerSyncMessage::Sync => {}
+        }
+        self.compare(instances, client);
+    }
+
+    pub fn view<'a>(&'a self, instances: &[Instance]) -> Element<'a, Message> {
+        let servers = server_choices(instances);
+        let pending = self
+            .comparisons
+            .iter()
+            .filter(|comparison| comparison.status.needs_sync())
+            .count();
+
+        let header = row![
+            text("Mod").width(Length::FillPortion(2)),
+            text("Server").width(Length::FillPortion(1)),
+            text("Client").width(Length::FillPortion(1)),
+            text("").width(80),
+        ]
+        .spacing(10)
+        .padding([5, 10]);
+
+        let rows = self
+            .comparisons
+            .iter()
+            .enumerate()
+            .map(|(index, comparison)| {
+                let badge: fn(&_) -> _ = match comparison.status {
+                    SyncStatus::Matches => style::badge_success,
+                    SyncStatus::Missing | SyncStatus::Mismatch => style::badge_danger,
+                    SyncStatus::Optional | SyncStatus::Extra => container::bordered_box,
+                };
+                container(
+                    row![
+                        text(comparison.name()).width(Length::FillPortion(2)),
+                        text(version(&comparison.server)).width(Length::FillPortion(1)),
+                        text(version(&comparison.client)).width(Length::FillPortion(1)),
+                        container(
+                            container(text(comparison.status.to_string()).size(12))
+                                .padding([1, 5])
+                                .style(badge)
+                        )
+                        .width(80),
+                    ]
+                    .spacing(10)
+                    .align_y(Vertical::Center),
+                )
+                .padding([5, 10])
+                .style(style::striped(index))
+                .into()
+            });
+
+        let content: Element<Message> = if servers.is_empty() {
+            text("no server instances yet").into()
+        } else if self.comparisons.is_empty() {
+            text("the server has no mods clients need").into()
+        } else {
+            column![header, horizontal_rule(1)]
+                .extend(rows)
+                .padding(10)
+                .into()
+        };
+
+        column![
+            row![
+                button("<")
+                    .style(button::secondary)
+                    .on_press(Message::CloseServerSync),
+                text("Sync with server").size(20),
+                horizontal_space(),
+                pick_list(servers, self.server.clone(), |server| {
+                    Message::ServerSync(ServerSyncMessage::Server(server))
+                })
+                .placeholder("<server instance>"),
+            ]
+            .spacing(10)
+            .padding(10)
+            .align_y(Vertical::Center),
+            horizontal_rule(1),
+            scrollable(content).height(Length::Fill),
+            row![
+                horizontal_space(),
+                button(text(format!("Sync {} mods", pending))).on_press_maybe(
+                    (pending > 0).then_some(Message::ServerSync(ServerSyncMessage::Sync))
+                ),
+            ]
+            .padding(10),
+        ]
+        .into()
+    }
+}
+
+fn version(mod_info: &Option<ModInfo>) -> String {
+    mod_info
+        .as_ref()
+        .map(|mod_info| mod_info.version.clone())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// Background job of [`ServerSyncMessage::Sync`].
+pub fn sync_job(server: &Instance, client: &Instance) -> Option<Toast> {
+    Some(match sync_client_mods(server, client) {
+        Ok(changes) if changes.is_empty() => {
+            Toast::info("Mods already match the server", &server.name)
+        }
+        Ok(changes) => Toast::info(
+            format!("Synced {} with {}", client.name, server.name),
+            drift_summary(
+                changes
+                    .iter()
+                    .map(|change| format!("{} {}", change.name(), version(&change.server))),
+            ),
+        ),
+        Err(e) => {
+            error!("failed syncing {}: {}", client.folder_name_string(), e);
+            Toast::error("Failed syncing with server", e)
+        }
+    })
+}
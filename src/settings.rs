@@ -0,0 +1,607 @@
+use crate::game::default_data_path;
+use log::debug;
+use serde_json::{Map, Value};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::{fmt, fs, io};
+
+pub const CLIENT_SETTINGS: &str = "clientsettings.json";
+pub const MOD_PATHS: &str = "stringListSettings.modPaths";
+
+/// Keys that always belong to the instance and are never synced from the global config.
+pub const INSTANCE_KEYS: [&str; 2] = [MOD_PATHS, "stringListSettings.disabledMods"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncGroup {
+    Keybinds,
+    Graphics,
+    Audio,
+}
+
+impl SyncGroup {
+    pub const ALL: [SyncGroup; 3] = [SyncGroup::Keybinds, SyncGroup::Graphics, SyncGroup::Audio];
+
+    pub fn keys(&self) -> &'static [&'static str] {
+        match self {
+            SyncGroup::Keybinds => &["keyMapping"],
+            SyncGroup::Graphics => &[
+                "intSettings.viewDistance",
+                "intSettings.maxFps",
+                "intSettings.fieldOfView",
+                "intSettings.vsyncMode",
+                "intSettings.ssaoQuality",
+                "intSettings.shadowMapQuality",
+                "intSettings.particleLevel",
+                "floatSettings.guiScale",
+                "floatSettings.gammaLevel",
+            ],
+            SyncGroup::Audio => &[
+                "intSettings.soundLevel",
+                "intSettings.entitySoundLevel",
+                "intSettings.ambientSoundLevel",
+                "intSettings.weatherSoundLevel",
+                "intSettings.musicLevel",
+                "intSettings.musicFrequency",
+            ],
+        }
+    }
+
+    /// All keys of the group are in the sync list.
+    pub fn is_enabled(&self, sync_keys: &[String]) -> bool {
+        self.keys()
+            .iter()
+            .all(|key| sync_keys.iter().any(|k| k == key))
+    }
+
+    pub fn toggle(&self, sync_keys: &mut Vec<String>, enabled: bool) {
+        sync_keys.retain(|key| !self.keys().contains(&key.as_str()));
+        if enabled {
+            sync_keys.extend(self.keys().iter().map(ToString::to_string));
+        }
+    }
+}
+
+impl fmt::Display for SyncGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncGroup::Keybinds => write!(f, "Keybinds"),
+            SyncGroup::Graphics => write!(f, "Graphics"),
+            SyncGroup::Audio => write!(f, "Audio"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(io::Error),
+    ParseError(json5::Error),
+    WriteError(serde_json::Error),
+    /// The edited value no longer has the structure of the file, with the key that differs.
+    StructureChanged(String),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Io(e) => write!(f, "{}", e),
+            SettingsError::ParseError(e) => write!(f, "invalid JSON: {}", e),
+            SettingsError::WriteError(e) => write!(f, "failed writing JSON: {}", e),
+            SettingsError::StructureChanged(key) => {
+                write!(f, "'{}' does not match the structure of the file", key)
+            }
+        }
+    }
+}
+
+impl Error for SettingsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SettingsError::Io(e) => Some(e),
+            SettingsError::ParseError(e) => Some(e),
+            SettingsError::WriteError(e) => Some(e),
+            SettingsError::StructureChanged(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for SettingsError {
+    fn from(e: io::Error) -> Self {
+        SettingsError::Io(e)
+    }
+}
+
+/// clientsettings.json of the game without `--dataPath`.
+pub fn global_settings_path() -> Option<PathBuf> {
+    default_data_path().map(|path| path.join(CLIENT_SETTINGS))
+}
+
+/// Reads a JSON file leniently, the game and mods do not always write strict JSON.
+pub fn read_json(path: &Path) -> Result<Value, SettingsError> {
+    let data = fs::read_to_string(path)?;
+    json5::from_str(&data).map_err(SettingsError::ParseError)
+}
+
+pub fn write_json(path: &Path, value: &Value) -> Result<(), SettingsError> {
+    let data = serde_json::to_string_pretty(value).map_err(SettingsError::WriteError)?;
+    fs::write(path, data)?;
+
+    Ok(())
+}
+
+/// Looks up a dotted key like `intSettings.viewDistance`.
+pub fn get_key<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    key.split('.')
+        .try_fold(value, |value, part| value.get(part))
+}
+
+/// Sets a dotted key, creating missing objects on the way.
+pub fn set_key(value: &mut Value, key: &str, new_value: Value) {
+    let mut current = value;
+    for part in key.split('.') {
+        if !current.is_object() {
+            *current = Value::Object(Map::new());
+        }
+        current = current
+            .as_object_mut()
+            .expect("just made an object")
+            .entry(part)
+            .or_insert(Value::Null);
+    }
+    *current = new_value;
+}
+
+/// Copies the keys from the global settings, keys missing there are left alone.
+pub fn sync_keys(global: &Value, settings: &mut Value, keys: &[String]) {
+    for key in keys {
+        if INSTANCE_KEYS.contains(&key.as_str()) {
+            debug!("not syncing instance key {}", key);
+            continue;
+        }
+        if let Some(value) = get_key(global, key) {
+            set_key(settings, key, value.clone());
+        }
+    }
+}
+
+/// The base game content is loaded from `Mods` in the install folder, mods of the instance
+/// from its own `Mods` folder.
+pub fn set_mod_paths(settings: &mut Value, mods_path: &Path) {
+    set_key(
+        settings,
+        MOD_PATHS,
+        Value::from(vec![
+            "Mods".to_string(),
+            mods_path.to_string_lossy().to_string(),
+        ]),
+    );
+}
+
+/// Copies the global clientsettings.json into a data folder, pointing `modPaths` at its mods.
+pub fn copy_global_settings(data_path: &Path, mods_path: &Path) -> Result<(), SettingsError> {
+    let Some(global_path) = global_settings_path() else {
+        return Err(SettingsError::Io(io::ErrorKind::NotFound.into()));
+    };
+
+    let mut settings = read_json(&global_path)?;
+    set_mod_paths(&mut settings, mods_path);
+    write_json(&data_path.join(CLIENT_SETTINGS), &settings)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowMode {
+    Windowed,
+    Fullscreen,
+    Borderless,
+}
+
+impl WindowMode {
+    pub const ALL: [WindowMode; 3] = [
+        WindowMode::Windowed,
+        WindowMode::Fullscreen,
+        WindowMode::Borderless,
+    ];
+
+    fn from_i64(value: i64) -> Option<WindowMode> {
+        match value {
+            0 => Some(WindowMode::Windowed),
+            1 => Some(WindowMode::Fullscreen),
+            2 => Some(WindowMode::Borderless),
+            _ => None,
+        }
+    }
+
+    fn to_i64(self) -> i64 {
+        match self {
+            WindowMode::Windowed => 0,
+            WindowMode::Fullscreen => 1,
+            WindowMode::Borderless => 2,
+        }
+    }
+}
+
+impl fmt::Display for WindowMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WindowMode::Windowed => write!(f, "Windowed"),
+            WindowMode::Fullscreen => write!(f, "Fullscreen"),
+            WindowMode::Borderless => write!(f, "Borderless"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub code: String,
+    pub key_code: i64,
+}
+
+/// Keys of the game's `GlKeys` enum that are not in a numbered run.
+const NAMED_KEYS: [(i64, &str); 50] = [
+    (0, "Unknown"),
+    (1, "Left Shift"),
+    (2, "Right Shift"),
+    (3, "Left Ctrl"),
+    (4, "Right Ctrl"),
+    (5, "Left Alt"),
+    (6, "Right Alt"),
+    (7, "Left Win"),
+    (8, "Right Win"),
+    (9, "Menu"),
+    (45, "Up"),
+    (46, "Down"),
+    (47, "Left"),
+    (48, "Right"),
+    (49, "Enter"),
+    (50, "Escape"),
+    (51, "Space"),
+    (52, "Tab"),
+    (53, "Backspace"),
+    (54, "Insert"),
+    (55, "Delete"),
+    (56, "Page Up"),
+    (57, "Page Down"),
+    (58, "Home"),
+    (59, "End"),
+    (60, "Caps Lock"),
+    (61, "Scroll Lock"),
+    (62, "Print Screen"),
+    (63, "Pause"),
+    (64, "Num Lock"),
+    (65, "Clear"),
+    (66, "Sleep"),
+    (77, "Keypad /"),
+    (78, "Keypad *"),
+    (79, "Keypad -"),
+    (80, "Keypad +"),
+    (81, "Keypad ."),
+    (82, "Keypad Enter"),
+    (119, "`"),
+    (120, "-"),
+    (121, "="),
+    (122, "["),
+    (123, "]"),
+    (124, ";"),
+    (125, "'"),
+    (126, ","),
+    (127, "."),
+    (128, "/"),
+    (129, "\\"),
+    (130, "Non-US \\"),
+];
+const LAST_KEY_CODE: i64 = 130;
+
+/// Key code of a binding as the game stores it, shown by name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameKey(pub i64);
+
+impl GameKey {
+    /// All keys the game knows, in key code order.
+    pub fn all() -> Vec<GameKey> {
+        (0..=LAST_KEY_CODE).map(GameKey).collect()
+    }
+
+    pub fn name(&self) -> Option<String> {
+        let code = self.0;
+        let name = match code {
+            10..=44 => format!("F{}", code - 9),
+            67..=76 => format!("Keypad {}", code - 67),
+            83..=108 => char::from(b'A' + (code - 83) as u8).to_string(),
+            109..=118 => (code - 109).to_string(),
+            _ => NAMED_KEYS
+                .iter()
+                .find(|(named, _)| *named == code)?
+                .1
+                .to_string(),
+        };
+        Some(name)
+    }
+}
+
+impl fmt::Display for GameKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "Key {}", self.0),
+        }
+    }
+}
+
+/// Typed view of the commonly edited settings, `None` for keys missing in the file.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CommonSettings {
+    pub screen_width: Option<i64>,
+    pub screen_height: Option<i64>,
+    pub window_mode: Option<WindowMode>,
+    pub view_distance: Option<i64>,
+    pub gui_scale: Option<f64>,
+    pub sound_level: Option<i64>,
+    pub music_level: Option<i64>,
+    pub key_bindings: Vec<KeyBinding>,
+    pub mod_paths: Vec<String>,
+    pub disabled_mods: Vec<String>,
+}
+
+const SCREEN_WIDTH: &str = "intSettings.screenWidth";
+const SCREEN_HEIGHT: &str = "intSettings.screenHeight";
+const WINDOW_MODE: &str = "intSettings.gameWindowMode";
+const VIEW_DISTANCE: &str = "intSettings.viewDistance";
+const GUI_SCALE: &str = "floatSettings.guiScale";
+const SOUND_LEVEL: &str = "intSettings.soundLevel";
+const MUSIC_LEVEL: &str = "intSettings.musicLevel";
+const KEY_MAPPING: &str = "keyMapping";
+const DISABLED_MODS: &str = "stringListSettings.disabledMods";
+
+/// clientsettings.json with typed access to common settings, all other keys pass through.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientSettings {
+    pub raw: Value,
+}
+
+impl ClientSettings {
+    pub fn load(path: &Path) -> Result<ClientSettings, SettingsError> {
+        Ok(Self {
+            raw: read_json(path)?,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), SettingsError> {
+        write_json(path, &self.raw)
+    }
+
+    pub fn common(&self) -> CommonSettings {
+        let int = |key| get_key(&self.raw, key).and_then(Value::as_i64);
+        let strings = |key| {
+            get_key(&self.raw, key)
+                .and_then(Value::as_array)
+                .map(|values| {
+                    values
+                        .iter()
+                        .filter_map(|value| value.as_str().map(String::from))
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        let key_bindings = get_key(&self.raw, KEY_MAPPING)
+            .and_then(Value::as_object)
+            .map(|mapping| {
+                mapping
+                    .iter()
+                    .filter_map(|(code, binding)| {
+                        Some(KeyBinding {
+                            code: code.clone(),
+                            key_code: binding.get("KeyCode")?.as_i64()?,
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        CommonSettings {
+            screen_width: int(SCREEN_WIDTH),
+            screen_height: int(SCREEN_HEIGHT),
+            window_mode: int(WINDOW_MODE).and_then(WindowMode::from_i64),
+            view_distance: int(VIEW_DISTANCE),
+            gui_scale: get_key(&self.raw, GUI_SCALE).and_then(Value::as_f64),
+            sound_level: int(SOUND_LEVEL),
+            music_level: int(MUSIC_LEVEL),
+            key_bindings,
+            mod_paths: strings(MOD_PATHS),
+            disabled_mods: strings(DISABLED_MODS),
+        }
+    }
+
+    /// Writes the typed settings back, keys that are `None` or empty and missing in the file
+    /// are not added.
+    pub fn apply(&mut self, common: &CommonSettings) {
+        let ints = [
+            (SCREEN_WIDTH, common.screen_width),
+            (SCREEN_HEIGHT, common.screen_height),
+            (WINDOW_MODE, common.window_mode.map(WindowMode::to_i64)),
+            (VIEW_DISTANCE, common.view_distance),
+            (SOUND_LEVEL, common.sound_level),
+            (MUSIC_LEVEL, common.music_level),
+        ];
+        for (key, value) in ints {
+            if let Some(value) = value {
+                set_key(&mut self.raw, key, Value::from(value));
+            }
+        }
+        if let Some(gui_scale) = common.gui_scale {
+            // the file may store whole scales as integers, which stay integers
+            let value = match get_key(&self.raw, GUI_SCALE) {
+                Some(old) if old.is_i64() && gui_scale.fract() == 0.0 => {
+                    Value::from(gui_scale as i64)
+                }
+                _ => Value::from(gui_scale),
+            };
+            set_key(&mut self.raw, GUI_SCALE, value);
+        }
+
+        for (key, values) in [
+            (MOD_PATHS, &common.mod_paths),
+            (DISABLED_MODS, &common.disabled_mods),
+        ] {
+            if !values.is_empty() || get_key(&self.raw, key).is_some() {
+                set_key(&mut self.raw, key, Value::from(values.clone()));
+            }
+        }
+
+        for binding in &common.key_bindings {
+            let key = format!("{}.{}.KeyCode", KEY_MAPPING, binding.code);
+            set_key(&mut self.raw, &key, Value::from(binding.key_code));
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SettingChange {
+    pub key: String,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+/// Changed leaf keys between two settings files, arrays are compared as a whole.
+pub fn diff(old: &Value, new: &Value) -> Vec<SettingChange> {
+    let mut changes = Vec::new();
+    diff_into(&mut changes, String::new(), Some(old), Some(new));
+    changes
+}
+
+fn diff_into(
+    changes: &mut Vec<SettingChange>,
+    key: String,
+    old: Option<&Value>,
+    new: Option<&Value>,
+) {
+    match (old, new) {
+        (Some(Value::Object(old)), Some(Value::Object(new))) => {
+            let keys = old
+                .keys()
+                .chain(new.keys().filter(|k| !old.contains_key(*k)));
+            for part in keys {
+                let child_key = if key.is_empty() {
+                    part.clone()
+                } else {
+                    format!("{}.{}", key, part)
+                };
+                diff_into(changes, child_key, old.get(part), new.get(part));
+            }
+        }
+        (old, new) if old != new => changes.push(SettingChange {
+            key,
+            old: old.cloned(),
+            new: new.cloned(),
+        }),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn sync_keeps_instance_keys() {
+        let global = json!({
+            "intSettings": { "viewDistance": 512, "soundLevel": 20 },
+            "stringListSettings": { "modPaths": ["Mods", "/global/Mods"] },
+            "keyMapping": { "walkforward": { "keyCode": 45 } }
+        });
+        let mut settings = json!({
+            "intSettings": { "viewDistance": 128, "soundLevel": 100 },
+            "stringListSettings": { "modPaths": ["Mods", "/instance/Mods"] }
+        });
+
+        let keys = [
+            "intSettings.viewDistance",
+            "keyMapping",
+            MOD_PATHS,
+            "missing.key",
+        ]
+        .map(String::from);
+        sync_keys(&global, &mut settings, &keys);
+
+        assert_eq!(
+            settings,
+            json!({
+                "intSettings": { "viewDistance": 512, "soundLevel": 100 },
+                "stringListSettings": { "modPaths": ["Mods", "/instance/Mods"] },
+                "keyMapping": { "walkforward": { "keyCode": 45 } }
+            })
+        );
+    }
+
+    #[test]
+    fn apply_common_settings() {
+        let mut settings = ClientSettings {
+            raw: json!({
+                "intSettings": { "viewDistance": 128, "unknownSetting": 7 },
+                "floatSettings": { "guiScale": 1 },
+                "keyMapping": { "jump": { "Code": "jump", "KeyCode": 51, "Shift": false } }
+            }),
+        };
+
+        let mut common = settings.common();
+        assert_eq!(common.view_distance, Some(128));
+        assert_eq!(common.screen_width, None);
+        assert_eq!(common.gui_scale, Some(1.0));
+        assert_eq!(
+            GameKey(common.key_bindings[0].key_code).to_string(),
+            "Space"
+        );
+
+        common.view_distance = Some(256);
+        common.key_bindings[0].key_code = 52;
+        settings.apply(&common);
+
+        assert_eq!(
+            settings.raw,
+            json!({
+                "intSettings": { "viewDistance": 256, "unknownSetting": 7 },
+                "floatSettings": { "guiScale": 1 },
+                "keyMapping": { "jump": { "Code": "jump", "KeyCode": 52, "Shift": false } }
+            })
+        );
+
+        common.gui_scale = Some(1.25);
+        settings.apply(&common);
+        assert_eq!(get_key(&settings.raw, GUI_SCALE), Some(&json!(1.25)));
+    }
+
+    #[test]
+    fn key_names() {
+        let names: Vec<_> = [0, 10, 44, 51, 67, 83, 108, 109, 129, 131]
+            .map(|code| GameKey(code).to_string())
+            .into();
+        assert_eq!(
+            names,
+            [
+                "Unknown", "F1", "F35", "Space", "Keypad 0", "A", "Z", "0", "\\", "Key 131"
+            ]
+        );
+        assert!(GameKey::all().iter().all(|key| key.name().is_some()));
+    }
+
+    #[test]
+    fn diff_settings() {
+        let old = json!({ "intSettings": { "a": 1, "b": 2 }, "list": [1] });
+        let new = json!({ "intSettings": { "a": 1, "b": 3, "c": 4 }, "list": [1, 2] });
+
+        let keys: Vec<_> = diff(&old, &new).into_iter().map(|c| c.key).collect();
+        assert_eq!(keys, vec!["intSettings.b", "intSettings.c", "list"]);
+    }
+
+    #[test]
+    fn toggle_sync_group() {
+        let mut keys = vec!["custom.key".to_string()];
+
+        SyncGroup::Audio.toggle(&mut keys, true);
+        assert!(SyncGroup::Audio.is_enabled(&keys));
+        assert!(!SyncGroup::Graphics.is_enabled(&keys));
+
+        SyncGroup::Audio.toggle(&mut keys, false);
+        assert_eq!(keys, vec!["custom.key".to_string()]);
+    }
+}
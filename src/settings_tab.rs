@@ -0,0 +1,311 @@
+use crate::ui::norm_str;
+use crate::{Message, style};
+use iced::alignment::Vertical;
+use iced::widget::{
+    Column, Row, button, column, container, horizontal_space, pick_list, row, scrollable, text,
+    text_input,
+};
+use iced::{Element, Length};
+use rustic_vs::settings::{
+    ClientSettings, CommonSettings, GameKey, KeyBinding, SettingChange, SettingsError, WindowMode,
+    diff,
+};
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    ScreenWidth,
+    ScreenHeight,
+    ViewDistance,
+    GuiScale,
+    SoundLevel,
+    MusicLevel,
+    ModPaths,
+    DisabledMods,
+}
+
+impl Field {
+    const ALL: [Field; 8] = [
+        Field::ScreenWidth,
+        Field::ScreenHeight,
+        Field::ViewDistance,
+        Field::GuiScale,
+        Field::SoundLevel,
+        Field::MusicLevel,
+        Field::ModPaths,
+        Field::DisabledMods,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Field::ScreenWidth => "Width:",
+            Field::ScreenHeight => "Height:",
+            Field::ViewDistance => "View distance:",
+            Field::GuiScale => "GUI scale:",
+            Field::SoundLevel => "Volume:",
+            Field::MusicLevel => "Music:",
+            Field::ModPaths => "Mod paths:",
+            Field::DisabledMods => "Disabled mods:",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum SettingsMessage {
+    Field(Field, String),
+    WindowMode(WindowMode),
+    Key(usize, GameKey),
+    Preview,
+    Back,
+    Save,
+}
+
+/// Form state of the settings tab, numbers are kept as text until they are previewed.
+pub struct SettingsEditor {
+    settings: ClientSettings,
+    fields: Vec<String>,
+    window_mode: Option<WindowMode>,
+    key_bindings: Vec<(String, GameKey)>,
+    keys: Vec<GameKey>,
+    preview: Option<Result<(ClientSettings, Vec<SettingChange>), String>>,
+}
+
+fn setting_row<'a>(label: &'a str, widget: Element<'a, Message>) -> Row<'a, Message> {
+    row![text(label).width(110), widget]
+        .spacing(10)
+        .align_y(Vertical::Center)
+}
+
+fn opt_to_string<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+fn parse_opt<T: FromStr>(field: Field, value: &str) -> Result<Option<T>, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value
+        .parse()
+        .map(Some)
+        .map_err(|_| format!("{} '{}' is not a number", field.label(), value))
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+impl SettingsEditor {
+    pub fn load(path: &Path) -> Result<SettingsEditor, SettingsError> {
+        let settings = ClientSettings::load(path)?;
+        let common = settings.common();
+
+        let fields = Field::ALL
+            .iter()
+            .map(|field| match field {
+                Field::ScreenWidth => opt_to_string(common.screen_width),
+                Field::ScreenHeight => opt_to_string(common.screen_height),
+                Field::ViewDistance => opt_to_string(common.view_distance),
+                Field::GuiScale => opt_to_string(common.gui_scale),
+                Field::SoundLevel => opt_to_string(common.sound_level),
+                Field::MusicLevel => opt_to_string(common.music_level),
+                Field::ModPaths => common.mod_paths.join(", "),
+                Field::DisabledMods => common.disabled_mods.join(", "),
+            })
+            .collect();
+
+        Ok(Self {
+            fields,
+            window_mode: common.window_mode,
+            key_bindings: common
+                .key_bindings
+                .iter()
+                .map(|binding| (binding.code.clone(), GameKey(binding.key_code)))
+                .collect(),
+            keys: GameKey::all(),
+            settings,
+            preview: None,
+        })
+    }
+
+    fn field(&self, field: Field) -> &str {
+        &self.fields[field as usize]
+    }
+
+    fn to_common(&self) -> Result<CommonSettings, String> {
+        Ok(CommonSettings {
+            screen_width: parse_opt(Field::ScreenWidth, self.field(Field::ScreenWidth))?,
+            screen_height: parse_opt(Field::ScreenHeight, self.field(Field::ScreenHeight))?,
+            window_mode: self.window_mode,
+            view_distance: parse_opt(Field::ViewDistance, self.field(Field::ViewDistance))?,
+            gui_scale: parse_opt(Field::GuiScale, self.field(Field::GuiScale))?,
+            sound_level: parse_opt(Field::SoundLevel, self.field(Field::SoundLevel))?,
+            music_level: parse_opt(Field::MusicLevel, self.field(Field::MusicLevel))?,
+            key_bindings: self
+                .key_bindings
+                .iter()
+                .map(|(code, key)| KeyBinding {
+                    code: code.clone(),
+                    key_code: key.0,
+                })
+                .collect(),
+            mod_paths: split_list(self.field(Field::ModPaths)),
+            disabled_mods: split_list(self.field(Field::DisabledMods)),
+        })
+    }
+
+    /// Returns `Some` with the save result when the settings were written.
+    pub fn update(
+        &mut self,
+        message: SettingsMessage,
+        path: &Path,
+    ) -> Option<Result<usize, SettingsError>> {
+        match message {
+            SettingsMessage::Field(field, value) => self.fields[field as usize] = value,
+            SettingsMessage::WindowMode(mode) => self.window_mode = Some(mode),
+            SettingsMessage::Key(index, key) => self.key_bindings[index].1 = key,
+            SettingsMessage::Preview => {
+                self.preview = Some(self.to_common().map(|common| {
+                    let mut settings = self.settings.clone();
+                    settings.apply(&common);
+                    let changes = diff(&self.settings.raw, &settings.raw);
+                    (settings, changes)
+                }));
+            }
+            SettingsMessage::Back => self.preview = None,
+            SettingsMessage::Save => {
+                if let Some(Ok((settings, changes))) = self.preview.take() {
+                    let result = settings.save(path).map(|_| changes.len());
+                    if result.is_ok() {
+                        self.settings = settings;
+                    }
+                    return Some(result);
+                }
+            }
+        }
+        None
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        match &self.preview {
+            Some(preview) => self.preview_view(preview),
+            None => self.form_view(),
+        }
+    }
+
+    fn form_view(&self) -> Element<'_, Message> {
+        let field_input = |field: Field| {
+            setting_row(
+                field.label(),
+                text_input("<not set>", self.field(field))
+                    .on_input(move |value| Message::Settings(SettingsMessage::Field(field, value)))
+                    .into(),
+            )
+        };
+
+        let mut form = Column::new()
+            .push(field_input(Field::ScreenWidth))
+            .push(field_input(Field::ScreenHeight))
+            .push(setting_row(
+                "Window:",
+                pick_list(WindowMode::ALL, self.window_mode, |mode| {
+                    Message::Settings(SettingsMessage::WindowMode(mode))
+                })
+                .placeholder("<not set>")
+                .into(),
+            ));
+        for field in &Field::ALL[2..] {
+            form = form.push(field_input(*field));
+        }
+
+        let key_bindings = self
+            .key_bindings
+            .iter()
+            .enumerate()
+            .map(|(index, (code, key))| {
+                setting_row(
+                    code.as_str(),
+                    pick_list(self.keys.as_slice(), Some(*key), move |key| {
+                        Message::Settings(SettingsMessage::Key(index, key))
+                    })
+                    .into(),
+                )
+                .into()
+            });
+
+        column![
+            scrollable(
+                form.push(text("Keybindings").size(16))
+                    .extend(key_bindings)
+                    .spacing(5)
+                    .padding(10),
+            )
+            .height(Length::Fill),
+            row![
+                horizontal_space(),
+                button("Preview changes").on_press(Message::Settings(SettingsMessage::Preview)),
+            ]
+            .padding(10),
+        ]
+        .into()
+    }
+
+    fn preview_view<'a>(
+        &'a self,
+        preview: &'a Result<(ClientSettings, Vec<SettingChange>), String>,
+    ) -> Element<'a, Message> {
+        let (content, can_save): (Element<Message>, bool) = match preview {
+            Err(e) => (text(e).style(text::danger).into(), false),
+            Ok((_, changes)) if changes.is_empty() => (text("no changes").into(), false),
+            Ok((_, changes)) => (
+                column(changes.iter().enumerate().map(|(index, change)| {
+                    let value = |value: &Option<serde_json::Value>| {
+                        value
+                            .as_ref()
+                            .map(|v| norm_str(&v.to_string()))
+                            .unwrap_or_else(|| "<not set>".to_string())
+                    };
+                    container(
+                        row![
+                            text(&change.key).width(Length::FillPortion(2)),
+                            text(value(&change.old))
+                                .style(text::danger)
+                                .width(Length::FillPortion(1)),
+                            text(value(&change.new))
+                                .style(text::success)
+                                .width(Length::FillPortion(1)),
+                        ]
+                        .spacing(10)
+                        .align_y(Vertical::Top),
+                    )
+                    .padding([5, 10])
+                    .style(style::striped(index))
+                    .into()
+                }))
+                .into(),
+                true,
+            ),
+        };
+
+        column![
+            scrollable(column![content].padding(10)).height(Length::Fill),
+            row![
+                horizontal_space(),
+                button("Save")
+                    .on_press_maybe(can_save.then_some(Message::Settings(SettingsMessage::Save))),
+                button("Back")
+                    .style(button::secondary)
+                    .on_press(Message::Settings(SettingsMessage::Back)),
+            ]
+            .spacing(10)
+            .padding(10),
+        ]
+        .into()
+    }
+}
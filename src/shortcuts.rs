@@ -0,0 +1,380 @@
+//! Launchers that start an instance without opening the GUI. Desktop entries run
+//! `rustic-vs launch` and are only written on Linux, into `~/.local/share/applications`.
+//! Launch scripts do what the launcher does without needing Rustic at all. Only scripts
+//! carrying [`SCRIPT_MARKER`] are replaced or removed, a `launch.sh` of the player stays.
+
+use crate::icons::{DEFAULT_ICON, icon_path};
+use crate::instance::{Instance, is_env_name};
+use crate::launcher_settings::ShortcutSettings;
+use crate::server_config::MOD_PATHS as SERVER_MOD_PATHS;
+use crate::settings::MOD_PATHS;
+use log::{debug, warn};
+use std::path::{Path, PathBuf};
+use std::{env, fs, io};
+
+const DESKTOP_PREFIX: &str = "rustic-vs-";
+const DEFAULT_ICON_NAME: &str = "rustic-vs.png";
+/// Written into the instance folder.
+pub const LAUNCH_SCRIPT: &str = "launch.sh";
+/// Second line of generated launch scripts, right after the shebang.
+pub const SCRIPT_MARKER: &str = "# Generated by Rustic, changes are lost when it is regenerated.";
+
+/// Sets the mod paths with jq or python, whichever is installed. Arguments are the settings
+/// file, the dotted key and the instance mods folder.
+const SET_MOD_PATHS: &str = r#"set_mod_paths() {
+    [ -f "$1" ] || return 0
+    if command -v jq > /dev/null; then
+        jq --arg key "$2" --arg mods "$3" \
+            'setpath($key | split("."); ["Mods", $mods])' "$1" > "$1.tmp"
+        mv "$1.tmp" "$1"
+    elif command -v python3 > /dev/null; then
+        python3 - "$@" << 'PYTHON'
+import json, sys
+path, key, mods = sys.argv[1:]
+with open(path) as f:
+    settings = json.load(f)
+*parents, name = key.split(".")
+value = settings
+for parent in parents:
+    value = value.setdefault(parent, {})
+value[name] = ["Mods", mods]
+with open(path, "w") as f:
+    json.dump(settings, f, indent=2)
+PYTHON
+    else
+        echo "jq or python3 is needed to point $1 at the instance mods" >&2
+    fi
+}"#;
+
+/// `$XDG_DATA_HOME`, `None` on other systems than Linux.
+fn data_home() -> Option<PathBuf> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+}
+
+pub fn desktop_entry_path(instance: &Instance) -> Option<PathBuf> {
+    let file_name = format!(
+        "{}{}.desktop",
+        DESKTOP_PREFIX,
+        instance.folder_name_string().replace(' ', "-")
+    );
+    Some(data_home()?.join("applications").join(file_name))
+}
+
+/// Escapes a string value, see the Desktop Entry Specification.
+fn escape_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+        .replace('\r', "\\r")
+}
+
+/// Quotes an argument of the `Exec` key, which is escaped again as string value.
+fn exec_arg(arg: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in arg.chars() {
+        match c {
+            '"' | '`' | '$' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '%' => quoted.push_str("%%"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    escape_value(&quoted)
+}
+
+/// Entry that runs `<exe> launch <folder>` in the Rustic folder, as the CLI needs it as CWD.
+pub fn desktop_entry(instance: &Instance, exe: &Path, root: &Path, icon: &Path) -> String {
+    let comment = if instance.is_server() {
+        "Vintage Story server instance"
+    } else {
+        "Vintage Story instance"
+    };
+    let exec = [
+        exe.to_string_lossy().as_ref(),
+        "launch",
+        &instance.folder_name_string(),
+    ]
+    .map(exec_arg)
+    .join(" ");
+
+    format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name={}\n\
+         Comment={}\n\
+         Exec={}\n\
+         Path={}\n\
+         Icon={}\n\
+         Terminal={}\n\
+         Categories=Game;\n",
+        escape_value(&instance.name),
+        comment,
+        exec,
+        escape_value(&root.to_string_lossy()),
+        escape_value(&icon.to_string_lossy()),
+        // servers need somewhere to show their console
+        instance.is_server(),
+    )
+}
+
+/// Quotes an argument for `sh`.
+fn shell_arg(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+fn shell_path(path: &Path) -> String {
+    shell_arg(&path.to_string_lossy())
+}
+
+/// POSIX shell script doing what [`crate::launcher::launch`] does, except syncing settings
+/// from the global config and backing up saves. Extra arguments are passed to the game.
+/// `None` without a game executable.
+pub fn launch_script(instance: &Instance) -> Option<String> {
+    let game_exe_path = instance.game_exe_path.as_ref()?;
+    let (settings_path, key) = if instance.is_server() {
+        (instance.server_config_path(), SERVER_MOD_PATHS)
+    } else {
+        (instance.settings_path(), MOD_PATHS)
+    };
+
+    let mut script = format!(
+        "#!/bin/sh\n\
+         {}\n\
+         # Starts {} like Rustic does.\n\
+         set -e\n\n",
+        SCRIPT_MARKER,
+        instance.name.replace('\n', " "),
+    );
+    // linked instances keep the mod paths of the install they belong to
+    if !instance.is_linked() {
+        script.push_str(&format!(
+            "{}\n\n\
+             set_mod_paths {} {} {}\n",
+            SET_MOD_PATHS,
+            shell_path(&settings_path),
+            key,
+            shell_path(&instance.mods_path()),
+        ));
+    }
+    for (name, value) in &instance.env {
+        if !is_env_name(name) {
+            warn!("not exporting invalid variable name {:?}", name);
+            continue;
+        }
+        script.push_str(&format!("export {}={}\n", name, shell_arg(value)));
+    }
+    if let Some(install_dir) = instance.install_dir() {
+        script.push_str(&format!("cd {}\n", shell_path(install_dir)));
+    }
+    script.push_str(&format!(
+        "exec {} --dataPath {}",
+        shell_path(game_exe_path),
+        shell_path(&instance.path())
+    ));
+    for mod_path in &instance.add_mod_paths {
+        script.push_str(&format!(" --addModPath {}", shell_path(mod_path)));
+    }
+    script.push_str(" \"$@\"\n");
+
+    Some(script)
+}
+
+pub fn launch_script_path(instance: &Instance) -> PathBuf {
+    instance.path().join(LAUNCH_SCRIPT)
+}
+
+/// Whether the file at `path` is a script written by Rustic.
+fn is_generated(path: &Path) -> bool {
+    fs::read_to_string(path).is_ok_and(|script| script.lines().nth(1) == Some(SCRIPT_MARKER))
+}
+
+/// Writes the script, see [`launch_script`]. It is removed while no game executable is set.
+/// Fails instead of replacing a script Rustic didn't write.
+pub fn write_launch_script(instance: &Instance) -> io::Result<()> {
+    let Some(script) = launch_script(instance) else {
+        return remove_launch_script(instance);
+    };
+
+    let path = launch_script_path(instance);
+    if path.exists() && !is_generated(&path) {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} was not written by Rustic", path.display()),
+        ));
+    }
+    fs::write(&path, script)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+    }
+    debug!("wrote {}", path.display());
+    Ok(())
+}
+
+pub fn remove_launch_script(instance: &Instance) -> io::Result<()> {
+    remove_generated(&launch_script_path(instance))
+}
+
+/// Removes the script at `path` unless Rustic didn't write it.
+fn remove_generated(path: &Path) -> io::Result<()> {
+    if !path.is_file() {
+        return Ok(());
+    }
+    if !is_generated(path) {
+        debug!("keeping {}, it was not written by Rustic", path.display());
+        return Ok(());
+    }
+    fs::remove_file(path)
+}
+
+/// Absolute path of the instance icon, the default icon is copied next to the applications
+/// folder as the GUI only has it embedded.
+fn entry_icon(instance: &Instance, root: &Path) -> io::Result<PathBuf> {
+    if let Some(path) = icon_path(&instance.icon) {
+        return Ok(root.join(path));
+    }
+
+    let path = data_home()
+        .ok_or(io::ErrorKind::NotFound)?
+        .join("icons")
+        .join(DEFAULT_ICON_NAME);
+    if !path.is_file() {
+        fs::create_dir_all(path.parent().expect("icon path has a parent"))?;
+        fs::write(&path, DEFAULT_ICON)?;
+    }
+    Ok(path)
+}
+
+pub fn write_desktop_entry(instance: &Instance) -> io::Result<PathBuf> {
+    let path = desktop_entry_path(instance).ok_or(io::ErrorKind::Unsupported)?;
+    let root = env::current_dir()?;
+    let entry = desktop_entry(
+        instance,
+        &env::current_exe()?,
+        &root,
+        &entry_icon(instance, &root)?,
+    );
+
+    fs::create_dir_all(path.parent().expect("entry path has a parent"))?;
+    fs::write(&path, entry)?;
+    debug!("wrote {}", path.display());
+    Ok(path)
+}
+
+pub fn remove_desktop_entry(instance: &Instance) -> io::Result<()> {
+    match desktop_entry_path(instance) {
+        Some(path) if path.is_file() => fs::remove_file(path),
+        _ => Ok(()),
+    }
+}
+
+/// Writes or removes the launchers of the instance, needed after it is created or saved and
+/// whenever the settings are saved. Desktop entries are skipped where they are not supported.
+pub fn update_shortcuts(settings: &ShortcutSettings, instance: &Instance) -> io::Result<()> {
+    if settings.launch_scripts {
+        write_launch_script(instance)?;
+    } else {
+        remove_launch_script(instance)?;
+    }
+
+    if desktop_entry_path(instance).is_none() {
+        return Ok(());
+    }
+    if settings.desktop_entries {
+        write_desktop_entry(instance).map(|_| ())
+    } else {
+        remove_desktop_entry(instance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn entry_quotes_exec() {
+        let mut instance = Instance::new("Modded 100% \"fun\"");
+        instance.folder_name = "Modded 100%".into();
+
+        let entry = desktop_entry(
+            &instance,
+            Path::new("/opt/rustic vs/rustic-vs"),
+            Path::new("/home/player/rustic"),
+            Path::new("/home/player/rustic/icons/tree.png"),
+        );
+
+        assert_eq!(
+            entry,
+            "[Desktop Entry]\n\
+             Type=Application\n\
+             Name=Modded 100% \"fun\"\n\
+             Comment=Vintage Story instance\n\
+             Exec=\"/opt/rustic vs/rustic-vs\" \"launch\" \"Modded 100%%\"\n\
+             Path=/home/player/rustic\n\
+             Icon=/home/player/rustic/icons/tree.png\n\
+             Terminal=false\n\
+             Categories=Game;\n"
+        );
+    }
+
+    #[test]
+    fn script_quotes_args() {
+        let mut instance = Instance::new("Bob's world");
+        assert_eq!(launch_script(&instance), None);
+
+        instance.game_exe_path = Some("/opt/vintagestory/Vintagestory".into());
+        instance.add_mod_paths = vec!["/home/player/my mod".into()];
+        instance
+            .env
+            .insert("MESA_GL_VERSION_OVERRIDE".into(), "4.5".into());
+        instance.env.insert("X;rm -rf ~".into(), "1".into());
+        let script = launch_script(&instance).unwrap();
+
+        let data_path = shell_path(&instance.path());
+        assert!(data_path.ends_with(r"/instances/Bob'\''s world'"));
+        assert!(script.contains(&format!(
+            "set_mod_paths {} stringListSettings.modPaths {}\n",
+            shell_path(&instance.settings_path()),
+            shell_path(&instance.mods_path())
+        )));
+        assert!(script.starts_with(&format!("#!/bin/sh\n{}\n", SCRIPT_MARKER)));
+        assert!(script.contains("export MESA_GL_VERSION_OVERRIDE='4.5'\ncd '/opt/vintagestory'\n"));
+        assert!(!script.contains("rm -rf"));
+        assert!(script.ends_with(&format!(
+            "exec '/opt/vintagestory/Vintagestory' --dataPath {} \
+             --addModPath '/home/player/my mod' \"$@\"\n",
+            data_path
+        )));
+    }
+
+    #[test]
+    fn keeps_foreign_scripts() {
+        let temp_dir = tempdir().unwrap();
+        let own = temp_dir.path().join("own.sh");
+        let generated = temp_dir.path().join(LAUNCH_SCRIPT);
+        fs::write(&own, "#!/bin/sh\nexec ./Vintagestory\n").unwrap();
+        let mut instance = Instance::new("Scripted");
+        instance.game_exe_path = Some("/opt/vintagestory/Vintagestory".into());
+        fs::write(&generated, launch_script(&instance).unwrap()).unwrap();
+
+        assert!(!is_generated(&own));
+        assert!(is_generated(&generated));
+        remove_generated(&own).unwrap();
+        remove_generated(&generated).unwrap();
+        assert!(own.is_file());
+        assert!(!generated.exists());
+        remove_generated(&generated).unwrap();
+    }
+}
@@ -0,0 +1,379 @@
+use crate::backups::{BackupTrigger, back_up_on};
+use crate::instance::{Instance, MODS_FOLDER};
+use crate::mods::{mod_files, sha256_file};
+use crate::store::store_root;
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{fs, io};
+
+pub const SNAPSHOTS_FOLDER: &str = "ModSnapshots";
+/// Mod files of all snapshots, named after their SHA-256.
+const OBJECTS_FOLDER: &str = "objects";
+/// Older snapshots are removed with the files only they stored.
+pub const KEEP_SNAPSHOTS: usize = 20;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SnapshotFile {
+    pub name: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// State of the `Mods` folder before an action changed it.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Snapshot {
+    #[serde(skip)]
+    pub id: OsString,
+    /// Seconds since the Unix epoch.
+    pub created: u64,
+    pub action: String,
+    #[serde(default)]
+    pub files: Vec<SnapshotFile>,
+}
+
+impl Snapshot {
+    pub fn created_time(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.created)
+    }
+
+    pub fn name(&self) -> String {
+        humantime::format_rfc3339_seconds(self.created_time()).to_string()
+    }
+
+    /// Snapshots of the same second have ids like `<created>-2`, the first one has 1.
+    fn number(&self) -> u32 {
+        self.id
+            .to_str()
+            .and_then(|id| id.rsplit_once('-'))
+            .and_then(|(_, number)| number.parse().ok())
+            .unwrap_or(1)
+    }
+}
+
+fn snapshots_path(data_path: &Path) -> PathBuf {
+    data_path.join(SNAPSHOTS_FOLDER)
+}
+
+/// Stored copy of a mod file from any snapshot of the data folder.
+pub fn stored_object(data_path: &Path, sha256: &str) -> Option<PathBuf> {
+    Some(snapshots_path(data_path).join(OBJECTS_FOLDER).join(sha256)).filter(|path| path.is_file())
+}
+
+/// Call before the `Mods` folder changes: backs up the saves if the instance asks for it
+/// before mod updates, then takes a snapshot.
+pub fn before_mods_change(instance: &Instance, action: &str) -> io::Result<Snapshot> {
+    back_up_on(instance, BackupTrigger::ModUpdate)
+        .map_err(|e| io::Error::new(e.kind(), format!("failed backing up saves: {}", e)))?;
+    take_snapshot(instance, action)
+}
+
+/// Records the `Mods` folder and stores files the snapshots do not have yet, then removes
+/// snapshots beyond [`KEEP_SNAPSHOTS`].
+pub fn take_snapshot(instance: &Instance, action: &str) -> io::Result<Snapshot> {
+    let data_path = instance.path();
+    let snapshot = record(&data_path, store_root().ok().as_deref(), action)?;
+    info!(
+        "snapshot {} of {} before {}",
+        snapshot.id.display(),
+        instance.folder_name_string(),
+        action
+    );
+
+    let removed = prune_snapshots(&data_path, KEEP_SNAPSHOTS)?;
+    if removed > 0 {
+        debug!("removed {} old snapshots", removed);
+    }
+    Ok(snapshot)
+}
+
+/// Files the mod store has are hardlinked from it, see [`store_object`].
+pub(crate) fn record(data_path: &Path, store: Option<&Path>, action: &str) -> io::Result<Snapshot> {
+    let snapshots_path = snapshots_path(data_path);
+    let objects_path = snapshots_path.join(OBJECTS_FOLDER);
+    fs::create_dir_all(&objects_path)?;
+
+    let mut files = Vec::new();
+    for (name, path) in mod_files(&data_path.join(MODS_FOLDER))? {
+        let sha256 = sha256_file(&path)?;
+        let object_path = objects_path.join(&sha256);
+        if !object_path.exists() {
+            debug!("storing {} as {}", name, sha256);
+            store_object(&path, store.map(|store| store.join(&sha256)), &object_path)?;
+        }
+        files.push(SnapshotFile {
+            size: fs::metadata(&path)?.len(),
+            name,
+            sha256,
+        });
+    }
+
+    let created = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let mut id = created.to_string();
+    let mut number = 2;
+    while snapshots_path.join(format!("{}.toml", id)).exists() {
+        id = format!("{}-{}", created, number);
+        number += 1;
+    }
+
+    let snapshot = Snapshot {
+        id: id.clone().into(),
+        created,
+        action: action.to_string(),
+        files,
+    };
+    let toml_data = toml::to_string_pretty(&snapshot).map_err(io::Error::other)?;
+    fs::write(snapshots_path.join(format!("{}.toml", id)), toml_data)?;
+
+    Ok(snapshot)
+}
+
+/// Hardlinks the mod store copy if there is one, a copy is made if that fails, e.g. on
+/// another file system. Snapshots never link to the store, which is cleaned up without
+/// looking at them.
+fn store_object(path: &Path, store_object: Option<PathBuf>, object_path: &Path) -> io::Result<()> {
+    if let Some(store_object) = store_object.filter(|object| object.is_file()) {
+        match fs::hard_link(&store_object, object_path) {
+            Ok(()) => return Ok(()),
+            Err(e) => debug!(
+                "copying instead of linking {}: {}",
+                store_object.display(),
+                e
+            ),
+        }
+    }
+    fs::copy(path, object_path)?;
+    Ok(())
+}
+
+/// All snapshots of the instance, newest first.
+pub fn list_snapshots(instance: &Instance) -> io::Result<Vec<Snapshot>> {
+    read_snapshots(&instance.path())
+}
+
+/// Unreadable snapshot files are logged and skipped.
+fn read_snapshots(data_path: &Path) -> io::Result<Vec<Snapshot>> {
+    let path = snapshots_path(data_path);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut snapshots = Vec::new();
+    for entry in fs::read_dir(path)?.flatten() {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "toml") {
+            continue;
+        }
+        let snapshot = fs::read_to_string(&path)
+            .and_then(|data| toml::from_str::<Snapshot>(&data).map_err(io::Error::other));
+        match snapshot {
+            Ok(mut snapshot) => {
+                snapshot.id = path.file_stem().unwrap_or_default().to_os_string();
+                snapshots.push(snapshot);
+            }
+            Err(e) => error!("skipping snapshot {}: {}", path.display(), e),
+        }
+    }
+    snapshots.sort_by_key(|snapshot| Reverse((snapshot.created, snapshot.number())));
+
+    Ok(snapshots)
+}
+
+/// Removes all but the newest `keep` snapshots and the stored files no remaining snapshot
+/// lists, returns the number of removed snapshots.
+fn prune_snapshots(data_path: &Path, keep: usize) -> io::Result<usize> {
+    let snapshots_path = snapshots_path(data_path);
+    let snapshots = read_snapshots(data_path)?;
+    let (kept, removed) = snapshots.split_at(keep.min(snapshots.len()));
+
+    for snapshot in removed {
+        let mut file_name = snapshot.id.clone();
+        file_name.push(".toml");
+        debug!("removing snapshot {}", snapshot.id.display());
+        fs::remove_file(snapshots_path.join(file_name))?;
+    }
+
+    let referenced: HashSet<&str> = kept
+        .iter()
+        .flat_map(|snapshot| &snapshot.files)
+        .map(|file| file.sha256.as_str())
+        .collect();
+    let objects_path = snapshots_path.join(OBJECTS_FOLDER);
+    if objects_path.exists() {
+        for entry in fs::read_dir(objects_path)?.flatten() {
+            if !referenced.contains(entry.file_name().to_string_lossy().as_ref()) {
+                fs::remove_file(entry.path())?;
+            }
+        }
+    }
+
+    Ok(removed.len())
+}
+
+/// Makes the `Mods` folder match the snapshot exactly, the current state is snapshotted first
+/// so the roll back can be undone, see [`before_mods_change`].
+pub fn roll_back(instance: &Instance, snapshot: &Snapshot) -> io::Result<()> {
+    let data_path = instance.path();
+    check_objects(&data_path, snapshot)?;
+    before_mods_change(instance, &format!("roll back to {}", snapshot.name()))?;
+    restore(&data_path, snapshot)?;
+
+    info!(
+        "rolled back {} to snapshot {}",
+        instance.folder_name_string(),
+        snapshot.id.display()
+    );
+    Ok(())
+}
+
+fn check_objects(data_path: &Path, snapshot: &Snapshot) -> io::Result<()> {
+    let objects_path = snapshots_path(data_path).join(OBJECTS_FOLDER);
+    match snapshot
+        .files
+        .iter()
+        .find(|file| !objects_path.join(&file.sha256).is_file())
+    {
+        Some(file) => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} is missing from the snapshot store", file.name),
+        )),
+        None => Ok(()),
+    }
+}
+
+fn restore(data_path: &Path, snapshot: &Snapshot) -> io::Result<()> {
+    let objects_path = snapshots_path(data_path).join(OBJECTS_FOLDER);
+    let mods_path = data_path.join(MODS_FOLDER);
+    fs::create_dir_all(&mods_path)?;
+    for (name, path) in mod_files(&mods_path)? {
+        let keep = match snapshot.files.iter().find(|file| file.name == name) {
+            Some(file) => sha256_file(&path)? == file.sha256,
+            None => false,
+        };
+        if !keep {
+            debug!("removing {}", name);
+            fs::remove_file(path)?;
+        }
+    }
+
+    for file in &snapshot.files {
+        let path = mods_path.join(&file.name);
+        if !path.exists() {
+            debug!("restoring {}", file.name);
+            fs::copy(objects_path.join(&file.sha256), path)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn mods(data_path: &Path) -> Vec<(String, String)> {
+        mod_files(&data_path.join(MODS_FOLDER))
+            .unwrap()
+            .into_iter()
+            .map(|(name, path)| (name, fs::read_to_string(path).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn snapshot_roundtrip() {
+        let temp_dir = tempdir().unwrap();
+        let data_path = temp_dir.path();
+        let mods_path = data_path.join(MODS_FOLDER);
+        fs::create_dir_all(&mods_path).unwrap();
+        fs::write(mods_path.join("carryon.zip"), "1.9.0").unwrap();
+        fs::write(mods_path.join("expandedfoods.zip"), "1.7.3").unwrap();
+        let before = mods(data_path);
+
+        let snapshot = record(data_path, None, "update").unwrap();
+        fs::remove_file(mods_path.join("carryon.zip")).unwrap();
+        fs::write(mods_path.join("expandedfoods.zip"), "1.7.4").unwrap();
+        fs::write(mods_path.join("extrachests.zip"), "1.1.0").unwrap();
+        fs::write(snapshots_path(data_path).join("broken.toml"), "created = ").unwrap();
+
+        let snapshots = read_snapshots(data_path).unwrap();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].action, "update");
+        check_objects(data_path, &snapshot).unwrap();
+        restore(data_path, &snapshot).unwrap();
+        assert_eq!(mods(data_path), before);
+    }
+
+    #[test]
+    fn prune_keeps_newest() {
+        let temp_dir = tempdir().unwrap();
+        let data_path = temp_dir.path();
+        let mods_path = data_path.join(MODS_FOLDER);
+        fs::create_dir_all(&mods_path).unwrap();
+        fs::write(mods_path.join("carryon.zip"), "1.9.0").unwrap();
+        let oldest = record(data_path, None, "first").unwrap();
+        fs::write(mods_path.join("carryon.zip"), "1.10.0").unwrap();
+        record(data_path, None, "second").unwrap();
+        record(data_path, None, "third").unwrap();
+
+        assert_eq!(prune_snapshots(data_path, 2).unwrap(), 1);
+        let actions: Vec<_> = read_snapshots(data_path)
+            .unwrap()
+            .into_iter()
+            .map(|snapshot| snapshot.action)
+            .collect();
+        assert_eq!(actions, ["third", "second"]);
+        // the only file of the oldest snapshot went with it
+        assert!(check_objects(data_path, &oldest).is_err());
+    }
+
+    #[test]
+    fn same_second_newest_first() {
+        let temp_dir = tempdir().unwrap();
+        let snapshots_path = snapshots_path(temp_dir.path());
+        fs::create_dir_all(&snapshots_path).unwrap();
+        for (id, created) in [("100", 100), ("100-2", 100), ("100-10", 100), ("99", 99)] {
+            let toml_data = format!("created = {}\naction = \"{}\"\n", created, id);
+            fs::write(snapshots_path.join(format!("{}.toml", id)), toml_data).unwrap();
+        }
+
+        let actions: Vec<_> = read_snapshots(temp_dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|snapshot| snapshot.action)
+            .collect();
+        assert_eq!(actions, ["100-10", "100-2", "100", "99"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn links_from_store() {
+        use std::os::unix::fs::MetadataExt;
+
+        let temp_dir = tempdir().unwrap();
+        let (data_path, store) = (temp_dir.path().join("data"), temp_dir.path().join("store"));
+        let mods_path = data_path.join(MODS_FOLDER);
+        fs::create_dir_all(&mods_path).unwrap();
+        fs::create_dir_all(&store).unwrap();
+        fs::write(mods_path.join("carryon.zip"), "1.9.0").unwrap();
+        fs::write(mods_path.join("expandedfoods.zip"), "1.7.3").unwrap();
+        let sha256 = sha256_file(&mods_path.join("carryon.zip")).unwrap();
+        fs::copy(mods_path.join("carryon.zip"), store.join(&sha256)).unwrap();
+
+        let snapshot = record(&data_path, Some(&store), "update").unwrap();
+        let links = |file: &SnapshotFile| {
+            fs::metadata(stored_object(&data_path, &file.sha256).unwrap())
+                .unwrap()
+                .nlink()
+        };
+        assert_eq!(links(&snapshot.files[0]), 2);
+        assert_eq!(links(&snapshot.files[1]), 1);
+    }
+}
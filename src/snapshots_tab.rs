@@ -0,0 +1,145 @@
+use crate::toast::Toast;
+use crate::{Message, style};
+use iced::alignment::{Horizontal, Vertical};
+use iced::widget::{button, column, container, horizontal_space, row, scrollable, text};
+use iced::{Element, Length};
+use log::error;
+use rustic_vs::instance::Instance;
+use rustic_vs::snapshots::{Snapshot, list_snapshots, roll_back, take_snapshot};
+
+#[derive(Debug, Clone)]
+pub enum SnapshotsMessage {
+    Take,
+    RollBack(usize),
+    ConfirmRollBack,
+    CancelRollBack,
+}
+
+/// Snapshots of the `Mods` folder with roll back.
+pub struct SnapshotsTab {
+    snapshots: Result<Vec<Snapshot>, String>,
+    confirm_roll_back: Option<usize>,
+}
+
+impl SnapshotsTab {
+    pub fn load(instance: &Instance) -> SnapshotsTab {
+        Self {
+            snapshots: list_snapshots(instance).map_err(|e| e.to_string()),
+            confirm_roll_back: None,
+        }
+    }
+
+    pub fn reload(&mut self, instance: &Instance) {
+        self.snapshots = list_snapshots(instance).map_err(|e| e.to_string());
+    }
+
+    /// Snapshot to roll back to on [`SnapshotsMessage::ConfirmRollBack`], see [`roll_back_job`].
+    pub fn confirmed_roll_back(&mut self) -> Option<Snapshot> {
+        self.confirm_roll_back
+            .take()
+            .and_then(|index| self.snapshots.as_ref().ok()?.get(index).cloned())
+    }
+
+    /// Returns a toast for finished actions.
+    pub fn update(&mut self, message: SnapshotsMessage, instance: &mut Instance) -> Option<Toast> {
+        let toast = match message {
+            SnapshotsMessage::RollBack(index) => {
+                self.confirm_roll_back = Some(index);
+                return None;
+            }
+            SnapshotsMessage::CancelRollBack => {
+                self.confirm_roll_back = None;
+                return None;
+            }
+            SnapshotsMessage::Take => match take_snapshot(instance, "manual") {
+                Ok(snapshot) => Toast::info("Snapshot taken", snapshot.name()),
+                Err(e) => {
+                    error!("failed taking snapshot: {}", e);
+                    Toast::error("Failed taking snapshot", e)
+                }
+            },
+            // runs in the background, see `confirmed_roll_back`
+            SnapshotsMessage::ConfirmRollBack => return None,
+        };
+
+        self.reload(instance);
+        Some(toast)
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let snapshots: Element<Message> = match &self.snapshots {
+            Err(e) => text(e).style(text::danger).into(),
+            Ok(snapshots) if snapshots.is_empty() => text("no snapshots yet")
+                .height(50)
+                .width(Length::Fill)
+                .align_y(Vertical::Center)
+                .align_x(Horizontal::Center)
+                .into(),
+            Ok(snapshots) => column(snapshots.iter().enumerate().map(|(index, snapshot)| {
+                let action: Element<Message> = if self.confirm_roll_back == Some(index) {
+                    row![
+                        text("Replace current mods?").size(12),
+                        button(text("Roll back").size(12))
+                            .style(button::danger)
+                            .on_press(Message::Snapshots(SnapshotsMessage::ConfirmRollBack)),
+                        button(text("Cancel").size(12))
+                            .style(button::secondary)
+                            .on_press(Message::Snapshots(SnapshotsMessage::CancelRollBack)),
+                    ]
+                    .spacing(5)
+                    .align_y(Vertical::Center)
+                    .into()
+                } else {
+                    button(text("Roll back").size(12))
+                        .style(button::secondary)
+                        .on_press(Message::Snapshots(SnapshotsMessage::RollBack(index)))
+                        .into()
+                };
+
+                container(
+                    row![
+                        column![
+                            text(snapshot.name()),
+                            text(format!(
+                                "{} • {} files",
+                                snapshot.action,
+                                snapshot.files.len()
+                            ))
+                            .size(12),
+                        ]
+                        .spacing(2)
+                        .width(Length::Fill),
+                        action,
+                    ]
+                    .spacing(10)
+                    .align_y(Vertical::Center),
+                )
+                .padding([5, 10])
+                .style(style::striped(index))
+                .into()
+            }))
+            .into(),
+        };
+
+        column![
+            scrollable(column![snapshots].padding(10)).height(Length::Fill),
+            row![
+                horizontal_space(),
+                button("Take snapshot").on_press(Message::Snapshots(SnapshotsMessage::Take)),
+            ]
+            .padding(10),
+        ]
+        .into()
+    }
+}
+
+/// Background job of [`SnapshotsMessage::ConfirmRollBack`], the saves may be backed up first.
+pub fn roll_back_job(instance: &Instance, snapshot: &Snapshot) -> Option<Toast> {
+    Some(match roll_back(instance, snapshot) {
+        Ok(()) => Toast::info("Mods rolled back", snapshot.name()),
+        Err(e) => {
+            error!("failed rolling back to {}: {}", snapshot.id.display(), e);
+            Toast::error("Failed rolling back mods", e)
+        }
+    })
+}
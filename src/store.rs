@@ -0,0 +1,223 @@
+//! Content-addressed store for mod files shared by all instances. Mod files in the instance
+//! `Mods` folders are replaced by links to `store/<sha256>`, keeping their file names so
+//! [`crate::mods::load_mods`] reads them like any other file.
+
+use crate::instance::{Instance, MODS_FOLDER, instance_folders};
+use crate::launcher_settings::StoreSettings;
+use crate::mods::{mod_files, sha256_file};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::{env, fs, io};
+
+pub const STORE_FOLDER: &str = "store";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkMode {
+    /// Needs the store on the same file system as the instances.
+    #[default]
+    Hardlink,
+    Symlink,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StoreReport {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+pub fn store_root() -> io::Result<PathBuf> {
+    Ok(env::current_dir()?.join(STORE_FOLDER))
+}
+
+/// Replaces the file by a link into the store, returns the bytes saved if the store already
+/// had the content.
+fn link_file(store: &Path, path: &Path, mode: LinkMode) -> io::Result<u64> {
+    if fs::symlink_metadata(path)?.is_symlink() && link_target(store, path).is_some() {
+        return Ok(0);
+    }
+
+    let sha256 = sha256_file(path)?;
+    let object = store.join(&sha256);
+    let saved = if object.exists() {
+        if is_same_file(path, &object)? {
+            return Ok(0);
+        }
+        fs::metadata(path)?.len()
+    } else {
+        fs::create_dir_all(store)?;
+        debug!("storing {} as {}", path.display(), sha256);
+        fs::copy(path, &object)?;
+        0
+    };
+
+    let mut link_path = path.as_os_str().to_os_string();
+    link_path.push(".link");
+    let link_path = PathBuf::from(link_path);
+    match mode {
+        LinkMode::Hardlink => fs::hard_link(&object, &link_path)?,
+        LinkMode::Symlink => symlink_file(&object, &link_path)?,
+    }
+    fs::rename(&link_path, path)?;
+
+    Ok(saved)
+}
+
+/// Store object a symlink points to.
+fn link_target(store: &Path, path: &Path) -> Option<String> {
+    let target = fs::read_link(path).ok()?;
+    (target.parent()? == store)
+        .then(|| target.file_name()?.to_str().map(String::from))
+        .flatten()
+}
+
+#[cfg(unix)]
+fn is_same_file(a: &Path, b: &Path) -> io::Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+
+    let (a, b) = (fs::metadata(a)?, fs::metadata(b)?);
+    Ok(a.dev() == b.dev() && a.ino() == b.ino())
+}
+
+#[cfg(not(unix))]
+fn is_same_file(_a: &Path, _b: &Path) -> io::Result<bool> {
+    Ok(false)
+}
+
+#[cfg(unix)]
+fn symlink_file(original: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(original, link)
+}
+
+#[cfg(windows)]
+fn symlink_file(original: &Path, link: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(original, link)
+}
+
+/// Moves all mod files of the instance into the store, the report counts deduplicated files.
+/// Linked instances are skipped, their mods belong to another install.
+pub fn link_instance(instance: &Instance, mode: LinkMode) -> io::Result<StoreReport> {
+    if instance.is_linked() {
+        debug!(
+            "not linking mods of linked {}",
+            instance.folder_name_string()
+        );
+        return Ok(StoreReport::default());
+    }
+    let store = store_root()?;
+
+    let mut report = StoreReport::default();
+    for (_, path) in mod_files(&instance.mods_path())? {
+        let saved = link_file(&store, &path, mode)?;
+        if saved > 0 {
+            report.files += 1;
+            report.bytes += saved;
+        }
+    }
+
+    info!(
+        "linked mods of {} to the store, {} files deduplicated",
+        instance.folder_name_string(),
+        report.files
+    );
+    Ok(report)
+}
+
+/// Links new mod files after they were installed, if the store is enabled.
+pub fn link_if_enabled(settings: &StoreSettings, instance: &Instance) -> io::Result<()> {
+    if settings.enabled {
+        link_instance(instance, settings.link_mode)?;
+    }
+    Ok(())
+}
+
+/// Deletes store objects no instance links to anymore. Every folder in `instances` is
+/// scanned, broken instances still link to their mods.
+pub fn collect_garbage() -> io::Result<StoreReport> {
+    let store = store_root()?;
+    if !store.exists() {
+        return Ok(StoreReport::default());
+    }
+
+    let mut referenced = HashSet::new();
+    for instance_path in instance_folders()? {
+        for (_, path) in mod_files(&instance_path.join(MODS_FOLDER))? {
+            match link_target(&store, &path) {
+                Some(sha256) => referenced.insert(sha256),
+                None => referenced.insert(sha256_file(&path)?),
+            };
+        }
+    }
+
+    let mut report = StoreReport::default();
+    for entry in fs::read_dir(&store)?.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !referenced.contains(&name) {
+            debug!("removing unreferenced {}", name);
+            report.bytes += entry.metadata()?.len();
+            report.files += 1;
+            fs::remove_file(entry.path())?;
+        }
+    }
+
+    info!("removed {} unreferenced files from the store", report.files);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mods::load_mods;
+    use std::io::Write;
+    use tempfile::tempdir;
+    use zip::ZipWriter;
+    use zip::write::SimpleFileOptions;
+
+    fn write_mod(path: &Path, mod_id: &str) {
+        let mut zip = ZipWriter::new(fs::File::create(path).unwrap());
+        zip.start_file("modinfo.json", SimpleFileOptions::default())
+            .unwrap();
+        write!(
+            zip,
+            r#"{{ "modid": "{}", "name": "Test", "version": "1.0.0" }}"#,
+            mod_id
+        )
+        .unwrap();
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn linked_mods_load() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        let (store, a, b) = (root.join("store"), root.join("a"), root.join("b"));
+        fs::create_dir_all(&a).unwrap();
+        fs::create_dir_all(&b).unwrap();
+        write_mod(&a.join("test.zip"), "test");
+        write_mod(&b.join("test.zip"), "test");
+
+        assert_eq!(
+            link_file(&store, &a.join("test.zip"), LinkMode::Hardlink).unwrap(),
+            0
+        );
+        assert!(link_file(&store, &b.join("test.zip"), LinkMode::Symlink).unwrap() > 0);
+        // already linked
+        assert_eq!(
+            link_file(&store, &a.join("test.zip"), LinkMode::Hardlink).unwrap(),
+            0
+        );
+        assert_eq!(
+            link_file(&store, &b.join("test.zip"), LinkMode::Symlink).unwrap(),
+            0
+        );
+
+        assert_eq!(fs::read_dir(&store).unwrap().count(), 1);
+        for folder in [&a, &b] {
+            let mods = load_mods(folder).unwrap();
+            assert_eq!(mods.len(), 1);
+            assert_eq!(mods[0].mod_id, "test");
+        }
+    }
+}
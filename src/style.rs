@@ -1,3 +1,4 @@
+use crate::toast::ToastLevel;
 use iced::widget::{button, container, text_input};
 use iced::{Border, Color, Theme};
 
@@ -62,7 +63,7 @@ pub fn text_input_warning(theme: &Theme, status: text_input::Status) -> text_inp
 }
 
 pub fn striped(index: usize) -> impl Fn(&Theme) -> container::Style {
-    if index % 2 == 0 {
+    if index.is_multiple_of(2) {
         container::transparent
     } else {
         move |theme: &Theme| {
@@ -75,3 +76,65 @@ pub fn striped(index: usize) -> impl Fn(&Theme) -> container::Style {
         }
     }
 }
+
+pub fn badge_danger(theme: &Theme) -> container::Style {
+    let palette = theme.extended_palette();
+
+    container::Style {
+        background: Some(palette.danger.base.color.into()),
+        text_color: Some(palette.danger.base.text),
+        border: Border {
+            radius: 3.into(),
+            ..Border::default()
+        },
+        ..container::Style::default()
+    }
+}
+
+pub fn badge_success(theme: &Theme) -> container::Style {
+    let palette = theme.extended_palette();
+
+    container::Style {
+        background: Some(palette.success.base.color.into()),
+        text_color: Some(palette.success.base.text),
+        border: Border {
+            radius: 3.into(),
+            ..Border::default()
+        },
+        ..container::Style::default()
+    }
+}
+
+pub fn broken_instance(theme: &Theme) -> container::Style {
+    let palette = theme.extended_palette();
+
+    container::Style {
+        border: Border {
+            width: 1.0,
+            radius: 5.into(),
+            color: palette.background.strong.color,
+        },
+        text_color: Some(palette.background.strong.color),
+        ..container::Style::default()
+    }
+}
+
+pub fn toast(level: ToastLevel) -> impl Fn(&Theme) -> container::Style {
+    move |theme: &Theme| {
+        let palette = theme.extended_palette();
+        let accent = match level {
+            ToastLevel::Info => palette.primary.strong.color,
+            ToastLevel::Error => palette.danger.base.color,
+        };
+
+        container::Style {
+            background: Some(palette.background.weak.color.into()),
+            border: Border {
+                width: 1.0,
+                radius: 5.into(),
+                color: accent,
+            },
+            ..container::Style::default()
+        }
+    }
+}
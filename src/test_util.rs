@@ -0,0 +1,14 @@
+//! Fixtures shared by the unit tests.
+
+use crate::mods::ModInfo;
+
+/// Mod named after its id, in a zip named after id and version.
+pub fn mod_info(mod_id: &str, version: &str) -> ModInfo {
+    ModInfo {
+        zip_name: format!("{}_{}.zip", mod_id, version).into(),
+        mod_id: mod_id.to_string(),
+        name: mod_id.to_string(),
+        version: version.to_string(),
+        ..ModInfo::default()
+    }
+}
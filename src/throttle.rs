@@ -0,0 +1,120 @@
+//! Limits for ModDB requests. All clones of a [`crate::api::ModDb`] share one [`Limiter`], so
+//! bulk checks across instances stay within the limits together. Dropping a request future
+//! cancels it and frees its slot, including while it waits for a retry.
+
+use crate::launcher_settings::RequestSettings;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+
+#[derive(Debug, Clone)]
+pub struct Limiter {
+    permits: Arc<Semaphore>,
+    /// Earliest start of the next request.
+    next_slot: Arc<Mutex<Instant>>,
+    interval: Duration,
+}
+
+impl Limiter {
+    pub fn new(settings: &RequestSettings) -> Limiter {
+        Self {
+            permits: Arc::new(Semaphore::new(settings.max_concurrent.max(1))),
+            next_slot: Arc::new(Mutex::new(Instant::now())),
+            interval: Duration::from_secs(1) / settings.per_second.max(1),
+        }
+    }
+
+    /// Waits for a free request slot, the request may run while the permit is held.
+    pub async fn acquire(&self) -> OwnedSemaphorePermit {
+        let permit = self
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("limiter semaphore is never closed");
+
+        let slot = {
+            let mut next_slot = self.next_slot.lock().unwrap_or_else(|e| e.into_inner());
+            let slot = (*next_slot).max(Instant::now());
+            *next_slot = slot + self.interval;
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+
+        permit
+    }
+}
+
+/// Exponential backoff with up to 50% jitter, so throttled clients do not retry in lockstep.
+pub fn backoff(settings: &RequestSettings, attempt: u32) -> Duration {
+    let delay = settings
+        .backoff
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(settings.max_backoff);
+    delay + delay.mul_f64(fastrand::f64() * 0.5)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_grows() {
+        let settings = RequestSettings {
+            backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+            ..RequestSettings::default()
+        };
+
+        for (attempt, min) in [(0, 100), (1, 200), (2, 400), (5, 1000)] {
+            let delay = backoff(&settings, attempt);
+            assert!(delay >= Duration::from_millis(min), "{:?}", delay);
+            assert!(delay <= Duration::from_millis(min * 3 / 2), "{:?}", delay);
+        }
+    }
+
+    #[test]
+    fn cancelled_wait_keeps_no_slot() {
+        let limiter = Limiter::new(&RequestSettings {
+            max_concurrent: 1,
+            per_second: 1000,
+            ..RequestSettings::default()
+        });
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+
+        runtime.block_on(async {
+            let held = limiter.acquire().await;
+            let waiting = tokio::time::timeout(Duration::from_millis(10), limiter.acquire());
+            assert!(waiting.await.is_err());
+
+            drop(held);
+            let next = tokio::time::timeout(Duration::from_secs(1), limiter.acquire());
+            assert!(next.await.is_ok());
+        });
+    }
+
+    #[test]
+    fn rate_limit() {
+        let limiter = Limiter::new(&RequestSettings {
+            per_second: 20,
+            ..RequestSettings::default()
+        });
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+
+        let start = std::time::Instant::now();
+        runtime.block_on(async {
+            for _ in 0..5 {
+                drop(limiter.acquire().await);
+            }
+        });
+        // the first request starts right away
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+}
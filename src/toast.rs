@@ -0,0 +1,40 @@
+use std::time::{Duration, Instant};
+
+const INFO_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToastLevel {
+    Info,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct Toast {
+    pub title: String,
+    pub body: String,
+    pub level: ToastLevel,
+    created: Instant,
+}
+
+impl Toast {
+    pub fn info(title: impl Into<String>, body: impl ToString) -> Toast {
+        Self {
+            title: title.into(),
+            body: body.to_string(),
+            level: ToastLevel::Info,
+            created: Instant::now(),
+        }
+    }
+
+    pub fn error(title: impl Into<String>, error: impl ToString) -> Toast {
+        Self {
+            level: ToastLevel::Error,
+            ..Self::info(title, error)
+        }
+    }
+
+    /// Errors stay until they are closed.
+    pub fn is_expired(&self, now: Instant) -> bool {
+        self.level == ToastLevel::Info && now.duration_since(self.created) > INFO_TIMEOUT
+    }
+}
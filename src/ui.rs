@@ -1,12 +1,16 @@
-use crate::icons::load_icon;
-use crate::instance::Instance;
-use crate::{Message, Rustic, style};
+use crate::toast::Toast;
+use crate::{InstanceTab, Message, Rustic, style};
 use iced::alignment::{Horizontal, Vertical};
 use iced::widget::{
-    Container, Row, button, center, column, container, horizontal_rule, horizontal_space, image,
-    mouse_area, opaque, radio, row, scrollable, stack, text, text_input,
+    Container, Row, Scrollable, button, center, checkbox, column, container, horizontal_rule,
+    horizontal_space, image, mouse_area, opaque, radio, row, scrollable, stack, text, text_input,
 };
-use iced::{Element, Length};
+use iced::{Element, Length, Padding};
+use rustic_vs::icons::{DEFAULT_ICON, icon_path};
+use rustic_vs::instance::{AdoptMode, BrokenInstance, Instance, InstanceKind};
+use rustic_vs::mods::Compatibility;
+use rustic_vs::settings::SyncGroup;
+use rustic_vs::store::LinkMode;
 use std::convert::Into;
 
 pub fn form_text_input<'a, Message>(
@@ -53,6 +57,9 @@ pub fn instance_form<'a>(
     name_on_input: impl Fn(String) -> Message + 'a,
     on_submit: Message,
 ) -> Container<'a, Message> {
+    let is_new = matches!(state.show_modal, Some(crate::Modal::NewInstance));
+    let is_client = state.instance_kind.is_client();
+
     container(column![
         row![text(title).size(20)].padding(10).spacing(10),
         horizontal_rule(1),
@@ -64,6 +71,34 @@ pub fn instance_form<'a>(
                 name_on_input,
                 on_submit.clone()
             ),
+            form_row(
+                "Kind:",
+                row(InstanceKind::ALL.into_iter().map(|kind| {
+                    radio(
+                        kind.to_string(),
+                        kind,
+                        Some(state.instance_kind),
+                        Message::InstanceKindSelected,
+                    )
+                    .into()
+                }))
+                .spacing(20)
+                .into()
+            ),
+            form_row(
+                if is_client { "Game:" } else { "Server:" },
+                text_input(
+                    if is_client {
+                        "<path to game executable>"
+                    } else {
+                        "<path to VintagestoryServer>"
+                    },
+                    &state.game_exe_path
+                )
+                .on_input(Message::GameExePath)
+                .on_submit(on_submit.clone())
+                .into()
+            ),
             form_row(
                 "Icon:",
                 scrollable(
@@ -90,6 +125,31 @@ pub fn instance_form<'a>(
                 .into()
             )
             .height(75),
+        ]
+        .push_maybe(is_client.then(|| {
+            form_row(
+                "Sync:",
+                column(SyncGroup::ALL.into_iter().map(|group| {
+                    checkbox(group.to_string(), group.is_enabled(&state.sync_settings))
+                        .on_toggle(move |enabled| Message::SyncGroupToggled(group, enabled))
+                        .into()
+                }))
+                .spacing(5)
+                .into(),
+            )
+        }))
+        .push_maybe((is_new && is_client).then(|| {
+            form_row(
+                "",
+                checkbox(
+                    "Start from global clientsettings.json",
+                    state.copy_global_settings,
+                )
+                .on_toggle(Message::CopyGlobalSettings)
+                .into(),
+            )
+        }))
+        .push(
             row![
                 horizontal_space(),
                 button(text("OK").align_x(Horizontal::Center))
@@ -101,7 +161,7 @@ pub fn instance_form<'a>(
                     .on_press(Message::HideModal),
             ]
             .spacing(10)
-        ]
+        )
         .padding(10)
         .spacing(10),
     ])
@@ -109,18 +169,207 @@ pub fn instance_form<'a>(
     .style(style::rounded_container)
 }
 
-pub fn instance_row_base(instance: &Instance) -> Row<crate::Message> {
-    row![
-        Element::from(image(load_icon(&instance.icon)).width(48).height(48)),
+pub fn adopt_form(state: &Rustic) -> Container<'_, Message> {
+    let preview: Element<Message> = match &state.adopt_preview {
+        Ok(mods) if mods.is_empty() => text("no mods found").size(12).into(),
+        Ok(mods) => column(mods.iter().map(|mod_info| {
+            text(if mod_info.name.is_empty() {
+                mod_info.zip_name.to_string_lossy().to_string()
+            } else {
+                format!("{} {}", mod_info.name, mod_info.version)
+            })
+            .size(12)
+            .into()
+        }))
+        .spacing(2)
+        .into(),
+        Err(e) => text(e).size(12).style(text::danger).into(),
+    };
+
+    container(column![
+        row![text("Adopt data folder").size(20)]
+            .padding(10)
+            .spacing(10),
+        horizontal_rule(1),
         column![
-            text(instance.name.clone()).size(16),
+            form_text_input(
+                "Name:",
+                "<enter name>",
+                &state.instance_name,
+                Message::InstanceName,
+                Message::AdoptDataFolderSubmit,
+            ),
+            form_text_input(
+                "Folder:",
+                "<path to data folder>",
+                &state.adopt_path,
+                Message::AdoptPath,
+                Message::AdoptDataFolderSubmit,
+            ),
+            form_row(
+                "Mode:",
+                row![
+                    radio(
+                        "Copy",
+                        AdoptMode::Copy,
+                        Some(state.adopt_mode),
+                        Message::AdoptModeSelected
+                    ),
+                    radio(
+                        "Link",
+                        AdoptMode::Link,
+                        Some(state.adopt_mode),
+                        Message::AdoptModeSelected
+                    ),
+                ]
+                .spacing(20)
+                .into()
+            ),
+            text(match state.adopt_mode {
+                AdoptMode::Copy => "The folder is copied, the original stays as it is.",
+                AdoptMode::Link =>
+                    "The original folder is changed: Rustic writes instance.toml into it \
+                     and installs mods into its Mods folder.",
+            })
+            .size(12),
+            form_row(
+                "Mods:",
+                scrollable(preview)
+                    .style(|t, s| scrollable::Style {
+                        container: style::rounded_container(t),
+                        ..scrollable::default(t, s)
+                    })
+                    .width(Length::Fill)
+                    .into()
+            )
+            .height(120),
             row![
-                text(format!("{} mods", instance.mods_count())).size(12),
-                text("•").size(12),
-                text(format!("Folder: {}", instance.folder_name_string())).size(12),
+                horizontal_space(),
+                button(text("Adopt").align_x(Horizontal::Center))
+                    .width(90)
+                    .on_press_maybe(
+                        state
+                            .adopt_preview
+                            .is_ok()
+                            .then_some(Message::AdoptDataFolderSubmit)
+                            .filter(|_| !state.adopting)
+                    ),
+                button(text("Cancel").align_x(Horizontal::Center))
+                    .width(90)
+                    .style(button::secondary)
+                    .on_press(Message::HideModal),
             ]
-            .spacing(5)
-            .align_y(Vertical::Center)
+            .spacing(10)
+        ]
+        .padding(10)
+        .spacing(10),
+    ])
+    .width(400)
+    .style(style::rounded_container)
+}
+
+pub fn launcher_settings_form(state: &Rustic) -> Container<'_, Message> {
+    let store = &state.launcher_settings_form.mod_store;
+    let store_action = |label, message| {
+        button(text(label).size(12))
+            .style(button::secondary)
+            .on_press(message)
+    };
+
+    container(column![
+        row![text("Launcher settings").size(20)].padding(10),
+        horizontal_rule(1),
+        column![
+            checkbox("Share mod files between instances", store.enabled)
+                .on_toggle(Message::StoreEnabled),
+            form_row(
+                "Links:",
+                row![
+                    radio(
+                        "Hardlinks",
+                        LinkMode::Hardlink,
+                        Some(store.link_mode),
+                        Message::StoreLinkMode
+                    ),
+                    radio(
+                        "Symlinks",
+                        LinkMode::Symlink,
+                        Some(store.link_mode),
+                        Message::StoreLinkMode
+                    ),
+                ]
+                .spacing(20)
+                .into()
+            ),
+            form_row(
+                "Store:",
+                row![
+                    store_action("Link all instances", Message::LinkAllInstances),
+                    store_action("Remove unused files", Message::CollectGarbage),
+                ]
+                .spacing(5)
+                .into()
+            ),
+            horizontal_rule(1),
+            checkbox(
+                "Cache ModDB pages and downloads for offline use",
+                state.launcher_settings_form.cache.enabled
+            )
+            .on_toggle(Message::CacheEnabled),
+            form_row(
+                "Cache:",
+                store_action("Clear cache", Message::ClearCache).into()
+            ),
+            horizontal_rule(1),
+            checkbox(
+                "Add instances to the applications menu (Linux)",
+                state.launcher_settings_form.shortcuts.desktop_entries
+            )
+            .on_toggle(Message::DesktopEntries),
+            checkbox(
+                "Write launch.sh into instance folders to start them without Rustic",
+                state.launcher_settings_form.shortcuts.launch_scripts
+            )
+            .on_toggle(Message::LaunchScripts),
+            row![
+                horizontal_space(),
+                button(text("OK").align_x(Horizontal::Center))
+                    .width(90)
+                    .on_press(Message::LauncherSettingsSubmit),
+                button(text("Cancel").align_x(Horizontal::Center))
+                    .width(90)
+                    .style(button::secondary)
+                    .on_press(Message::HideModal),
+            ]
+            .spacing(10)
+        ]
+        .padding(10)
+        .spacing(10),
+    ])
+    .width(400)
+    .style(style::rounded_container)
+}
+
+pub fn instance_row_base(instance: &Instance) -> Row<'_, Message> {
+    row![
+        Element::from(image(icon_handle(&instance.icon)).width(48).height(48)),
+        column![
+            text(instance.name.clone()).size(16),
+            row![]
+                .push_maybe(instance.is_server().then(|| text("Server •").size(12)))
+                .push(text(format!("{} mods", instance.mods_count())).size(12))
+                .push(text("•").size(12))
+                .push_maybe(instance.game_exe_path.as_ref().map(|_| {
+                    text(match &instance.game_version {
+                        Some(version) => format!("Game: {}", version),
+                        None => "Game: unknown".to_string(),
+                    })
+                    .size(12)
+                }))
+                .push_maybe(instance.game_exe_path.as_ref().map(|_| text("•").size(12)))
+                .push(text(format!("Folder: {}", instance.folder_name_string())).size(12))
+                .spacing(5)
+                .align_y(Vertical::Center)
         ]
         .spacing(5),
     ]
@@ -128,69 +377,325 @@ pub fn instance_row_base(instance: &Instance) -> Row<crate::Message> {
     .align_y(Vertical::Center)
 }
 
-pub fn instance_view(state: &Rustic) -> Container<crate::Message> {
-    let index = state.selected_index.expect("modal open without selection");
-    let instance = &state.instances[index];
+pub fn broken_instance_row(broken: &BrokenInstance, index: usize) -> Container<'_, Message> {
+    let action = |label| button(text(label).size(12)).style(button::secondary);
 
-    container(column![
-        instance_row_base(instance)
-            .push(horizontal_space())
-            .push(
-                button("Edit")
-                    .style(button::secondary)
-                    .on_press(crate::Message::EditInstance(index))
-            )
-            .push(
-                button("X")
-                    .style(button::secondary)
-                    .on_press(crate::Message::HideModal)
-            )
-            .padding(10),
-        horizontal_rule(1),
-        scrollable(
-            column(
+    container(
+        row![
+            image(icon_handle(&None)).width(48).height(48).opacity(0.5),
+            column![
+                text(broken.folder_name_string()).size(16),
+                text(format!("Failed loading: {}", broken.error))
+                    .size(12)
+                    .style(text::danger),
+                row![
+                    action("Recreate instance.toml").on_press_maybe(
+                        broken
+                            .can_recreate()
+                            .then_some(Message::RecreateInstance(index))
+                    ),
+                    action("Open file").on_press_maybe(
+                        broken
+                            .has_toml
+                            .then(|| Message::OpenPath(broken.toml_path()))
+                    ),
+                    action("Open folder").on_press(Message::OpenPath(broken.path())),
+                    action("Adopt folder").on_press_maybe(
+                        broken.is_data_folder.then_some(Message::AdoptFolder(index))
+                    ),
+                ]
+                .spacing(5),
+            ]
+            .spacing(5),
+        ]
+        .spacing(10)
+        .align_y(Vertical::Center),
+    )
+    .style(style::broken_instance)
+    .width(Length::Fill)
+    .padding(Padding::from(5).right(10))
+}
+
+/// `None` without a selected instance, e.g. when it was removed while the modal was open.
+pub fn instance_view(state: &Rustic) -> Option<Container<'_, Message>> {
+    let index = state.selected_index?;
+    let instance = state.instances.get(index)?;
+
+    let tab_button = |label, tab| {
+        button(label)
+            .style(if state.instance_tab == tab {
+                button::primary
+            } else {
+                button::text
+            })
+            .on_press(Message::SelectTab(tab))
+    };
+
+    let content: Element<Message> = match state.instance_tab {
+        InstanceTab::Mods => match (
+            &state.mod_detail,
+            &state.server_sync,
+            &state.mod_config_editor,
+        ) {
+            (Some(detail), _, _) => detail.view(&state.instances, state.theme().palette()),
+            (None, Some(sync), _) => sync.view(&state.instances),
+            (None, None, Some(editor)) => editor.view(),
+            (None, None, None) => column![
+                mods_list(instance).height(Length::Fill),
+                row![]
+                    .push_maybe((!instance.is_server()).then(|| {
+                        button("Sync with server")
+                            .style(button::secondary)
+                            .on_press(Message::OpenServerSync(index))
+                    }))
+                    .push(horizontal_space())
+                    .push(button("Lock").on_press(Message::LockMods(index)))
+                    .push(
+                        button("Verify")
+                            .style(button::secondary)
+                            .on_press(Message::VerifyLock(index)),
+                    )
+                    .push(
+                        button("Sync")
+                            .style(button::secondary)
+                            .on_press(Message::SyncLock(index)),
+                    )
+                    .spacing(10)
+                    .padding(10),
+            ]
+            .into(),
+        },
+        InstanceTab::Settings if instance.is_server() => match &state.server_config_editor {
+            Some(Ok(editor)) => editor.view(),
+            Some(Err(e)) => center(text(e)).height(100).into(),
+            None => horizontal_space().into(),
+        },
+        InstanceTab::Settings => match &state.settings_editor {
+            Some(Ok(editor)) => editor.view(),
+            Some(Err(e)) => center(text(e)).height(100).into(),
+            None => horizontal_space().into(),
+        },
+        InstanceTab::Worlds => match &state.worlds_tab {
+            Some(tab) => tab.view(),
+            None => horizontal_space().into(),
+        },
+        InstanceTab::Backups => match &state.backups_tab {
+            Some(tab) => tab.view(instance),
+            None => horizontal_space().into(),
+        },
+        InstanceTab::Snapshots => match &state.snapshots_tab {
+            Some(tab) => tab.view(),
+            None => horizontal_space().into(),
+        },
+        InstanceTab::Console => {
+            let folder = instance.folder_name_string();
+            match state.consoles.get(&folder) {
+                Some(console) => console.view(folder),
+                None => center(
+                    column![
+                        text("The server is not running"),
+                        button("Start").on_press(Message::Launch(index)),
+                    ]
+                    .spacing(10)
+                    .align_x(Horizontal::Center),
+                )
+                .height(100)
+                .into(),
+            }
+        }
+    };
+
+    Some(
+        container(column![
+            instance_row_base(instance)
+                .push(horizontal_space())
+                .push(
+                    button("Edit")
+                        .style(button::secondary)
+                        .on_press(Message::EditInstance(index))
+                )
+                .push(
+                    button("X")
+                        .style(button::secondary)
+                        .on_press(Message::HideModal)
+                )
+                .padding(10),
+            row![
+                tab_button("Mods", InstanceTab::Mods),
+                tab_button("Settings", InstanceTab::Settings),
+                tab_button("Worlds", InstanceTab::Worlds),
+                tab_button("Backups", InstanceTab::Backups),
+                tab_button("Snapshots", InstanceTab::Snapshots),
+            ]
+            .push_maybe(
                 instance
-                    .mods
-                    .iter()
-                    .enumerate()
-                    .map(|(index, mod_info)| {
-                        container(if mod_info.name.is_empty() {
-                            row![
-                                text(mod_info.zip_name.to_string_lossy()).width(Length::Fill),
-                                text("<parse error>").size(12),
-                            ]
-                            .spacing(10)
-                            .align_y(Vertical::Top)
-                        } else {
-                            row![
-                                text(&mod_info.name).width(Length::FillPortion(1)),
-                                text(&mod_info.version).width(60),
-                                text(norm_str(&mod_info.description)).width(Length::FillPortion(2)),
+                    .is_server()
+                    .then(|| tab_button("Console", InstanceTab::Console))
+            )
+            .spacing(5)
+            .padding([0, 10]),
+            horizontal_rule(1),
+            content,
+        ])
+        .width(Length::Fill)
+        .style(style::rounded_container),
+    )
+}
+
+pub fn mods_list(instance: &Instance) -> Scrollable<'_, Message> {
+    scrollable(
+        column(
+            instance
+                .mods
+                .iter()
+                .enumerate()
+                .map(|(index, mod_info)| {
+                    let compatibility = instance
+                        .game_version
+                        .as_ref()
+                        .map(|game_version| mod_info.compatibility(game_version));
+
+                    container(if mod_info.name.is_empty() {
+                        row![
+                            text(mod_info.zip_name.to_string_lossy()).width(Length::Fill),
+                            text("<parse error>").size(12),
+                        ]
+                        .spacing(10)
+                        .align_y(Vertical::Top)
+                    } else {
+                        row![
+                            column![
+                                button(text(&mod_info.name))
+                                    .style(button::text)
+                                    .padding(0)
+                                    .on_press(Message::OpenModDetail(index))
                             ]
-                            .spacing(10)
-                            .align_y(Vertical::Top)
-                        })
-                        .padding([5, 10])
-                        .style(style::striped(index))
+                            .push_maybe(compatibility.and_then(incompatible_badge))
+                            .push_maybe((!instance.loads_side(mod_info.side)).then(|| {
+                                container(text("client only, not loaded").size(12))
+                                    .padding([1, 5])
+                                    .style(style::badge_danger)
+                            }))
+                            .spacing(5)
+                            .width(Length::FillPortion(1)),
+                            text(&mod_info.version).width(60),
+                            text(norm_str(&mod_info.description)).width(Length::FillPortion(2)),
+                            button(text("Config").size(12))
+                                .style(button::secondary)
+                                .on_press_maybe(
+                                    mod_info
+                                        .config_path
+                                        .is_some()
+                                        .then_some(Message::OpenModConfig(index))
+                                ),
+                        ]
+                        .spacing(10)
+                        .align_y(Vertical::Top)
                     })
-                    .map(Element::from),
-            )
-            .push_maybe(instance.mods.is_empty().then(|| {
-                text("no mods found")
-                    .height(50)
-                    .width(Length::Fill)
-                    .align_y(Vertical::Center)
-                    .align_x(Horizontal::Center)
-            }))
-            .width(Length::Fill)
-            .padding(10)
+                    .padding([5, 10])
+                    .style(style::striped(index))
+                })
+                .map(Element::from),
         )
-        .spacing(0)
+        .push_maybe(instance.mods.is_empty().then(|| {
+            text("no mods found")
+                .height(50)
+                .width(Length::Fill)
+                .align_y(Vertical::Center)
+                .align_x(Horizontal::Center)
+        }))
+        .width(Length::Fill)
+        .padding(10),
+    )
+    .spacing(0)
+}
+
+pub fn incompatible_badge<'a>(compatibility: Compatibility) -> Option<Container<'a, Message>> {
+    let reason = match compatibility {
+        Compatibility::Compatible => return None,
+        Compatibility::RequiresNewer(version) => format!("incompatible: requires {}", version),
+        Compatibility::OlderMajor(version) => format!("incompatible: made for {}", version),
+    };
+
+    Some(
+        container(text(reason).size(12))
+            .padding([1, 5])
+            .style(style::badge_danger),
+    )
+}
+
+pub fn confirm_launch(instance: &Instance, index: usize) -> Container<'_, Message> {
+    let incompatible_mods = instance.incompatible_mods();
+
+    container(column![
+        row![text(format!("Launch {}?", instance.name)).size(20)].padding(10),
+        horizontal_rule(1),
+        column![
+            text(format!(
+                "{} mods are incompatible with game version {}:",
+                incompatible_mods.len(),
+                instance
+                    .game_version
+                    .as_ref()
+                    .map(ToString::to_string)
+                    .unwrap_or_default()
+            )),
+            scrollable(
+                column(
+                    incompatible_mods
+                        .into_iter()
+                        .map(|mod_info| text(format!("• {} {}", mod_info.name, mod_info.version)))
+                        .map(Element::from)
+                )
+                .spacing(5)
+            )
+            .height(Length::Shrink),
+            row![
+                horizontal_space(),
+                button(text("Launch anyway").align_x(Horizontal::Center))
+                    .style(button::danger)
+                    .on_press(Message::LaunchAnyway(index)),
+                button(text("Cancel").align_x(Horizontal::Center))
+                    .width(90)
+                    .style(button::secondary)
+                    .on_press(Message::HideModal),
+            ]
+            .spacing(10)
+        ]
+        .padding(10)
+        .spacing(10),
     ])
-    .width(Length::Fill)
+    .width(400)
     .style(style::rounded_container)
 }
 
+pub fn toasts(toasts: &[Toast]) -> Element<'_, Message> {
+    container(
+        column(toasts.iter().enumerate().map(|(index, toast)| {
+            container(
+                row![
+                    column![text(&toast.title).size(14), text(&toast.body).size(12)]
+                        .spacing(5)
+                        .width(Length::Fill),
+                    button(text("X").size(12))
+                        .style(button::text)
+                        .on_press(Message::CloseToast(index)),
+                ]
+                .spacing(10),
+            )
+            .padding(10)
+            .width(300)
+            .style(style::toast(toast.level))
+            .into()
+        }))
+        .spacing(10),
+    )
+    .padding(10)
+    .align_right(Length::Fill)
+    .align_bottom(Length::Fill)
+    .into()
+}
+
 // based on https://github.com/iced-rs/iced/blob/master/examples/modal/src/main.rs
 pub fn modal<'a, Message>(
     base: impl Into<Element<'a, Message>>,
@@ -214,6 +719,30 @@ where
     .into()
 }
 
+pub fn icon_handle(name: &Option<String>) -> image::Handle {
+    match icon_path(name) {
+        Some(path) => path.into(),
+        None => image::Handle::from_bytes(DEFAULT_ICON),
+    }
+}
+
 pub fn norm_str(str: &str) -> String {
     str.replace("\t", "    ")
 }
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
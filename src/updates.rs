@@ -0,0 +1,103 @@
+use crate::api::{ApiError, ModDb, Release};
+use crate::game::GameVersion;
+use crate::instance::Instance;
+use crate::mods::{ModInfo, is_newer, replace_mod_file};
+use crate::snapshots::before_mods_change;
+use log::{error, info};
+
+#[derive(Debug, Clone)]
+pub struct ModUpdate {
+    pub mod_info: ModInfo,
+    pub release: Release,
+}
+
+/// Asks ModDB for newer releases of every mod, mods without id or unknown to ModDB are skipped.
+pub async fn check_updates(
+    moddb: &ModDb,
+    mods: &[ModInfo],
+    game_version: Option<&GameVersion>,
+) -> Vec<ModUpdate> {
+    let mut updates = Vec::new();
+
+    for mod_info in mods.iter().filter(|mod_info| !mod_info.mod_id.is_empty()) {
+        match moddb.get_mod(&mod_info.mod_id).await {
+            Ok(Some(mod_db_mod)) => {
+                if let Some(release) = mod_db_mod.latest_release(game_version)
+                    && is_newer(&release.version, &mod_info.version)
+                {
+                    updates.push(ModUpdate {
+                        mod_info: mod_info.clone(),
+                        release: release.clone(),
+                    });
+                }
+            }
+            Ok(None) => info!("{} not found on ModDB", mod_info.mod_id),
+            Err(e) => error!("failed checking {}: {}", mod_info.mod_id, e),
+        }
+    }
+
+    updates
+}
+
+/// Installs the updates one by one, see [`before_mods_change`] for what happens first.
+pub async fn apply_updates(
+    moddb: &ModDb,
+    instance: &Instance,
+    updates: &[ModUpdate],
+) -> Result<(), ApiError> {
+    if updates.is_empty() {
+        return Ok(());
+    }
+    before_mods_change(instance, "update mods").map_err(ApiError::Io)?;
+
+    for update in updates {
+        let data = moddb.download(&update.release).await?;
+        replace_mod_file(
+            &instance.mods_path(),
+            Some(&update.mod_info.zip_name),
+            &update.release.filename,
+            &data,
+        )
+        .map_err(ApiError::Io)?;
+
+        info!(
+            "updated {} {} -> {}",
+            update.mod_info.mod_id, update.mod_info.version, update.release.version
+        );
+    }
+    Ok(())
+}
+
+/// Downloads a release into the instance, replacing the installed version of the mod if there
+/// is one. See [`before_mods_change`] for what happens before the `Mods` folder changes.
+pub async fn install_release(
+    moddb: &ModDb,
+    instance: &Instance,
+    release: &Release,
+) -> Result<(), ApiError> {
+    let data = moddb.download(release).await?;
+
+    let installed = instance.mods.iter().find(|mod_info| {
+        !release.mod_id.is_empty() && mod_info.mod_id.eq_ignore_ascii_case(&release.mod_id)
+    });
+    before_mods_change(
+        instance,
+        &format!("install {} {}", release.mod_id, release.version),
+    )
+    .map_err(ApiError::Io)?;
+    replace_mod_file(
+        &instance.mods_path(),
+        installed.map(|mod_info| mod_info.zip_name.as_os_str()),
+        &release.filename,
+        &data,
+    )
+    .map_err(ApiError::Io)?;
+
+    info!(
+        "installed {} {} into {}",
+        release.mod_id,
+        release.version,
+        instance.folder_name_string()
+    );
+    Ok(())
+}
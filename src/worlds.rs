@@ -0,0 +1,321 @@
+use filenamify::filenamify;
+use log::{debug, info};
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+use std::cmp::Reverse;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use std::{fmt, fs, io};
+
+pub const SAVES_FOLDER: &str = "Saves";
+pub const WORLD_EXTENSION: &str = "vcdbs";
+
+/// Field of `Seed` in the `SaveGame` protobuf.
+const SEED_FIELD: u64 = 7;
+/// Codes and names of the playstyles of the base game. Worlds of modded playstyles show none.
+const PLAYSTYLES: [(&str, &str); 5] = [
+    ("surviveandbuild", "Standard"),
+    ("wildernesssurvival", "Wilderness Survival"),
+    ("exploration", "Exploration"),
+    ("homosapiens", "Homo sapiens"),
+    ("creativebuilding", "Creative Building"),
+];
+
+/// World save in the `Saves` folder of a data folder. Seed and playstyle are read from the
+/// `SaveGame` protobuf in its `gamedata` table where possible, they are `None` otherwise.
+#[derive(Debug, Clone)]
+pub struct World {
+    pub path: PathBuf,
+    pub name: String,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    pub seed: Option<i32>,
+    pub playstyle: Option<&'static str>,
+}
+
+#[derive(Debug)]
+pub enum WorldError {
+    InvalidName,
+    AlreadyExists,
+    TrashError(trash::Error),
+    Io(io::Error),
+}
+
+impl fmt::Display for WorldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorldError::InvalidName => write!(f, "invalid world name"),
+            WorldError::AlreadyExists => write!(f, "a world with that name already exists"),
+            WorldError::TrashError(e) => write!(f, "failed moving to trash: {}", e),
+            WorldError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for WorldError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            WorldError::InvalidName | WorldError::AlreadyExists => None,
+            WorldError::TrashError(e) => Some(e),
+            WorldError::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for WorldError {
+    fn from(e: io::Error) -> Self {
+        WorldError::Io(e)
+    }
+}
+
+impl World {
+    fn load(path: PathBuf) -> io::Result<World> {
+        let metadata = fs::metadata(&path)?;
+        let save_game = read_save_game(&path)
+            .unwrap_or_else(|e| {
+                debug!("failed reading {}: {}", path.display(), e);
+                None
+            })
+            .unwrap_or_default();
+        let fields = top_level_fields(&save_game);
+
+        Ok(Self {
+            name: path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+            seed: fields.iter().find_map(|(number, value)| match value {
+                // int32 is sign extended to 64 bits, the lower half is the value
+                FieldValue::Varint(seed) if *number == SEED_FIELD => Some(*seed as i32),
+                _ => None,
+            }),
+            playstyle: fields.iter().find_map(|(_, value)| match value {
+                FieldValue::Bytes(code) => PLAYSTYLES
+                    .iter()
+                    .find(|(known, _)| known.as_bytes() == *code)
+                    .map(|(_, name)| *name),
+                FieldValue::Varint(_) => None,
+            }),
+            path,
+        })
+    }
+
+    fn sibling_path(&self, name: &str) -> Result<PathBuf, WorldError> {
+        let name = filenamify(name.trim());
+        if name.is_empty() {
+            return Err(WorldError::InvalidName);
+        }
+
+        Ok(self
+            .path
+            .with_file_name(format!("{}.{}", name, WORLD_EXTENSION)))
+    }
+
+    pub fn rename(&self, name: &str) -> Result<World, WorldError> {
+        let path = self.sibling_path(name)?;
+        if path == self.path {
+            return Ok(self.clone());
+        }
+        if path.exists() {
+            return Err(WorldError::AlreadyExists);
+        }
+
+        info!("renaming {} to {}", self.path.display(), path.display());
+        fs::rename(&self.path, &path)?;
+        Ok(World::load(path)?)
+    }
+
+    /// Copies the world next to itself as "<name> (copy)", numbered if that is taken.
+    pub fn duplicate(&self) -> Result<World, WorldError> {
+        let mut path = self.sibling_path(&format!("{} (copy)", self.name))?;
+        let mut number = 2;
+        while path.exists() {
+            path = self.sibling_path(&format!("{} (copy {})", self.name, number))?;
+            number += 1;
+        }
+
+        info!("copying {} to {}", self.path.display(), path.display());
+        fs::copy(&self.path, &path)?;
+        Ok(World::load(path)?)
+    }
+
+    pub fn trash(&self) -> Result<(), WorldError> {
+        info!("moving {} to trash", self.path.display());
+        trash::delete(&self.path).map_err(WorldError::TrashError)
+    }
+}
+
+/// The serialized `SaveGame`, opened read-only as the game may have the world open.
+fn read_save_game(path: &Path) -> rusqlite::Result<Option<Vec<u8>>> {
+    let connection = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    connection
+        .query_row("SELECT data FROM gamedata LIMIT 1", [], |row| row.get(0))
+        .optional()
+}
+
+#[derive(Debug, PartialEq)]
+enum FieldValue<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+}
+
+fn read_varint(data: &mut &[u8]) -> Option<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = data.split_first()?;
+        *data = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// Varint and length delimited fields of a protobuf message with their field numbers, nested
+/// messages are left as bytes. Stops at the first malformed field or group.
+fn top_level_fields(mut data: &[u8]) -> Vec<(u64, FieldValue<'_>)> {
+    let mut fields = Vec::new();
+    while let Some(key) = read_varint(&mut data) {
+        let number = key >> 3;
+        match key & 7 {
+            0 => match read_varint(&mut data) {
+                Some(value) => fields.push((number, FieldValue::Varint(value))),
+                None => break,
+            },
+            2 => {
+                let Some(len) = read_varint(&mut data)
+                    .and_then(|len| usize::try_from(len).ok())
+                    .filter(|len| *len <= data.len())
+                else {
+                    break;
+                };
+                let (value, rest) = data.split_at(len);
+                fields.push((number, FieldValue::Bytes(value)));
+                data = rest;
+            }
+            1 if data.len() >= 8 => data = &data[8..],
+            5 if data.len() >= 4 => data = &data[4..],
+            _ => break,
+        }
+    }
+    fields
+}
+
+/// All worlds in the `Saves` folder, most recently played first. A missing folder has no
+/// worlds.
+pub fn load_worlds(data_path: &Path) -> io::Result<Vec<World>> {
+    let saves_path = data_path.join(SAVES_FOLDER);
+    if !saves_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut worlds = fs::read_dir(saves_path)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == WORLD_EXTENSION))
+        .map(World::load)
+        .collect::<io::Result<Vec<_>>>()?;
+    worlds.sort_by_key(|world| Reverse(world.modified));
+
+    Ok(worlds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn varint(mut value: u64) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        while value >= 0x80 {
+            bytes.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        bytes.push(value as u8);
+        bytes
+    }
+
+    fn string_field(number: u64, value: &str) -> Vec<u8> {
+        let mut bytes = varint(number << 3 | 2);
+        bytes.extend(varint(value.len() as u64));
+        bytes.extend(value.as_bytes());
+        bytes
+    }
+
+    fn write_world(saves_path: &Path, name: &str, save_game: &[u8]) -> PathBuf {
+        let path = saves_path.join(format!("{}.{}", name, WORLD_EXTENSION));
+        let connection = Connection::open(&path).unwrap();
+        connection
+            .execute_batch("CREATE TABLE gamedata (savegameid integer PRIMARY KEY, data BLOB);")
+            .unwrap();
+        connection
+            .execute("INSERT INTO gamedata VALUES (1, ?1)", [save_game])
+            .unwrap();
+        path
+    }
+
+    #[test]
+    fn reads_seed_and_playstyle() {
+        let temp_dir = tempdir().unwrap();
+        let saves_path = temp_dir.path().join(SAVES_FOLDER);
+        fs::create_dir(&saves_path).unwrap();
+
+        let mut save_game = varint(1 << 3);
+        save_game.extend(varint(1024));
+        save_game.extend(varint(SEED_FIELD << 3));
+        save_game.extend(varint(-123_456_789_i64 as u64));
+        save_game.extend(varint(9 << 3 | 1));
+        save_game.extend([0; 8]);
+        save_game.extend(string_field(14, "New World"));
+        save_game.extend(string_field(22, "wildernesssurvival"));
+        write_world(&saves_path, "seeded", &save_game);
+        fs::write(saves_path.join("broken.vcdbs"), b"not a database").unwrap();
+
+        let worlds = load_worlds(temp_dir.path()).unwrap();
+        let seeded = worlds.iter().find(|world| world.name == "seeded").unwrap();
+        assert_eq!(seeded.seed, Some(-123_456_789));
+        assert_eq!(seeded.playstyle, Some("Wilderness Survival"));
+        let broken = worlds.iter().find(|world| world.name == "broken").unwrap();
+        assert_eq!((broken.seed, broken.playstyle), (None, None));
+    }
+
+    #[test]
+    fn rename_keeps_other_worlds() {
+        let temp_dir = tempdir().unwrap();
+        let saves_path = temp_dir.path().join(SAVES_FOLDER);
+        fs::create_dir(&saves_path).unwrap();
+        let world = World::load(write_world(&saves_path, "first", &[])).unwrap();
+        write_world(&saves_path, "second", &[]);
+
+        assert!(matches!(
+            world.rename(" second "),
+            Err(WorldError::AlreadyExists)
+        ));
+        assert!(matches!(world.rename("  "), Err(WorldError::InvalidName)));
+        assert_eq!(world.rename("first").unwrap().path, world.path);
+
+        let renamed = world.rename("third").unwrap();
+        assert_eq!(renamed.name, "third");
+        assert!(!world.path.exists());
+        assert!(saves_path.join("second.vcdbs").is_file());
+    }
+
+    #[test]
+    fn duplicates_are_numbered() {
+        let temp_dir = tempdir().unwrap();
+        let saves_path = temp_dir.path().join(SAVES_FOLDER);
+        fs::create_dir(&saves_path).unwrap();
+        let world = World::load(write_world(&saves_path, "world", &[])).unwrap();
+
+        let names: Vec<_> = (0..3).map(|_| world.duplicate().unwrap().name).collect();
+        assert_eq!(names, ["world (copy)", "world (copy 2)", "world (copy 3)"]);
+        assert_eq!(load_worlds(temp_dir.path()).unwrap().len(), 4);
+    }
+}
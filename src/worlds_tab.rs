@@ -0,0 +1,185 @@
+use crate::toast::Toast;
+use crate::ui::format_size;
+use crate::{Message, style};
+use iced::alignment::{Horizontal, Vertical};
+use iced::widget::{
+    Column, button, center, column, container, horizontal_space, row, scrollable, text, text_input,
+};
+use iced::{Element, Length};
+use log::error;
+use rustic_vs::worlds::{SAVES_FOLDER, World, WorldError, load_worlds};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone)]
+pub enum WorldsMessage {
+    Rename(usize),
+    Name(String),
+    RenameSubmit,
+    RenameCancel,
+    Duplicate(usize),
+    Trash(usize),
+}
+
+pub struct WorldsTab {
+    data_path: PathBuf,
+    worlds: Result<Vec<World>, String>,
+    renaming: Option<(usize, String)>,
+}
+
+impl WorldsTab {
+    pub fn load(data_path: &Path) -> WorldsTab {
+        Self {
+            data_path: data_path.to_path_buf(),
+            worlds: load_worlds(data_path).map_err(|e| e.to_string()),
+            renaming: None,
+        }
+    }
+
+    pub fn reload(&mut self) {
+        self.worlds = load_worlds(&self.data_path).map_err(|e| e.to_string());
+    }
+
+    fn world(&self, index: usize) -> Option<&World> {
+        self.worlds
+            .as_ref()
+            .ok()
+            .and_then(|worlds| worlds.get(index))
+    }
+
+    /// World to copy in the background, see [`duplicate_job`].
+    pub fn duplicated(&self, index: usize) -> Option<World> {
+        self.world(index).cloned()
+    }
+
+    /// Returns `Some` when a file operation failed. Duplicating is handled by the caller.
+    pub fn update(&mut self, message: WorldsMessage) -> Option<WorldError> {
+        let result = match message {
+            WorldsMessage::Rename(index) => {
+                self.renaming = self.world(index).map(|world| (index, world.name.clone()));
+                return None;
+            }
+            WorldsMessage::Name(name) => {
+                if let Some((_, value)) = &mut self.renaming {
+                    *value = name;
+                }
+                return None;
+            }
+            WorldsMessage::RenameCancel => {
+                self.renaming = None;
+                return None;
+            }
+            WorldsMessage::RenameSubmit => {
+                let (index, name) = self.renaming.take()?;
+                self.world(index)
+                    .map(|world| world.rename(&name).map(|_| ()))
+            }
+            WorldsMessage::Duplicate(_) => return None,
+            WorldsMessage::Trash(index) => self.world(index).map(World::trash),
+        };
+
+        self.reload();
+        result.and_then(Result::err)
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let worlds = match &self.worlds {
+            Ok(worlds) => worlds,
+            Err(e) => return center(text(e).style(text::danger)).height(100).into(),
+        };
+
+        let action = |label, message| {
+            button(text(label).size(12))
+                .style(button::secondary)
+                .on_press(Message::Worlds(message))
+        };
+
+        let rows = worlds.iter().enumerate().map(|(index, world)| {
+            let name: Element<Message> = match &self.renaming {
+                Some((renaming, name)) if *renaming == index => row![
+                    text_input("<world name>", name)
+                        .on_input(|name| Message::Worlds(WorldsMessage::Name(name)))
+                        .on_submit(Message::Worlds(WorldsMessage::RenameSubmit)),
+                    action("OK", WorldsMessage::RenameSubmit),
+                    action("Cancel", WorldsMessage::RenameCancel),
+                ]
+                .spacing(5)
+                .align_y(Vertical::Center)
+                .into(),
+                _ => {
+                    let details: Vec<_> = [
+                        world.seed.map(|seed| format!("Seed {}", seed)),
+                        world.playstyle.map(str::to_string),
+                    ]
+                    .into_iter()
+                    .flatten()
+                    .collect();
+                    column![text(&world.name)]
+                        .push_maybe(
+                            (!details.is_empty())
+                                .then(|| text(details.join(" · ")).size(12).style(text::secondary)),
+                        )
+                        .into()
+                }
+            };
+
+            container(
+                row![
+                    container(name).width(Length::Fill),
+                    text(format_size(world.size)).size(12).width(70),
+                    text(
+                        world
+                            .modified
+                            .map(|modified| humantime::format_rfc3339_seconds(modified).to_string())
+                            .unwrap_or_default()
+                    )
+                    .size(12)
+                    .width(150),
+                    action("Rename", WorldsMessage::Rename(index)),
+                    action("Duplicate", WorldsMessage::Duplicate(index)),
+                    button(text("Trash").size(12))
+                        .style(button::danger)
+                        .on_press(Message::Worlds(WorldsMessage::Trash(index))),
+                ]
+                .spacing(10)
+                .align_y(Vertical::Center),
+            )
+            .padding([5, 10])
+            .style(style::striped(index))
+            .into()
+        });
+
+        column![
+            scrollable(
+                Column::with_children(rows)
+                    .push_maybe(worlds.is_empty().then(|| {
+                        text("no worlds found")
+                            .height(50)
+                            .width(Length::Fill)
+                            .align_y(Vertical::Center)
+                            .align_x(Horizontal::Center)
+                    }))
+                    .padding(10)
+            )
+            .height(Length::Fill),
+            row![
+                horizontal_space(),
+                button("Open folder")
+                    .style(button::secondary)
+                    .on_press(Message::OpenPath(self.data_path.join(SAVES_FOLDER))),
+            ]
+            .padding(10),
+        ]
+        .into()
+    }
+}
+
+/// Copies the world, saves can be large.
+pub fn duplicate_job(world: &World) -> Option<Toast> {
+    Some(match world.duplicate() {
+        Ok(copy) => Toast::info("World duplicated", copy.name),
+        Err(e) => {
+            error!("failed duplicating {}: {}", world.path.display(), e);
+            Toast::error("Failed duplicating world", e)
+        }
+    })
+}